@@ -0,0 +1,105 @@
+#ifndef BITMAP_RS_H
+#define BITMAP_RS_H
+
+/* This file is generated by cbindgen from src/lib.rs - do not edit it by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The call succeeded.
+#define BITMAP_OK 0
+
+// A required pointer argument was null.
+#define BITMAP_ERROR_NULL_POINTER 1
+
+// The bitmap is malformed, or is not a 24bpp bitmap.
+#define BITMAP_ERROR_INVALID_BITMAP 2
+
+// The bitmap uses a feature that is not supported.
+#define BITMAP_ERROR_UNSUPPORTED 3
+
+// The coordinate is outside of the bitmap.
+#define BITMAP_ERROR_OUT_OF_BOUNDS 4
+
+// The buffer is too small to hold the result. The required size is written to the size
+// out-param.
+#define BITMAP_ERROR_BUFFER_TOO_SMALL 5
+
+// An unexpected internal error occurred.
+#define BITMAP_ERROR_INTERNAL 6
+
+// An opaque handle to a decoded 24bpp bitmap, created with [bitmap_parse] and released with
+// [bitmap_free].
+typedef struct BitmapHandle BitmapHandle;
+
+// A 24bpp pixel, in true RGB order (i.e., `red` is the red channel, even though the file stores
+// blue first).
+typedef struct BitmapPixel {
+  uint8_t red;
+  uint8_t green;
+  uint8_t blue;
+} BitmapPixel;
+
+// Parse a 24bpp BMP file from `data` into a new handle, which is written to `out_handle`.
+//
+// The returned handle is owned by the caller and must be released with [bitmap_free].
+//
+// # Safety
+//
+// `data` must point to `length` readable bytes, and `out_handle` must be a valid pointer to
+// write the handle to.
+int32_t bitmap_parse(const uint8_t *data, size_t length, struct BitmapHandle **out_handle);
+
+// Write the width of the bitmap, in pixels, to `out_width`.
+//
+// # Safety
+//
+// `handle` must be a live handle from [bitmap_parse], and `out_width` must be a valid pointer.
+int32_t bitmap_width(const struct BitmapHandle *handle, uint32_t *out_width);
+
+// Write the height of the bitmap, in pixels, to `out_height`.
+//
+// # Safety
+//
+// `handle` must be a live handle from [bitmap_parse], and `out_height` must be a valid pointer.
+int32_t bitmap_height(const struct BitmapHandle *handle, uint32_t *out_height);
+
+// Write the pixel at `(x, y)` to `out_pixel`, where `(0, 0)` is the first pixel in the file (the
+// bottom-left pixel of a bottom-up bitmap).
+//
+// # Safety
+//
+// `handle` must be a live handle from [bitmap_parse], and `out_pixel` must be a valid pointer.
+int32_t bitmap_get_pixel(const struct BitmapHandle *handle,
+                         uint32_t x,
+                         uint32_t y,
+                         struct BitmapPixel *out_pixel);
+
+// Serialize the bitmap as a BMP file into the caller-allocated `buffer` of `capacity` bytes, and
+// write the size of the file to `out_size`.
+//
+// If `buffer` is null or `capacity` is too small, nothing is written to the buffer, the required
+// size is still written to `out_size`, and [BITMAP_ERROR_BUFFER_TOO_SMALL] is returned, so the
+// size can be queried by calling this with a null buffer first.
+//
+// # Safety
+//
+// `handle` must be a live handle from [bitmap_parse], `buffer` must be null or point to
+// `capacity` writable bytes, and `out_size` must be a valid pointer.
+int32_t bitmap_serialize(const struct BitmapHandle *handle,
+                         uint8_t *buffer,
+                         size_t capacity,
+                         size_t *out_size);
+
+// Release a handle created with [bitmap_parse]. Passing null does nothing.
+//
+// # Safety
+//
+// `handle` must be null or a live handle from [bitmap_parse], and must not be used again after
+// this call.
+void bitmap_free(struct BitmapHandle *handle);
+
+#endif  /* BITMAP_RS_H */
@@ -0,0 +1,254 @@
+//! C bindings for bitmap-rs, so that non-Rust tooling (e.g., a C# mod loader) can decode and
+//! encode 24bpp bitmap images.
+//!
+//! The C header is generated with cbindgen and checked in at `include/bitmap_rs.h`.
+//!
+//! ## Conventions
+//!
+//! - Every function that can fail returns one of the `BITMAP_*` status codes, where
+//!   [BITMAP_OK] (zero) indicates success. Results are returned through out-params, which are
+//!   only written on success.
+//! - [bitmap_parse] returns an opaque [BitmapHandle] that is owned by the caller and must be
+//!   released with exactly one call to [bitmap_free]. The handle must not be used after it has
+//!   been freed.
+//! - Buffers passed in are only borrowed for the duration of the call. [bitmap_serialize] writes
+//!   into a buffer allocated (and freed) by the caller, so no memory allocated by this library is
+//!   ever handed to the caller to free, other than the handle itself.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use bitmap_rs::{Bitmap, Pixel24Bit};
+//! use bitmap_rs_ffi::*;
+//! use std::ptr;
+//!
+//! let pixels = vec![Pixel24Bit::from_rgb(1, 2, 3), Pixel24Bit::from_rgb(4, 5, 6)];
+//! let bytes = Bitmap::new_from_pixels(2, 1, pixels).unwrap().to_bytes();
+//!
+//! // The file stores each pixel as blue, green, red...
+//! assert_eq!(bytes[54..60], [3, 2, 1, 6, 5, 4]);
+//!
+//! unsafe {
+//!     let mut handle = ptr::null_mut();
+//!     assert_eq!(bitmap_parse(bytes.as_ptr(), bytes.len(), &mut handle), BITMAP_OK);
+//!
+//!     let mut width = 0;
+//!     assert_eq!(bitmap_width(handle, &mut width), BITMAP_OK);
+//!     assert_eq!(width, 2);
+//!
+//!     let mut pixel = BitmapPixel { red: 0, green: 0, blue: 0 };
+//!     assert_eq!(bitmap_get_pixel(handle, 1, 0, &mut pixel), BITMAP_OK);
+//!     // ...but BitmapPixel holds the channels in RGB order.
+//!     assert_eq!((pixel.red, pixel.green, pixel.blue), (4, 5, 6));
+//!     assert_eq!(bitmap_get_pixel(handle, 2, 0, &mut pixel), BITMAP_ERROR_OUT_OF_BOUNDS);
+//!
+//!     // Query the required size, then serialize into a buffer of that size.
+//!     let mut size = 0;
+//!     assert_eq!(bitmap_serialize(handle, ptr::null_mut(), 0, &mut size), BITMAP_ERROR_BUFFER_TOO_SMALL);
+//!     let mut buffer = vec![0u8; size];
+//!     assert_eq!(bitmap_serialize(handle, buffer.as_mut_ptr(), buffer.len(), &mut size), BITMAP_OK);
+//!     assert_eq!(buffer, bytes);
+//!
+//!     bitmap_free(handle);
+//! }
+//! ```
+
+use bitmap_rs::{Bitmap, Error, Pixel24Bit};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::{ptr, slice};
+
+/// The call succeeded.
+pub const BITMAP_OK: i32 = 0;
+
+/// A required pointer argument was null.
+pub const BITMAP_ERROR_NULL_POINTER: i32 = 1;
+
+/// The bitmap is malformed, or is not a 24bpp bitmap.
+pub const BITMAP_ERROR_INVALID_BITMAP: i32 = 2;
+
+/// The bitmap uses a feature that is not supported.
+pub const BITMAP_ERROR_UNSUPPORTED: i32 = 3;
+
+/// The coordinate is outside of the bitmap.
+pub const BITMAP_ERROR_OUT_OF_BOUNDS: i32 = 4;
+
+/// The buffer is too small to hold the result. The required size is written to the size
+/// out-param.
+pub const BITMAP_ERROR_BUFFER_TOO_SMALL: i32 = 5;
+
+/// An unexpected internal error occurred.
+pub const BITMAP_ERROR_INTERNAL: i32 = 6;
+
+/// An opaque handle to a decoded 24bpp bitmap, created with [bitmap_parse] and released with
+/// [bitmap_free].
+pub struct BitmapHandle(Bitmap<Pixel24Bit>);
+
+/// A 24bpp pixel, in true RGB order (i.e., `red` is the red channel, even though the file stores
+/// blue first).
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct BitmapPixel {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl From<Pixel24Bit> for BitmapPixel {
+    fn from(pixel: Pixel24Bit) -> Self {
+        let [red, green, blue] = pixel.to_rgb();
+        BitmapPixel { red, green, blue }
+    }
+}
+
+/// Get the status code that corresponds to the error.
+fn status_of(error: &Error) -> i32 {
+    match error {
+        Error::Unsupported(_) => BITMAP_ERROR_UNSUPPORTED,
+        Error::IllegalParameter(_) => BITMAP_ERROR_INVALID_BITMAP,
+        Error::CoordinateOutOfBounds(_, _) => BITMAP_ERROR_OUT_OF_BOUNDS,
+        // None of the functions write files, so an I/O error (or any other error) isn't expected.
+        _ => BITMAP_ERROR_INTERNAL,
+    }
+}
+
+/// Run the body, converting any panic into [BITMAP_ERROR_INTERNAL] so that it doesn't unwind
+/// into the caller.
+fn guard(body: impl FnOnce() -> i32) -> i32 {
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or(BITMAP_ERROR_INTERNAL)
+}
+
+/// Parse a 24bpp BMP file from `data` into a new handle, which is written to `out_handle`.
+///
+/// The returned handle is owned by the caller and must be released with [bitmap_free].
+///
+/// # Safety
+///
+/// `data` must point to `length` readable bytes, and `out_handle` must be a valid pointer to
+/// write the handle to.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bitmap_parse(data: *const u8, length: usize, out_handle: *mut *mut BitmapHandle) -> i32 {
+    if data.is_null() || out_handle.is_null() {
+        return BITMAP_ERROR_NULL_POINTER;
+    }
+
+    // SAFETY: the caller guarantees that data points to length readable bytes.
+    let bytes = unsafe { slice::from_raw_parts(data, length) }.to_vec();
+
+    // Malformed input can panic while decoding (e.g., if it is shorter than the headers), so
+    // treat a panic here as an invalid bitmap rather than an internal error.
+    let bitmap = match catch_unwind(|| Bitmap::<Pixel24Bit>::new_from_bytes(bytes)) {
+        Ok(Ok(bitmap)) => bitmap,
+        Ok(Err(err)) => return status_of(&err),
+        Err(_) => return BITMAP_ERROR_INVALID_BITMAP,
+    };
+
+    // SAFETY: the caller guarantees that out_handle is valid for writes.
+    unsafe { *out_handle = Box::into_raw(Box::new(BitmapHandle(bitmap))) };
+    BITMAP_OK
+}
+
+/// Write the width of the bitmap, in pixels, to `out_width`.
+///
+/// # Safety
+///
+/// `handle` must be a live handle from [bitmap_parse], and `out_width` must be a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bitmap_width(handle: *const BitmapHandle, out_width: *mut u32) -> i32 {
+    // SAFETY: the caller guarantees that the pointers are valid, if they are not null.
+    let (Some(handle), Some(out_width)) = (unsafe { handle.as_ref() }, unsafe { out_width.as_mut() }) else {
+        return BITMAP_ERROR_NULL_POINTER;
+    };
+
+    guard(|| {
+        *out_width = handle.0.get_width();
+        BITMAP_OK
+    })
+}
+
+/// Write the height of the bitmap, in pixels, to `out_height`.
+///
+/// # Safety
+///
+/// `handle` must be a live handle from [bitmap_parse], and `out_height` must be a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bitmap_height(handle: *const BitmapHandle, out_height: *mut u32) -> i32 {
+    // SAFETY: the caller guarantees that the pointers are valid, if they are not null.
+    let (Some(handle), Some(out_height)) = (unsafe { handle.as_ref() }, unsafe { out_height.as_mut() }) else {
+        return BITMAP_ERROR_NULL_POINTER;
+    };
+
+    guard(|| {
+        *out_height = handle.0.get_height();
+        BITMAP_OK
+    })
+}
+
+/// Write the pixel at `(x, y)` to `out_pixel`, where `(0, 0)` is the first pixel in the file (the
+/// bottom-left pixel of a bottom-up bitmap).
+///
+/// # Safety
+///
+/// `handle` must be a live handle from [bitmap_parse], and `out_pixel` must be a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bitmap_get_pixel(handle: *const BitmapHandle, x: u32, y: u32, out_pixel: *mut BitmapPixel) -> i32 {
+    // SAFETY: the caller guarantees that the pointers are valid, if they are not null.
+    let (Some(handle), Some(out_pixel)) = (unsafe { handle.as_ref() }, unsafe { out_pixel.as_mut() }) else {
+        return BITMAP_ERROR_NULL_POINTER;
+    };
+
+    guard(|| match handle.0.try_get_pixel_at(x, y) {
+        Ok(pixel) => {
+            *out_pixel = BitmapPixel::from(*pixel);
+            BITMAP_OK
+        },
+        Err(err) => status_of(&err),
+    })
+}
+
+/// Serialize the bitmap as a BMP file into the caller-allocated `buffer` of `capacity` bytes, and
+/// write the size of the file to `out_size`.
+///
+/// If `buffer` is null or `capacity` is too small, nothing is written to the buffer, the required
+/// size is still written to `out_size`, and [BITMAP_ERROR_BUFFER_TOO_SMALL] is returned, so the
+/// size can be queried by calling this with a null buffer first.
+///
+/// # Safety
+///
+/// `handle` must be a live handle from [bitmap_parse], `buffer` must be null or point to
+/// `capacity` writable bytes, and `out_size` must be a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bitmap_serialize(handle: *const BitmapHandle, buffer: *mut u8, capacity: usize, out_size: *mut usize) -> i32 {
+    // SAFETY: the caller guarantees that the pointers are valid, if they are not null.
+    let (Some(handle), Some(out_size)) = (unsafe { handle.as_ref() }, unsafe { out_size.as_mut() }) else {
+        return BITMAP_ERROR_NULL_POINTER;
+    };
+
+    guard(|| {
+        let bytes = handle.0.to_bytes();
+        *out_size = bytes.len();
+
+        if buffer.is_null() || capacity < bytes.len() {
+            return BITMAP_ERROR_BUFFER_TOO_SMALL;
+        }
+
+        // SAFETY: the caller guarantees that buffer points to capacity writable bytes, and
+        // capacity is at least bytes.len().
+        unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), buffer, bytes.len()) };
+        BITMAP_OK
+    })
+}
+
+/// Release a handle created with [bitmap_parse]. Passing null does nothing.
+///
+/// # Safety
+///
+/// `handle` must be null or a live handle from [bitmap_parse], and must not be used again after
+/// this call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bitmap_free(handle: *mut BitmapHandle) {
+    if !handle.is_null() {
+        // SAFETY: the handle was created with Box::into_raw in bitmap_parse, and the caller
+        // guarantees that it hasn't already been freed.
+        drop(unsafe { Box::from_raw(handle) });
+    }
+}
@@ -0,0 +1,61 @@
+//! Benchmark the closest palette color search across palette sizes.
+//!
+//! Run with `cargo bench -p bitmap-rs --bench closest_match`. The throughput is reported in
+//! pixels matched per second.
+
+use bitmap_rs::{Bitmap, Pixel24Bit};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+
+/// The number of pixels matched to the palette in each iteration.
+const PIXEL_COUNT: usize = 64;
+
+/// Create a square palette of `size` colors spread evenly over the RGB cube.
+fn palette(size: u32) -> Bitmap<Pixel24Bit> {
+    let side = size.isqrt();
+    let pixels = (0..size)
+        .map(|i| Pixel24Bit {
+            red: (i * 256 / size) as u8,
+            green: (i.wrapping_mul(97) % 256) as u8,
+            blue: (i.wrapping_mul(193) % 256) as u8,
+        })
+        .collect();
+
+    Bitmap::new_from_pixels(side as i32, side as i32, pixels).unwrap()
+}
+
+/// Create pixels with pseudo-random colors to match against the palette.
+fn pixels(count: usize) -> Vec<Pixel24Bit> {
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    (0..count)
+        .map(|_| {
+            // A linear congruential generator is plenty for generating benchmark data.
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let [red, green, blue, ..] = (state >> 32).to_le_bytes();
+            Pixel24Bit { red, green, blue }
+        })
+        .collect()
+}
+
+fn closest_match(c: &mut Criterion) {
+    let pixels = pixels(PIXEL_COUNT);
+
+    let mut group = c.benchmark_group("find_pixel_by_closest_match");
+    group.throughput(Throughput::Elements(PIXEL_COUNT as u64));
+
+    for size in [256, 4096, 65536] {
+        let palette = palette(size);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &palette, |b, palette| {
+            b.iter(|| {
+                for pixel in &pixels {
+                    black_box(palette.find_pixel_by_closest_match(black_box(pixel)));
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, closest_match);
+criterion_main!(benches);
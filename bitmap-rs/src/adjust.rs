@@ -0,0 +1,275 @@
+use crate::Error::IllegalParameter;
+use crate::{hsv_to_rgb, math, rgb_to_hsv, Bitmap, Error, Pixel24Bit};
+use alloc::vec::Vec;
+
+impl Pixel24Bit {
+    /// Apply the given function to each of the channels of the pixel.
+    fn map_channels(&self, f: impl Fn(u8) -> u8) -> Pixel24Bit {
+        Pixel24Bit { red: f(self.red), green: f(self.green), blue: f(self.blue) }
+    }
+
+    /// Get the perceived brightness of the pixel (using the Rec. 601 weights of the true red, green
+    /// and blue channels, see [Pixel24Bit::to_rgb]), from 0.0 to 255.0.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Pixel24Bit};
+    ///
+    /// assert_eq!(rgb!(255, 255, 255).luminance(), 255.0);
+    /// assert!(Pixel24Bit::from_rgb(0, 255, 0).luminance() > Pixel24Bit::from_rgb(255, 0, 0).luminance());
+    /// assert!(Pixel24Bit::from_rgb(255, 0, 0).luminance() > Pixel24Bit::from_rgb(0, 0, 255).luminance());
+    /// ```
+    pub fn luminance(&self) -> f64 {
+        let [red, green, blue] = self.to_rgb().map(f64::from);
+        0.299 * red + 0.587 * green + 0.114 * blue
+    }
+
+    /// Linearly interpolate from this pixel to `other` by `t` (from 0.0 for this pixel to 1.0 for
+    /// `other`, clamped to that range), with each channel rounded to the nearest value.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::rgb;
+    ///
+    /// let pixel = rgb!(0, 100, 255).blend(&rgb!(255, 100, 0), 0.5);
+    /// assert_eq!((pixel.red, pixel.green, pixel.blue), (128, 100, 128));
+    ///
+    /// assert_eq!(rgb!(0, 0, 0).blend(&rgb!(255, 255, 255), 2.0).red, 255);
+    /// ```
+    pub fn blend(&self, other: &Pixel24Bit, t: f64) -> Pixel24Bit {
+        let t = t.clamp(0.0, 1.0);
+        let blend_channel = |from: u8, to: u8| math::round(f64::from(from) + (f64::from(to) - f64::from(from)) * t) as u8;
+        Pixel24Bit {
+            red: blend_channel(self.red, other.red),
+            green: blend_channel(self.green, other.green),
+            blue: blend_channel(self.blue, other.blue),
+        }
+    }
+}
+
+impl Bitmap<Pixel24Bit> {
+    /// Adjust the brightness of the image by adding the given amount to every channel of every
+    /// pixel, saturating at the minimum and maximum channel values.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{hex, Bitmap, Pixel};
+    ///
+    /// let mut bitmap = Bitmap::new_from_pixels(1, 1, vec![hex!("#4CAF50").unwrap()]).unwrap();
+    /// bitmap.adjust_brightness(100);
+    /// assert_eq!(bitmap.pixels[0].to_bytes(), hex!("#B0FFB4").unwrap().to_bytes());
+    /// ```
+    pub fn adjust_brightness(&mut self, amount: i16) {
+        for pixel in &mut self.pixels {
+            *pixel = pixel.map_channels(|channel| (i16::from(channel) + amount).clamp(0, 255) as u8);
+        }
+    }
+
+    /// Apply gamma correction to the image, where a gamma above 1.0 brightens the midtones and a
+    /// gamma below 1.0 darkens them.
+    ///
+    /// Each channel is mapped to `255 * (channel / 255) ^ (1 / gamma)`.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// let mut bitmap = Bitmap::new_from_pixels(3, 1, vec![rgb!(0, 0, 0), rgb!(64, 64, 64), rgb!(255, 255, 255)]).unwrap();
+    /// bitmap.adjust_gamma(2.2).unwrap();
+    /// assert_eq!(bitmap.pixels.iter().map(|pixel| pixel.red).collect::<Vec<_>>(), vec![0, 136, 255]);
+    ///
+    /// assert!(bitmap.adjust_gamma(0.0).is_err());
+    /// ```
+    pub fn adjust_gamma(&mut self, gamma: f64) -> Result<(), Error> {
+        if !gamma.is_finite() || gamma <= 0.0 {
+            return Err(IllegalParameter("gamma must be a positive number"));
+        }
+
+        // There are only 256 possible channel values, so compute each of them once.
+        let lookup: Vec<u8> = (0..=255u8)
+            .map(|channel| math::round(255.0 * math::powf(f64::from(channel) / 255.0, 1.0 / gamma)) as u8)
+            .collect();
+
+        for pixel in &mut self.pixels {
+            *pixel = pixel.map_channels(|channel| lookup[channel as usize]);
+        }
+
+        Ok(())
+    }
+
+    /// Create a copy of the image with its contrast stretched, by linearly rescaling every
+    /// channel so that the darkest pixel becomes black and the brightest pixel becomes white (by
+    /// luminance).
+    ///
+    /// An image of a single luminance is copied unchanged. See [Bitmap::auto_contrast_clipped] to
+    /// ignore a few outlying pixels.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// // A low-contrast gradient from (100, 100, 100) to (150, 150, 150).
+    /// let pixels = (0..=50).map(|i| rgb!(100 + i, 100 + i, 100 + i)).collect();
+    /// let bitmap = Bitmap::new_from_pixels(51, 1, pixels).unwrap().auto_contrast();
+    ///
+    /// assert_eq!(bitmap.pixels.iter().map(|pixel| pixel.red).min(), Some(0));
+    /// assert_eq!(bitmap.pixels.iter().map(|pixel| pixel.red).max(), Some(255));
+    /// ```
+    pub fn auto_contrast(&self) -> Self {
+        self.stretch_contrast(0.0)
+    }
+
+    /// Create a copy of the image with its contrast stretched (see [Bitmap::auto_contrast]),
+    /// ignoring the `clip` fraction of pixels at each end of the luminance range when finding the
+    /// darkest and brightest pixels (e.g., 0.01 to ignore the darkest and brightest 1%), so that a
+    /// few outliers don't prevent the stretch. Clipped pixels saturate at black or white.
+    ///
+    /// Returns an error if `clip` is not at least 0.0 and less than 0.5.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// // A gradient from (100, 100, 100) to (150, 150, 150), with a black and a white outlier.
+    /// let pixels = [rgb!(0, 0, 0), rgb!(255, 255, 255)].into_iter()
+    ///     .chain((0..=50).map(|i| rgb!(100 + i, 100 + i, 100 + i)))
+    ///     .collect();
+    /// let bitmap = Bitmap::new_from_pixels(53, 1, pixels).unwrap();
+    /// assert_eq!(bitmap.auto_contrast().to_bytes(), bitmap.to_bytes());
+    ///
+    /// let stretched = bitmap.auto_contrast_clipped(0.02).unwrap();
+    /// assert_eq!(stretched.pixels[2].red, 0);
+    /// assert_eq!(stretched.pixels[52].red, 255);
+    ///
+    /// assert!(bitmap.auto_contrast_clipped(0.5).is_err());
+    /// ```
+    pub fn auto_contrast_clipped(&self, clip: f64) -> Result<Self, Error> {
+        if !(0.0..0.5).contains(&clip) {
+            return Err(IllegalParameter("clip must be at least 0.0 and less than 0.5"));
+        }
+
+        Ok(self.stretch_contrast(clip))
+    }
+
+    /// Stretch the contrast of a copy of the image, ignoring the given (valid) `clip` fraction of
+    /// pixels at each end of the luminance range.
+    fn stretch_contrast(&self, clip: f64) -> Self {
+        let mut luminances: Vec<f64> = self.pixels.iter().map(Pixel24Bit::luminance).collect();
+        luminances.sort_unstable_by(f64::total_cmp);
+
+        // Leave an empty image, or one of a single luminance, unchanged.
+        let clipped = (clip * luminances.len() as f64) as usize;
+        let (darkest, scale) = match (luminances.get(clipped), luminances.iter().rev().nth(clipped)) {
+            (Some(&darkest), Some(&brightest)) if brightest > darkest => (darkest, 255.0 / (brightest - darkest)),
+            _ => (0.0, 1.0),
+        };
+
+        let lookup: Vec<u8> = (0..=255u8)
+            .map(|channel| math::round((f64::from(channel) - darkest) * scale).clamp(0.0, 255.0) as u8)
+            .collect();
+
+        self.map_pixels(|_, _, pixel| pixel.map_channels(|channel| lookup[channel as usize]))
+    }
+
+    /// Create a posterized copy of the image, by rounding every channel to the nearest of
+    /// `levels` evenly spaced values from 0 to 255.
+    ///
+    /// Returns an error if there are fewer than 2 levels.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// let pixels = (0..=255).map(|i| rgb!(i, 255 - i, i / 2)).collect();
+    /// let bitmap = Bitmap::new_from_pixels(256, 1, pixels).unwrap();
+    /// let posterized = bitmap.posterize(2).unwrap();
+    ///
+    /// assert!(posterized.pixels.iter().all(|pixel| pixel.to_rgb().iter().all(|&channel| channel == 0 || channel == 255)));
+    /// assert_eq!(posterized.pixels[100].to_rgb(), [0, 255, 0]);
+    ///
+    /// assert!(bitmap.posterize(1).is_err());
+    /// ```
+    pub fn posterize(&self, levels: u8) -> Result<Self, Error> {
+        if levels < 2 {
+            return Err(IllegalParameter("posterizing requires at least 2 levels"));
+        }
+
+        let step = 255.0 / f64::from(levels - 1);
+        let lookup: Vec<u8> = (0..=255u8)
+            .map(|channel| math::round(math::round(f64::from(channel) / step) * step) as u8)
+            .collect();
+
+        Ok(self.map_pixels(|_, _, pixel| pixel.map_channels(|channel| lookup[channel as usize])))
+    }
+
+    /// Create a copy of the image reduced to the 216 colors of the web-safe palette, by rounding
+    /// every channel to the nearest multiple of 51.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// let bitmap = Bitmap::new_from_pixels(1, 1, vec![rgb!(40, 200, 255)]).unwrap();
+    /// assert_eq!(bitmap.to_websafe().pixels[0].to_rgb(), [51, 204, 255]);
+    /// ```
+    pub fn to_websafe(&self) -> Self {
+        self.map_pixels(|_, _, pixel| pixel.map_channels(|channel| ((u16::from(channel) + 25) / 51 * 51) as u8))
+    }
+
+    /// Get a copy of the image with its hue, saturation and value adjusted.
+    ///
+    /// The hue of every pixel is rotated by `hue_shift` (as a fraction of a full turn, so 0.5
+    /// rotates the hue by 180 degrees), and its saturation and value are multiplied by
+    /// `saturation_scale` and `value_scale` respectively (clamped to 1.0). Negative scales are
+    /// treated as 0.0, and a non-finite hue shift or a NaN scale leaves that component unchanged.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{Bitmap, Pixel24Bit};
+    ///
+    /// // Rotating red by 180 degrees gives cyan.
+    /// let bitmap = Bitmap::new_from_pixels(1, 1, vec![Pixel24Bit::from_rgb(255, 0, 0)]).unwrap();
+    /// assert_eq!(bitmap.adjust_hsv(0.5, 1.0, 1.0).pixels[0].to_rgb(), [0, 255, 255]);
+    ///
+    /// // Rotating by 120 degrees turns red (the last byte of the pixel in the file) into green.
+    /// let bitmap = Bitmap::new_from_pixels(1, 1, vec![Pixel24Bit::from([0, 0, 255])]).unwrap();
+    /// assert_eq!(<[u8; 3]>::from(bitmap.adjust_hsv(1.0 / 3.0, 1.0, 1.0).pixels[0]), [0, 255, 0]);
+    ///
+    /// // Desaturating and darkening.
+    /// let adjusted = bitmap.adjust_hsv(0.0, 0.0, 0.5);
+    /// assert_eq!(adjusted.pixels[0].to_rgb(), [128, 128, 128]);
+    /// assert_eq!(bitmap.adjust_hsv(0.0, -1.0, f64::NAN).pixels[0].to_rgb(), [255, 255, 255]);
+    /// ```
+    pub fn adjust_hsv(&self, hue_shift: f64, saturation_scale: f64, value_scale: f64) -> Self {
+        let hue_shift = if hue_shift.is_finite() { hue_shift } else { 0.0 };
+
+        // Every non-zero saturation and value is at least 1/255, so larger scales all saturate.
+        let scale = |scale: f64| if scale.is_nan() { 1.0 } else { scale.clamp(0.0, 255.0) };
+        let (saturation_scale, value_scale) = (scale(saturation_scale), scale(value_scale));
+
+        self.map_pixels(|_, _, pixel| {
+            let (hue, saturation, value) = rgb_to_hsv(pixel);
+
+            // Wrap the hue around to the range [0.0, 1.0).
+            let mut hue = (hue + hue_shift) % 1.0;
+            if hue < 0.0 {
+                hue += 1.0;
+            }
+            if hue >= 1.0 {
+                hue = 0.0;
+            }
+
+            hsv_to_rgb(hue, (saturation * saturation_scale).min(1.0), (value * value_scale).min(1.0))
+                .expect("the hue is wrapped and the saturation and value are clamped into range")
+        })
+    }
+}
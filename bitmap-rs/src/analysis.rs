@@ -0,0 +1,302 @@
+use crate::Error::{CoordinateOutOfBounds, IllegalParameter};
+use crate::{math, Bitmap, Error, Pixel, Pixel24Bit, PixelChannels};
+use alloc::vec::Vec;
+
+/// The size of the square window that [Bitmap::ssim] slides over the images (or the size of the
+/// images, if they are smaller).
+const SSIM_WINDOW_SIZE: usize = 8;
+
+/// The constants that stabilize the division in [Bitmap::ssim] when the means or variances of a
+/// window are close to zero, for 8-bit channels (`(0.01 * 255)^2` and `(0.03 * 255)^2`).
+const SSIM_C1: f64 = 6.5025;
+const SSIM_C2: f64 = 58.5225;
+
+/// Get the mean color of the pixels, rounding each channel to the nearest value, or black if
+/// there are no pixels.
+fn mean_color<'a, P: PixelChannels + 'a>(pixels: impl Iterator<Item = &'a P>) -> P {
+    let mut sums = [0u64; 4];
+    let mut count = 0u64;
+    for pixel in pixels {
+        for (sum, channel) in sums.iter_mut().zip(pixel.channels()) {
+            *sum += u64::from(channel);
+        }
+        count += 1;
+    }
+
+    P::from_channels(sums.map(|sum| (sum + count / 2).checked_div(count).unwrap_or(0) as u16))
+}
+
+impl<P: PixelChannels + core::fmt::Debug> Bitmap<P> {
+    /// Get the mean color of the image, with each channel rounded to the nearest value.
+    ///
+    /// An image with no pixels has a mean color of black.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap, Pixel48Bit};
+    ///
+    /// // Half black and half white.
+    /// let mut pixels = vec![rgb!(0, 0, 0); 8];
+    /// pixels.extend(vec![rgb!(255, 255, 255); 8]);
+    /// let bitmap = Bitmap::new_from_pixels(4, 4, pixels).unwrap();
+    ///
+    /// let average = bitmap.average_color();
+    /// assert_eq!((average.red, average.green, average.blue), (128, 128, 128));
+    ///
+    /// // Wider pixels are averaged at their full precision.
+    /// let pixels = vec![Pixel48Bit { red: 0x1000, green: 0, blue: 0xFFFF }, Pixel48Bit { red: 0x1003, green: 0, blue: 0xFFFF }];
+    /// let average = Bitmap::new_from_pixels(2, 1, pixels).unwrap().average_color();
+    /// assert_eq!((average.red, average.green, average.blue), (0x1002, 0, 0xFFFF));
+    /// ```
+    pub fn average_color(&self) -> P {
+        mean_color(self.pixels.iter())
+    }
+
+    /// Get the mean color of the `width`x`height` region of the image that starts at `(x, y)`,
+    /// with each channel rounded to the nearest value.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// // A black left half and a white right half.
+    /// let pixels = (0..16).map(|i| if i % 4 < 2 { rgb!(0, 0, 0) } else { rgb!(255, 255, 255) }).collect();
+    /// let bitmap = Bitmap::new_from_pixels(4, 4, pixels).unwrap();
+    ///
+    /// assert_eq!(bitmap.average_color_region(2, 0, 2, 4).unwrap().red, 255);
+    /// assert_eq!(bitmap.average_color_region(1, 1, 2, 2).unwrap().red, 128);
+    ///
+    /// assert!(bitmap.average_color_region(3, 3, 2, 1).is_err());
+    /// assert!(bitmap.average_color_region(0, 0, 0, 1).is_err());
+    /// ```
+    pub fn average_color_region(&self, x: u32, y: u32, width: u32, height: u32) -> Result<P, Error> {
+        if width == 0 || height == 0 {
+            return Err(IllegalParameter("region must contain at least one pixel"));
+        }
+
+        // Check the last pixel of the region (avoiding overflow), which also checks the first.
+        let last_x = x.checked_add(width - 1).ok_or(CoordinateOutOfBounds(u32::MAX, y))?;
+        let last_y = y.checked_add(height - 1).ok_or(CoordinateOutOfBounds(x, u32::MAX))?;
+        self.try_get_pixel_at(last_x, last_y)?;
+
+        let image_width = self.get_width() as usize;
+        let (x, y, width, height) = (x as usize, y as usize, width as usize, height as usize);
+        Ok(mean_color(
+            self.pixels.chunks_exact(image_width)
+                .skip(y)
+                .take(height)
+                .flat_map(|row| &row[x..x + width])
+        ))
+    }
+
+}
+
+impl Bitmap<Pixel24Bit> {
+    /// Pair each pixel of the image with the pixel at the same position in the other image,
+    /// accounting for either image being stored top-down (see [Bitmap::is_top_down]).
+    ///
+    /// Returns an error if the images have different dimensions.
+    fn corresponding_pixels<'a>(&'a self, other: &'a Bitmap<Pixel24Bit>) -> Result<Vec<(&'a Pixel24Bit, &'a Pixel24Bit)>, Error> {
+        let (width, height) = (self.get_width(), self.get_height());
+        if (width, height) != (other.get_width(), other.get_height()) {
+            return Err(IllegalParameter("the images must have the same dimensions"));
+        }
+
+        let flipped = self.is_top_down() != other.is_top_down();
+        (0..self.pixels.len())
+            .map(|index| {
+                let (x, y) = self.coords_of(index);
+                let other_y = if flipped { height - 1 - y } else { y };
+                Ok((self.try_get_pixel_at(x, y)?, other.try_get_pixel_at(x, other_y)?))
+            })
+            .collect()
+    }
+
+    /// Get the mean squared error between the channels of the image and the other image, as
+    /// `[red, green, blue]`.
+    fn channel_mse(&self, other: &Bitmap<Pixel24Bit>) -> Result<[f64; 3], Error> {
+        let pixels = self.corresponding_pixels(other)?;
+
+        let mut sums = [0.0; 3];
+        for (pixel, other) in &pixels {
+            for ((sum, channel), other) in sums.iter_mut().zip(pixel.to_rgb()).zip(other.to_rgb()) {
+                let error = f64::from(channel) - f64::from(other);
+                *sum += error * error;
+            }
+        }
+
+        let count = pixels.len().max(1) as f64;
+        Ok(sums.map(|sum| sum / count))
+    }
+
+    /// Get the root-mean-square error between each channel of the image and the other image, as
+    /// `[red, green, blue]`, comparing the pixels at the same positions.
+    ///
+    /// Returns an error if the images have different dimensions.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{Bitmap, Pixel24Bit};
+    ///
+    /// let pixel = Pixel24Bit::from_rgb(10, 20, 30);
+    /// let image = Bitmap::new_from_pixels(2, 2, vec![pixel; 4]).unwrap();
+    /// let copy = Bitmap::new_from_pixels(2, 2, vec![pixel; 4]).unwrap();
+    /// assert_eq!(image.rmse(&copy).unwrap(), [0.0, 0.0, 0.0]);
+    ///
+    /// // Half of the pixels are 8 off in the red channel, which is the last byte of each pixel in
+    /// // the file.
+    /// let red_off = Pixel24Bit::from([30, 20, 18]);
+    /// let off = Bitmap::new_from_pixels(2, 2, vec![red_off, pixel, red_off, pixel]).unwrap();
+    /// let [red, green, blue] = image.rmse(&off).unwrap();
+    /// assert!((red - 32f64.sqrt()).abs() < 1e-9 && green == 0.0 && blue == 0.0);
+    /// assert!((image.rmse_combined(&off).unwrap() - (32.0 / 3f64).sqrt()).abs() < 1e-9);
+    ///
+    /// let smaller = Bitmap::new_from_pixels(1, 2, vec![pixel; 2]).unwrap();
+    /// assert!(image.rmse(&smaller).is_err());
+    /// ```
+    pub fn rmse(&self, other: &Bitmap<Pixel24Bit>) -> Result<[f64; 3], Error> {
+        Ok(self.channel_mse(other)?.map(math::sqrt))
+    }
+
+    /// Get the root-mean-square error between the image and the other image over all of the
+    /// channels together (see [Bitmap::rmse]).
+    ///
+    /// Returns an error if the images have different dimensions.
+    pub fn rmse_combined(&self, other: &Bitmap<Pixel24Bit>) -> Result<f64, Error> {
+        let [red, green, blue] = self.channel_mse(other)?;
+        Ok(math::sqrt((red + green + blue) / 3.0))
+    }
+
+    /// Get the peak signal-to-noise ratio of the image against the other image in decibels, from
+    /// the mean squared error over all of the channels (see [Bitmap::rmse_combined]). Higher is
+    /// closer, and identical images have an infinite ratio.
+    ///
+    /// Returns an error if the images have different dimensions.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// let image = Bitmap::new_from_pixels(2, 2, vec![rgb!(10, 20, 30); 4]).unwrap();
+    /// let copy = Bitmap::new_from_pixels(2, 2, vec![rgb!(10, 20, 30); 4]).unwrap();
+    /// assert_eq!(image.psnr(&copy).unwrap(), f64::INFINITY);
+    ///
+    /// // Every channel of every pixel is 1 off, for a mean squared error of 1.
+    /// let perturbed = Bitmap::new_from_pixels(2, 2, vec![rgb!(11, 21, 31); 4]).unwrap();
+    /// let psnr = image.psnr(&perturbed).unwrap();
+    /// assert!((psnr - 48.13).abs() < 0.01, "{psnr}");
+    /// ```
+    pub fn psnr(&self, other: &Bitmap<Pixel24Bit>) -> Result<f64, Error> {
+        let [red, green, blue] = self.channel_mse(other)?;
+        let mse = (red + green + blue) / 3.0;
+        if mse == 0.0 {
+            return Ok(f64::INFINITY);
+        }
+
+        Ok(10.0 * math::log10(255.0 * 255.0 / mse))
+    }
+
+    /// Get the structural similarity index (SSIM) of the image against the other image, which
+    /// compares the local brightness, contrast and structure of their grayscale versions (see
+    /// [Pixel24Bit::luminance]), and so reflects perceived similarity better than
+    /// [Bitmap::rmse] or [Bitmap::psnr].
+    ///
+    /// The index is the mean over every position of an 8x8 window (or the whole image, if it is
+    /// smaller) slid across the images, from -1.0 (inverted structure) to 1.0 (identical).
+    ///
+    /// Returns an error if the images have different dimensions.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// // A checkerboard, and the same checkerboard with the colors inverted.
+    /// let checkerboard = |dark, light| (0..16 * 16)
+    ///     .map(|i| if (i % 16 + i / 16) % 2 == 0 { dark } else { light })
+    ///     .collect::<Vec<_>>();
+    /// let image = Bitmap::new_from_pixels(16, 16, checkerboard(rgb!(0, 0, 0), rgb!(255, 255, 255))).unwrap();
+    /// let copy = Bitmap::new_from_pixels(16, 16, checkerboard(rgb!(0, 0, 0), rgb!(255, 255, 255))).unwrap();
+    /// let inverted = Bitmap::new_from_pixels(16, 16, checkerboard(rgb!(255, 255, 255), rgb!(0, 0, 0))).unwrap();
+    ///
+    /// assert!((image.ssim(&copy).unwrap() - 1.0).abs() < 1e-9);
+    /// assert!(image.ssim(&inverted).unwrap() < -0.99);
+    /// ```
+    pub fn ssim(&self, other: &Bitmap<Pixel24Bit>) -> Result<f64, Error> {
+        let (gray, other_gray): (Vec<f64>, Vec<f64>) = self.corresponding_pixels(other)?
+            .into_iter()
+            .map(|(pixel, other)| (pixel.luminance(), other.luminance()))
+            .unzip();
+
+        let (width, height) = (self.get_width() as usize, self.get_height() as usize);
+        if gray.is_empty() {
+            return Ok(1.0);
+        }
+
+        let (window_width, window_height) = (SSIM_WINDOW_SIZE.min(width), SSIM_WINDOW_SIZE.min(height));
+        let window_area = (window_width * window_height) as f64;
+
+        let mut total = 0.0;
+        let mut windows = 0;
+        for top in 0..=height - window_height {
+            for left in 0..=width - window_width {
+                let window = || (top..top + window_height)
+                    .flat_map(move |y| (left..left + window_width).map(move |x| y * width + x))
+                    .map(|index| (gray[index], other_gray[index]));
+
+                let (sum, other_sum) = window().fold((0.0, 0.0), |(sum, other_sum), (x, y)| (sum + x, other_sum + y));
+                let (mean, other_mean) = (sum / window_area, other_sum / window_area);
+
+                let (variance, other_variance, covariance) = window().fold((0.0, 0.0, 0.0), |(variance, other_variance, covariance), (x, y)| {
+                    let (dx, dy) = (x - mean, y - other_mean);
+                    (variance + dx * dx, other_variance + dy * dy, covariance + dx * dy)
+                });
+                let (variance, other_variance, covariance) = (variance / window_area, other_variance / window_area, covariance / window_area);
+
+                total += ((2.0 * mean * other_mean + SSIM_C1) * (2.0 * covariance + SSIM_C2))
+                    / ((mean * mean + other_mean * other_mean + SSIM_C1) * (variance + other_variance + SSIM_C2));
+                windows += 1;
+            }
+        }
+
+        Ok(total / f64::from(windows))
+    }
+}
+
+/// Measure how well palette `b` can reproduce the colors of palette `a`, as the mean difference
+/// between each distinct color of `a` and its closest color in `b`.
+///
+/// A lower value means better coverage, with 0.0 meaning that `b` contains every color of `a`.
+/// If `b` has no pixels, no color of `a` can be reproduced, so the result is infinite.
+///
+/// ## Example
+///
+/// ```rust
+/// use bitmap_rs::{palette_coverage, rgb, Bitmap};
+///
+/// let a = Bitmap::new_from_pixels(2, 1, vec![rgb!(0, 0, 0), rgb!(255, 255, 255)]).unwrap();
+/// let b = Bitmap::new_from_pixels(2, 1, vec![rgb!(255, 255, 255), rgb!(0, 0, 0)]).unwrap();
+/// let dark = Bitmap::new_from_pixels(2, 1, vec![rgb!(0, 0, 0), rgb!(32, 32, 32)]).unwrap();
+///
+/// assert_eq!(palette_coverage(&a, &a), 0.0);
+/// assert_eq!(palette_coverage(&a, &b), 0.0);
+///
+/// // The dark colors can't reproduce white, but black and white can approximate both dark colors.
+/// assert!(palette_coverage(&a, &dark) > palette_coverage(&dark, &a));
+/// ```
+pub fn palette_coverage<P: Pixel + Clone + core::fmt::Debug>(a: &Bitmap<P>, b: &Bitmap<P>) -> f64 {
+    let colors = a.unique_palette();
+    if colors.is_empty() {
+        return 0.0;
+    }
+
+    let total: f64 = colors.iter()
+        .map(|(color, _)| b.pixels.iter().map(|other| color.difference(other)).fold(f64::INFINITY, f64::min))
+        .sum();
+
+    total / colors.len() as f64
+}
@@ -1,11 +1,19 @@
 use crate::error::Error;
 use crate::error::Error::IllegalParameter;
 use crate::helpers::array_from_slice;
-use crate::Error::Unsupported;
-use std::iter::repeat_n;
+#[cfg(feature = "std")]
+use crate::helpers::CountingWriter;
+use crate::rle::{decode_rle8, encode_rle8};
+use crate::Error::{CoordinateOutOfBounds, Unsupported};
+use crate::math;
+use alloc::collections::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::iter::repeat_n;
 
 /// The set of supported bitmap type identifiers.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum BitmapIdentifier {
     /// Windows 3.x, 95, NT, etc.,
     BM
@@ -27,7 +35,7 @@ impl BitmapIdentifier {
 }
 
 /// The bitmap file header.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Header {
     /// The identifier that indicates the type of BMP file.
     pub identifier: BitmapIdentifier,
@@ -87,10 +95,91 @@ impl Header {
 }
 
 /// The set of supported bitmap compression methods.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum CompressionMethod {
     /// No compression.
     BiRgb,
+
+    /// Run-length encoding for 8bpp (indexed) bitmaps.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{hex, Bitmap, Pixel, Pixel24Bit, Pixel8Bit};
+    ///
+    /// let color_table = vec![hex!("#FF0000").unwrap(), hex!("#00FF00").unwrap(), hex!("#0000FF").unwrap()];
+    /// let indices = [0, 0, 0, 1, 2, 1, 1, 1];
+    /// let pixels = indices.iter().map(|&index| Pixel8Bit { index }).collect();
+    /// let uncompressed = Bitmap::new_from_indexed_pixels(4, 2, color_table, pixels).unwrap().to_bytes();
+    ///
+    /// // Replace the pixel data with the equivalent RLE8 data.
+    /// let offset = u32::from_le_bytes(uncompressed[10..14].try_into().unwrap()) as usize;
+    /// let mut compressed = uncompressed[..offset].to_vec();
+    /// compressed.extend_from_slice(&[
+    ///     3, 0, 1, 1, 0, 0,          // A run of three 0s, a run of one 1, then end-of-line.
+    ///     0, 3, 2, 1, 1, 0, 1, 1,    // An absolute run of 2, 1, 1 (padded), then a run of one 1.
+    ///     0, 1,                      // End-of-bitmap.
+    /// ]);
+    /// let size = compressed.len() as u32;
+    /// compressed[2..6].copy_from_slice(&size.to_le_bytes());
+    /// compressed[30..34].copy_from_slice(&1u32.to_le_bytes());
+    ///
+    /// let expected = Bitmap::<Pixel8Bit>::new_from_bytes(uncompressed).unwrap();
+    /// let decoded = Bitmap::<Pixel8Bit>::new_from_bytes(compressed.clone()).unwrap();
+    /// assert_eq!(
+    ///     decoded.pixels.iter().map(|pixel| pixel.index).collect::<Vec<_>>(),
+    ///     expected.pixels.iter().map(|pixel| pixel.index).collect::<Vec<_>>(),
+    /// );
+    ///
+    /// // The indices can also be expanded through the color table.
+    /// let expanded = Bitmap::<Pixel24Bit>::new_from_bytes(compressed).unwrap();
+    /// assert_eq!(expanded.pixels[4].to_bytes(), hex!("#0000FF").unwrap().to_bytes());
+    /// ```
+    BiRle8,
+
+    /// No compression, with the color channels of each pixel described by bit masks.
+    ///
+    /// This is only supported for 16bpp bitmaps, where each pixel is expanded to 24bpp by scaling
+    /// each of its masked channels to 8 bits.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{Bitmap, Pixel, Pixel24Bit};
+    ///
+    /// // Build a 2x2 bitmap file with the given DIB header fields and pixel data.
+    /// let bitmap_file = |bits_per_pixel: u16, compression: u32, masks: &[u32], pixel_data: &[u8]| {
+    ///     let offset = 14 + 40 + masks.len() as u32 * 4;
+    ///     let mut bytes = b"BM".to_vec();
+    ///     bytes.extend_from_slice(&(offset + pixel_data.len() as u32).to_le_bytes());
+    ///     bytes.extend_from_slice(&[0, 0, 0, 0]);
+    ///     bytes.extend_from_slice(&offset.to_le_bytes());
+    ///     bytes.extend_from_slice(&40u32.to_le_bytes());
+    ///     bytes.extend_from_slice(&2i32.to_le_bytes());
+    ///     bytes.extend_from_slice(&2i32.to_le_bytes());
+    ///     bytes.extend_from_slice(&1u16.to_le_bytes());
+    ///     bytes.extend_from_slice(&bits_per_pixel.to_le_bytes());
+    ///     bytes.extend_from_slice(&compression.to_le_bytes());
+    ///     bytes.extend_from_slice(&[0; 20]);
+    ///     masks.iter().for_each(|mask| bytes.extend_from_slice(&mask.to_le_bytes()));
+    ///     bytes.extend_from_slice(pixel_data);
+    ///     Bitmap::<Pixel24Bit>::new_from_bytes(bytes).unwrap()
+    /// };
+    ///
+    /// let to_bytes = |pixels: &[u16]| pixels.iter().flat_map(|pixel| pixel.to_le_bytes()).collect::<Vec<u8>>();
+    ///
+    /// // Red, green, blue and white, in 24bpp, RGB565 and RGB555.
+    /// let expected = bitmap_file(24, 0, &[], &[0, 0, 255, 0, 255, 0, 0, 0, 255, 0, 0, 255, 255, 255, 0, 0]);
+    /// let rgb565 = bitmap_file(16, 3, &[0xF800, 0x07E0, 0x001F], &to_bytes(&[0xF800, 0x07E0, 0x001F, 0xFFFF]));
+    /// let rgb555 = bitmap_file(16, 3, &[0x7C00, 0x03E0, 0x001F], &to_bytes(&[0x7C00, 0x03E0, 0x001F, 0x7FFF]));
+    ///
+    /// for bitmap in [rgb565, rgb555] {
+    ///     for (pixel, expected_pixel) in bitmap.pixels.iter().zip(expected.pixels.iter()) {
+    ///         assert_eq!(pixel.to_bytes(), expected_pixel.to_bytes());
+    ///     }
+    /// }
+    /// ```
+    BiBitfields,
 }
 
 impl CompressionMethod {
@@ -99,27 +188,181 @@ impl CompressionMethod {
     /// This can be used directly as the [InformationHeader::compression_method].
     pub fn get_identifier(&self) -> u32 {
         match self {
-            CompressionMethod::BiRgb => 0
+            CompressionMethod::BiRgb => 0,
+            CompressionMethod::BiRle8 => 1,
+            CompressionMethod::BiBitfields => 3,
         }
     }
 
     pub fn from_identifier(identifier: u32) -> Result<Self, Error> {
         match identifier {
             0 => Ok(CompressionMethod::BiRgb),
+            1 => Ok(CompressionMethod::BiRle8),
+            3 => Ok(CompressionMethod::BiBitfields),
             _ => Err(IllegalParameter("unknown identifier"))
         }
     }
 }
 
+/// The color profile fields that were added to the DIB header by `BITMAPV5HEADER`.
+#[derive(Clone, Debug)]
+pub struct ColorProfileHeader {
+    /// The rendering intent of the bitmap.
+    pub intent: u32,
+
+    /// The offset of the color profile data from the start of the DIB header, in bytes.
+    pub profile_data: u32,
+
+    /// The size of the color profile data, in bytes.
+    pub profile_size: u32,
+
+    /// Reserved - set to 0.
+    pub reserved: u32,
+}
+
+/// The color mask and color space fields that were added to the DIB header by
+/// `BITMAPV4HEADER` (and that are also present in `BITMAPV5HEADER`).
+///
+/// See: https://learn.microsoft.com/en-us/windows/win32/api/wingdi/ns-wingdi-bitmapv5header
+///
+/// ## Example
+///
+/// ```rust
+/// use bitmap_rs::InformationHeader;
+///
+/// // A BITMAPV5HEADER for a 2x2 24bpp bitmap, with a different value in each field.
+/// let mut bytes = vec![];
+/// bytes.extend([124u32, 2, 2].iter().flat_map(|field| field.to_le_bytes()));
+/// bytes.extend([1u16, 24].iter().flat_map(|field| field.to_le_bytes()));
+/// bytes.extend([0u32, 16, 2835, 2834, 0, 0].iter().flat_map(|field| field.to_le_bytes()));
+/// bytes.extend([0x00FF_0000u32, 0x0000_FF00, 0x0000_00FF, 0xFF00_0000].iter().flat_map(|mask| mask.to_le_bytes()));
+/// bytes.extend_from_slice(b"BGRs");
+/// bytes.extend((1..=9i32).flat_map(|endpoint| endpoint.to_le_bytes()));
+/// bytes.extend([10u32, 11, 12, 4, 13, 14, 0].iter().flat_map(|field| field.to_le_bytes()));
+/// assert_eq!(bytes.len(), InformationHeader::V5_SIZE);
+///
+/// let header = InformationHeader::new_from_bytes(&bytes).unwrap();
+/// let color_space = header.color_space.as_ref().unwrap();
+/// assert_eq!((color_space.red_mask, color_space.alpha_mask), (0x00FF_0000, 0xFF00_0000));
+/// assert_eq!(&color_space.color_space_type.to_le_bytes(), b"BGRs");
+/// assert_eq!(color_space.endpoints, [1, 2, 3, 4, 5, 6, 7, 8, 9]);
+/// assert_eq!((color_space.gamma_red, color_space.gamma_green, color_space.gamma_blue), (10, 11, 12));
+/// let profile = color_space.profile.as_ref().unwrap();
+/// assert_eq!((profile.intent, profile.profile_data, profile.profile_size), (4, 13, 14));
+/// assert_eq!(header.to_bytes(), bytes);
+///
+/// // A BITMAPV4HEADER has the same fields, without the color profile.
+/// let mut bytes = bytes[..InformationHeader::V4_SIZE].to_vec();
+/// bytes[0..4].copy_from_slice(&(InformationHeader::V4_SIZE as u32).to_le_bytes());
+///
+/// let header = InformationHeader::new_from_bytes(&bytes).unwrap();
+/// let color_space = header.color_space.as_ref().unwrap();
+/// assert_eq!(color_space.endpoints, [1, 2, 3, 4, 5, 6, 7, 8, 9]);
+/// assert_eq!(color_space.gamma_blue, 12);
+/// assert!(color_space.profile.is_none());
+/// assert_eq!(header.to_bytes(), bytes);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ColorSpaceHeader {
+    /// The mask of the red component of each pixel.
+    pub red_mask: u32,
+
+    /// The mask of the green component of each pixel.
+    pub green_mask: u32,
+
+    /// The mask of the blue component of each pixel.
+    pub blue_mask: u32,
+
+    /// The mask of the alpha component of each pixel.
+    pub alpha_mask: u32,
+
+    /// The color space of the bitmap (e.g., `sRGB`).
+    pub color_space_type: u32,
+
+    /// The x, y and z coordinates of the red, green and blue endpoints of the color space.
+    pub endpoints: [i32; 9],
+
+    /// The tone response curve for red.
+    pub gamma_red: u32,
+
+    /// The tone response curve for green.
+    pub gamma_green: u32,
+
+    /// The tone response curve for blue.
+    pub gamma_blue: u32,
+
+    /// The color profile fields, if this is a `BITMAPV5HEADER`.
+    pub profile: Option<ColorProfileHeader>,
+}
+
+impl ColorSpaceHeader {
+    fn new_from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut endpoints = [0i32; 9];
+        for (i, endpoint) in endpoints.iter_mut().enumerate() {
+            let start = 60 + i * 4;
+            *endpoint = i32::from_le_bytes(*array_from_slice(&bytes[start..start + 4])?);
+        }
+
+        let profile = if bytes.len() >= InformationHeader::V5_SIZE {
+            Some(ColorProfileHeader {
+                intent: u32::from_le_bytes(*array_from_slice(&bytes[108..112])?),
+                profile_data: u32::from_le_bytes(*array_from_slice(&bytes[112..116])?),
+                profile_size: u32::from_le_bytes(*array_from_slice(&bytes[116..120])?),
+                reserved: u32::from_le_bytes(*array_from_slice(&bytes[120..124])?),
+            })
+        } else {
+            None
+        };
+
+        Ok(Self {
+            red_mask: u32::from_le_bytes(*array_from_slice(&bytes[40..44])?),
+            green_mask: u32::from_le_bytes(*array_from_slice(&bytes[44..48])?),
+            blue_mask: u32::from_le_bytes(*array_from_slice(&bytes[48..52])?),
+            alpha_mask: u32::from_le_bytes(*array_from_slice(&bytes[52..56])?),
+            color_space_type: u32::from_le_bytes(*array_from_slice(&bytes[56..60])?),
+            endpoints,
+            gamma_red: u32::from_le_bytes(*array_from_slice(&bytes[96..100])?),
+            gamma_green: u32::from_le_bytes(*array_from_slice(&bytes[100..104])?),
+            gamma_blue: u32::from_le_bytes(*array_from_slice(&bytes[104..108])?),
+            profile,
+        })
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.extend_from_slice(&self.red_mask.to_le_bytes());
+        bytes.extend_from_slice(&self.green_mask.to_le_bytes());
+        bytes.extend_from_slice(&self.blue_mask.to_le_bytes());
+        bytes.extend_from_slice(&self.alpha_mask.to_le_bytes());
+        bytes.extend_from_slice(&self.color_space_type.to_le_bytes());
+        self.endpoints.iter().for_each(|endpoint| bytes.extend_from_slice(&endpoint.to_le_bytes()));
+        bytes.extend_from_slice(&self.gamma_red.to_le_bytes());
+        bytes.extend_from_slice(&self.gamma_green.to_le_bytes());
+        bytes.extend_from_slice(&self.gamma_blue.to_le_bytes());
+
+        if let Some(profile) = &self.profile {
+            bytes.extend_from_slice(&profile.intent.to_le_bytes());
+            bytes.extend_from_slice(&profile.profile_data.to_le_bytes());
+            bytes.extend_from_slice(&profile.profile_size.to_le_bytes());
+            bytes.extend_from_slice(&profile.reserved.to_le_bytes());
+        }
+
+        bytes
+    }
+}
+
 /// The DIB header (bitmap information header).
 ///
 /// Also known as the Windows `BITMAPINFOHEADER`; this is the conventionally supported bitmap
 /// DIB header.
 ///
+/// The larger `BITMAPV4HEADER` and `BITMAPV5HEADER` DIB headers are also supported when reading,
+/// with their additional fields stored in [InformationHeader::color_space].
+///
 /// See: https://en.wikipedia.org/wiki/BMP_file_format#DIB_header_(bitmap_information_header)
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct InformationHeader {
-    /// The size of this header in bytes (40 bytes).
+    /// The size of this header in bytes (40 bytes, or 108/124 bytes for the V4/V5 headers).
     pub size: u32,
 
     /// The width of the image in bytes.
@@ -155,11 +398,21 @@ pub struct InformationHeader {
     ///
     /// Generally ignored.
     pub important_color_count: u32,
+
+    /// The additional fields of a `BITMAPV4HEADER` or `BITMAPV5HEADER`, if this is one.
+    pub color_space: Option<ColorSpaceHeader>,
 }
 
 impl InformationHeader {
+    /// The size of a `BITMAPINFOHEADER` in bytes.
     pub const SIZE: usize = 40;
 
+    /// The size of a `BITMAPV4HEADER` in bytes.
+    pub const V4_SIZE: usize = 108;
+
+    /// The size of a `BITMAPV5HEADER` in bytes.
+    pub const V5_SIZE: usize = 124;
+
     pub fn new<P: Pixel>(width: i32, height: i32) -> Self {
         Self {
             size: Self::SIZE as u32,
@@ -173,6 +426,7 @@ impl InformationHeader {
             vertical_resolution: P::pixels_per_meter(),
             color_palette_count: 0,
             important_color_count: 0,
+            color_space: None,
         }
     }
 
@@ -189,12 +443,26 @@ impl InformationHeader {
         let color_palette_count = u32::from_le_bytes(*array_from_slice(&bytes[32..36])?);
         let important_color_count = u32::from_le_bytes(*array_from_slice(&bytes[36..40])?);
 
-        if size != 40 {
+        if ![Self::SIZE, Self::V4_SIZE, Self::V5_SIZE].contains(&(size as usize)) || bytes.len() != size as usize {
             return Err(IllegalParameter("unexpected bitmap information header size"));
         }
 
-        if bits_per_pixel != 24 {
-            return Err(Unsupported("only 24bpp bitmaps are supported"));
+        let color_space = if size as usize > Self::SIZE {
+            Some(ColorSpaceHeader::new_from_bytes(bytes)?)
+        } else {
+            None
+        };
+
+        if ![1, 4, 8, 16, 24, 32, 48].contains(&bits_per_pixel) {
+            return Err(Unsupported("only 1bpp, 4bpp, 8bpp, 16bpp, 24bpp, 32bpp and 48bpp bitmaps are supported"));
+        }
+
+        if matches!(compression_method, CompressionMethod::BiRle8) && bits_per_pixel != 8 {
+            return Err(IllegalParameter("RLE8 compression is only valid for 8bpp bitmaps"));
+        }
+
+        if matches!(compression_method, CompressionMethod::BiBitfields) && bits_per_pixel != 16 {
+            return Err(Unsupported("bit field compression is only supported for 16bpp bitmaps"));
         }
 
         if color_plane_count != 1 {
@@ -213,6 +481,7 @@ impl InformationHeader {
             vertical_resolution,
             color_palette_count,
             important_color_count,
+            color_space,
         })
     }
 
@@ -229,10 +498,41 @@ impl InformationHeader {
         bytes[28..32].copy_from_slice(&self.vertical_resolution.to_le_bytes());
         bytes[32..36].copy_from_slice(&self.color_palette_count.to_le_bytes());
         bytes[36..40].copy_from_slice(&self.important_color_count.to_le_bytes());
-        bytes.to_vec()
+
+        let mut bytes = bytes.to_vec();
+        if let Some(color_space) = &self.color_space {
+            bytes.append(&mut color_space.to_bytes());
+        }
+
+        bytes
     }
 }
 
+/// A pixel format that a [Bitmap] can store.
+///
+/// ## Example
+///
+/// ```rust
+/// use bitmap_rs::{rgb, Error, Pixel};
+///
+/// /// An 8bpp grayscale pixel.
+/// #[derive(Debug)]
+/// struct Gray(u8);
+///
+/// impl Pixel for Gray {
+///     fn bits_per_pixel() -> u16 { 8 }
+///     fn pixels_per_meter() -> i32 { 2835 }
+///     fn new_from_bytes(bytes: &[u8]) -> Result<Self, Error> { Ok(Gray(bytes[0])) }
+///     fn is_black(&self) -> bool { self.0 == 0 }
+///     fn is_white(&self) -> bool { self.0 == 255 }
+///     fn to_bytes(&self) -> Vec<u8> { vec![self.0] }
+///     fn difference(&self, other: &Self) -> f64 { f64::from(self.0.abs_diff(other.0)) }
+/// }
+///
+/// // The methods that have default implementations don't need to be implemented.
+/// assert!(Gray(0).is_near_black(5) && !Gray(2).is_near_black(5));
+/// assert!(Gray::new_from_color(&rgb!(0, 0, 0)).is_err());
+/// ```
 pub trait Pixel {
     /// The number of bits used to represent each pixel.
     fn bits_per_pixel() -> u16;
@@ -244,12 +544,65 @@ pub trait Pixel {
     /// Create a new pixel from the given bytes.
     fn new_from_bytes(bytes: &[u8]) -> Result<Self, Error> where Self: Sized;
 
+    /// Create a new pixel from the given color table entry.
+    ///
+    /// This is used to expand indexed bitmaps into pixel formats that store their own color. By
+    /// default, the pixel format can't be created from a color, so indexed bitmaps can't be
+    /// expanded into it.
+    fn new_from_color(_color: &Pixel24Bit) -> Result<Self, Error> where Self: Sized {
+        Err(Unsupported("this pixel format cannot be created from a color"))
+    }
+
     /// Returns true if the pixel represents pure black.
     fn is_black(&self) -> bool;
 
     /// Returns true if the pixel represents pure white.
     fn is_white(&self) -> bool;
 
+    /// Returns true if every channel of the pixel is within `tolerance` of black.
+    ///
+    /// A tolerance of 0 is equivalent to [Pixel::is_black].
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Pixel};
+    ///
+    /// assert!(rgb!(0, 0, 0).is_near_black(0));
+    /// assert!(!rgb!(2, 1, 3).is_near_black(0));
+    ///
+    /// assert!(rgb!(2, 1, 3).is_near_black(5));
+    /// assert!(rgb!(5, 5, 5).is_near_black(5));
+    /// assert!(!rgb!(2, 6, 3).is_near_black(5));
+    /// ```
+    ///
+    /// By default, the tolerance is ignored, so only pure black is near black.
+    fn is_near_black(&self, _tolerance: u8) -> bool {
+        self.is_black()
+    }
+
+    /// Returns true if every channel of the pixel is within `tolerance` of white.
+    ///
+    /// A tolerance of 0 is equivalent to [Pixel::is_white].
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Pixel};
+    ///
+    /// assert!(rgb!(255, 255, 255).is_near_white(0));
+    /// assert!(!rgb!(253, 254, 252).is_near_white(0));
+    ///
+    /// assert!(rgb!(253, 254, 252).is_near_white(5));
+    /// assert!(rgb!(250, 250, 250).is_near_white(5));
+    /// assert!(!rgb!(253, 249, 252).is_near_white(5));
+    /// ```
+    ///
+    /// By default, the tolerance is ignored, so only pure white is near white.
+    fn is_near_white(&self, _tolerance: u8) -> bool {
+        self.is_white()
+    }
+
     /// Serialize the pixel to bytes.
     fn to_bytes(&self) -> Vec<u8>;
 
@@ -257,6 +610,72 @@ pub trait Pixel {
     fn difference(&self, other: &Self) -> f64;
 }
 
+/// Convert a pixel to another pixel format, rescaling or dropping channels as needed.
+///
+/// ## Example
+///
+/// ```rust
+/// use bitmap_rs::{ConvertPixel, Pixel24Bit, Pixel32Bit, Pixel48Bit};
+///
+/// let pixel: Pixel24Bit = Pixel48Bit { red: 0xFFFF, green: 0x8000, blue: 0x00FF }.convert();
+/// assert_eq!((pixel.red, pixel.green, pixel.blue), (255, 128, 1));
+///
+/// let pixel: Pixel48Bit = Pixel24Bit { red: 255, green: 128, blue: 1 }.convert();
+/// assert_eq!((pixel.red, pixel.green, pixel.blue), (0xFFFF, 0x8080, 0x0101));
+///
+/// // Pixels with alpha are composited over white.
+/// let pixel: Pixel24Bit = Pixel32Bit { red: 255, green: 128, blue: 0, alpha: 51 }.convert();
+/// assert_eq!((pixel.red, pixel.green, pixel.blue), (255, 230, 204));
+/// ```
+pub trait ConvertPixel<To: Pixel> {
+    /// Convert this pixel to the other pixel format.
+    fn convert(&self) -> To;
+}
+
+/// A pixel format that stores its own color as channels of up to 16 bits, which can be combined
+/// channel by channel (e.g., by [Bitmap::average_color]).
+///
+/// ## Example
+///
+/// ```rust
+/// use bitmap_rs::{Pixel32Bit, Pixel48Bit, PixelChannels};
+///
+/// let pixel = Pixel48Bit { red: 0x1234, green: 0xABCD, blue: 0x00FF };
+/// assert_eq!(pixel.channels(), [0x1234, 0xABCD, 0x00FF, 0]);
+///
+/// let pixel = Pixel32Bit::from_channels([1, 2, 3, 4]);
+/// assert_eq!((pixel.red, pixel.green, pixel.blue, pixel.alpha), (1, 2, 3, 4));
+/// ```
+pub trait PixelChannels: Pixel {
+    /// Get the channels of the pixel, in the order of its fields, with any unused channels set to
+    /// 0.
+    fn channels(&self) -> [u16; 4];
+
+    /// Create a pixel from channels in the order of its fields (see [PixelChannels::channels]),
+    /// ignoring any unused channels.
+    fn from_channels(channels: [u16; 4]) -> Self;
+}
+
+/// A 24bpp pixel, with 8 bits for each channel.
+///
+/// The channels are stored in the order that they appear in a bitmap file, which is blue, green,
+/// red. So for a pixel read from a file, `red` holds the blue channel and `blue` holds the red
+/// channel. Use [Pixel24Bit::from_rgb] and [Pixel24Bit::to_rgb] to convert from and to the true
+/// red, green and blue channels of the color.
+///
+/// ## Example
+///
+/// ```rust
+/// use bitmap_rs::{Bitmap, Pixel24Bit};
+///
+/// let orange = Pixel24Bit::from_rgb(255, 128, 0);
+/// assert_eq!((orange.red, orange.green, orange.blue), (0, 128, 255));
+/// assert_eq!(orange.to_rgb(), [255, 128, 0]);
+///
+/// // A bitmap file stores the blue byte first.
+/// let bytes = Bitmap::new_from_pixels(1, 1, vec![orange]).unwrap().to_bytes();
+/// assert_eq!(bytes[54..57], [0, 128, 255]);
+/// ```
 #[derive(Copy, Clone, Debug)]
 pub struct Pixel24Bit {
     pub red: u8,
@@ -264,6 +683,87 @@ pub struct Pixel24Bit {
     pub blue: u8,
 }
 
+impl Pixel24Bit {
+    /// Create a pixel from the true red, green and blue channels of a color, storing them in the
+    /// order that a bitmap file stores them in (see [Pixel24Bit]).
+    pub const fn from_rgb(red: u8, green: u8, blue: u8) -> Self {
+        Self { red: blue, green, blue: red }
+    }
+
+    /// Get the true red, green and blue channels of the color, from a pixel that stores them in
+    /// the order that a bitmap file stores them in (see [Pixel24Bit]).
+    pub const fn to_rgb(&self) -> [u8; 3] {
+        [self.blue, self.green, self.red]
+    }
+
+    /// Get the signed difference of each channel between this value and the other value, in the
+    /// order of the true red, green and blue channels (see [Pixel24Bit::to_rgb]).
+    ///
+    /// Each delta is `other - self`, so a positive value means the other pixel has more of that
+    /// channel.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::Pixel24Bit;
+    ///
+    /// let pixel = Pixel24Bit::from_rgb(0x4C, 0xAF, 0x50);
+    /// let other = Pixel24Bit::from_rgb(0x40, 0xB0, 0xFF);
+    /// assert_eq!(pixel.channel_differences(&other), [-12, 1, 175]);
+    /// ```
+    pub fn channel_differences(&self, other: &Self) -> [i16; 3] {
+        let [red, green, blue] = self.to_rgb();
+        let [other_red, other_green, other_blue] = other.to_rgb();
+
+        [
+            i16::from(other_red) - i16::from(red),
+            i16::from(other_green) - i16::from(green),
+            i16::from(other_blue) - i16::from(blue),
+        ]
+    }
+
+    /// Get the "redmean" difference between this value and the other value, which weights the
+    /// channels to approximate the sensitivity of human vision (with the weights of red and blue
+    /// depending on the mean red of the two pixels).
+    ///
+    /// The weights apply to the true red, green and blue channels (see [Pixel24Bit::to_rgb]), not
+    /// to the fields, which are in the order that a bitmap file stores them in.
+    ///
+    /// This is much cheaper than converting to a perceptual color space, and closer to perceived
+    /// differences than [Pixel::difference].
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{Pixel, Pixel24Bit};
+    ///
+    /// let gray = Pixel24Bit::from_rgb(128, 128, 128);
+    /// let greener = Pixel24Bit::from_rgb(128, 138, 128);
+    /// let redder = Pixel24Bit::from_rgb(140, 128, 128);
+    ///
+    /// // The greener gray is nearer by Euclidean distance, but the eye is more sensitive to green,
+    /// // so the redder gray is perceptually nearer.
+    /// assert!(gray.difference(&greener) < gray.difference(&redder));
+    /// assert!(gray.difference_redmean(&redder) < gray.difference_redmean(&greener));
+    ///
+    /// // On a red pixel, a change in blue is less noticeable than the same change in red.
+    /// let red = Pixel24Bit::from_rgb(255, 0, 0);
+    /// let bluer = Pixel24Bit::from_rgb(255, 0, 20);
+    /// let darker = Pixel24Bit::from_rgb(235, 0, 0);
+    /// assert!(red.difference_redmean(&bluer) < red.difference_redmean(&darker));
+    /// ```
+    pub fn difference_redmean(&self, other: &Self) -> f64 {
+        let mean_red = (f64::from(self.to_rgb()[0]) + f64::from(other.to_rgb()[0])) / 2.0;
+        let [red, green, blue] = self.channel_differences(other).map(f64::from);
+
+        math::sqrt(
+            (2.0 + mean_red / 256.0) * red * red
+                + 4.0 * green * green
+                + (2.0 + (255.0 - mean_red) / 256.0) * blue * blue
+        )
+    }
+}
+
 impl Pixel for Pixel24Bit {
     fn bits_per_pixel() -> u16 {
         24
@@ -285,6 +785,10 @@ impl Pixel for Pixel24Bit {
         })
     }
 
+    fn new_from_color(color: &Pixel24Bit) -> Result<Self, Error> {
+        Ok(*color)
+    }
+
     fn is_black(&self) -> bool {
         self.red == 0 && self.green == 0 && self.blue == 0
     }
@@ -293,137 +797,1124 @@ impl Pixel for Pixel24Bit {
         self.red == 255 && self.green == 255 && self.blue == 255
     }
 
+    fn is_near_black(&self, tolerance: u8) -> bool {
+        self.red <= tolerance && self.green <= tolerance && self.blue <= tolerance
+    }
+
+    fn is_near_white(&self, tolerance: u8) -> bool {
+        let threshold = 255 - tolerance;
+        self.red >= threshold && self.green >= threshold && self.blue >= threshold
+    }
+
     fn to_bytes(&self) -> Vec<u8> {
         [self.red, self.green, self.blue].to_vec()
     }
 
     fn difference(&self, other: &Self) -> f64 {
-        (
-            (f64::from(other.red) - f64::from(self.red)).powi(2) +
-                (f64::from(other.green) - f64::from(self.green)).powi(2) +
-                (f64::from(other.blue) - f64::from(self.blue)).powi(2)
-        ).sqrt()
+        let [red, green, blue] = self.channel_differences(other).map(f64::from);
+        math::sqrt(red * red + green * green + blue * blue)
     }
 }
 
-/// Represents a bitmap image.
+// Probing doesn't depend on the pixel format, so it's only defined for one, so that it can be
+// called as `Bitmap::probe` without naming a pixel format.
+impl Bitmap<Pixel24Bit> {
+    /// Read a summary of the bitmap file from its headers, without decoding the pixels, e.g., to
+    /// decide how to decode it.
+    ///
+    /// Only the headers are validated, so a file that probes successfully may still fail to
+    /// decode.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// let bitmap = Bitmap::new_from_pixels(3, -2, vec![rgb!(1, 2, 3); 6]).unwrap();
+    /// let info = Bitmap::probe(&bitmap.to_bytes()).unwrap();
+    /// assert_eq!((info.width, info.height, info.bits_per_pixel), (3, 2, 24));
+    /// assert!(info.is_top_down && !info.has_palette);
+    ///
+    /// let indexed = bitmap.to_indexed(2).unwrap();
+    /// let info = Bitmap::probe(&indexed.to_bytes()).unwrap();
+    /// assert_eq!(info.bits_per_pixel, 8);
+    /// assert!(info.has_palette);
+    ///
+    /// // The pixels aren't read, so a truncated file can still be probed.
+    /// let bytes = bitmap.to_bytes();
+    /// assert!(Bitmap::probe(&bytes[..bytes.len() - 1]).is_ok());
+    /// assert!(Bitmap::probe(&bytes[..20]).is_err());
+    /// ```
+    pub fn probe(bytes: &[u8]) -> Result<BitmapInfo, Error> {
+        let (_, information_header) = read_headers(bytes)?;
+
+        Ok(BitmapInfo {
+            width: information_header.width.unsigned_abs(),
+            height: information_header.height.unsigned_abs(),
+            is_top_down: information_header.height < 0,
+            bits_per_pixel: information_header.bits_per_pixel,
+            compression_method: information_header.compression_method,
+            has_palette: information_header.color_palette_count > 0 || information_header.bits_per_pixel <= 8,
+        })
+    }
+}
+
+/// Convert the bytes of a pixel, in the order that a bitmap file stores them in (blue, green,
+/// red), with [Pixel::new_from_bytes].
+///
+/// The conversions to and from `[u8; 3]` use the same order as the fields (see [Pixel24Bit]); use
+/// [Pixel24Bit::from_rgb] and [Pixel24Bit::to_rgb] for the true red, green and blue channels.
 ///
 /// ## Example
 ///
 /// ```rust
-/// use tempfile::NamedTempFile;
-/// use std::io::{BufWriter, Write};
-/// use std::iter::repeat_n;
-/// use bitmap_rs::{hex, Bitmap, Pixel24Bit};
+/// use bitmap_rs::{rgb, Pixel24Bit};
 ///
-/// // Create an image of 100px x 66px where each pixel is the color green.
-/// let width = 100;
-/// let height = 66;
-/// let pixels: Vec<Pixel24Bit> = repeat_n(
-///     hex!("#4CAF50").unwrap(),
-///     (width * height) as usize
-/// ).collect();
+/// let pixel = Pixel24Bit::try_from(&[76u8, 175, 80][..]).unwrap();
+/// assert_eq!(<[u8; 3]>::from(pixel), [76, 175, 80]);
+/// assert_eq!(pixel.to_rgb(), [80, 175, 76]);
+/// assert!(Pixel24Bit::try_from(&[76u8, 175][..]).is_err());
 ///
-/// let bitmap = Bitmap::new_from_pixels(width, height, pixels).unwrap();
+/// let pixel = Pixel24Bit::from([1, 2, 3]);
+/// assert_eq!((pixel.red, pixel.green, pixel.blue), (1, 2, 3));
 ///
-/// // Write the bitmap image data into a temporary file.
-/// let mut output_file = NamedTempFile::new().unwrap();
-/// let mut output_file_writer = BufWriter::new(output_file);
-/// output_file_writer.write_all(&bitmap.to_bytes()).unwrap();
-/// output_file_writer.flush().unwrap();
+/// // `rgb!` takes the true channels, which a bitmap file stores in reverse.
+/// assert_eq!(<[u8; 3]>::from(rgb!(1, 2, 3)), [3, 2, 1]);
 /// ```
-#[derive(Debug)]
-pub struct Bitmap<P: Pixel> {
-    pub header: Header,
-    pub information_header: InformationHeader,
-    pub pixels: Vec<P>,
-}
-
-impl<P: Pixel + std::fmt::Debug> Bitmap<P> {
-    /// Construct a new [Bitmap] from the given dimensions and pixel array.
-    ///
-    /// The height is automatically negated (this means the default for a positive height is that
-    /// the pixels are interpreted top-to-bottom).
-    pub fn new_from_pixels(width: i32, height: i32, pixels: Vec<P>) -> Result<Self, Error> {
-        let unsigned_abs_height = height.unsigned_abs();
-        if pixels.len() != (width.unsigned_abs() * unsigned_abs_height) as usize {
-            return Err(IllegalParameter("pixel length is not equal to width * height"));
-        }
-
-        let information_header = InformationHeader::new::<P>(width, height);
-        let headers_size = (Header::SIZE + information_header.size as usize) as u32;
+impl TryFrom<&[u8]> for Pixel24Bit {
+    type Error = Error;
 
-        let (_, padded_bytes_per_image) = Self::compute_padding(pixels.len() as u32, unsigned_abs_height);
-
-        Ok(Self {
-            header: Header::new(
-                headers_size + padded_bytes_per_image,
-                headers_size
-            ),
-            information_header,
-            pixels
-        })
+    fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+        Self::new_from_bytes(bytes)
     }
+}
 
-    /// Construct a new [Bitmap] from the given bitmap file bytes.
-    pub fn new_from_bytes(bytes: Vec<u8>) -> Result<Bitmap<P>, Error> {
-        let header = Header::new_from_bytes(&bytes[0..Header::SIZE])?;
-        let information_header = InformationHeader::new_from_bytes(&bytes[Header::SIZE..(Header::SIZE + InformationHeader::SIZE)])?;
-
-        let bytes_per_pixel = information_header.bits_per_pixel.div_ceil(8) as usize;
-        let pixel_count = information_header.height.unsigned_abs() * information_header.width.unsigned_abs();
-
-        let (padding_bytes_per_row, _) = Self::compute_padding(pixel_count, information_header.height.unsigned_abs());
-        let bytes_per_row = information_header.width.unsigned_abs() as usize * bytes_per_pixel;
-        let bytes_per_padded_row = bytes_per_row + padding_bytes_per_row as usize;
-
-        let mut pixels = Vec::with_capacity(pixel_count as usize);
-        let mut has_bad_pixel = false;
-
-        bytes[(header.offset as usize)..].chunks_exact(bytes_per_padded_row).for_each(|row| {
-            row[0..bytes_per_row].chunks_exact(bytes_per_pixel).for_each(|pixel| {
-                if let Ok(pixel) = P::new_from_bytes(pixel).map_err(|_| has_bad_pixel = true) {
-                    pixels.push(pixel);
-                }
-            });
-        });
+impl PixelChannels for Pixel24Bit {
+    fn channels(&self) -> [u16; 4] {
+        [self.red, self.green, self.blue, 0].map(u16::from)
+    }
 
-        if has_bad_pixel {
-            return Err(IllegalParameter("bad pixel data"));
-        }
+    fn from_channels([red, green, blue, _]: [u16; 4]) -> Self {
+        Self { red: red as u8, green: green as u8, blue: blue as u8 }
+    }
+}
 
-        Ok(Bitmap {
-            header,
-            information_header,
-            pixels
-        })
+impl From<[u8; 3]> for Pixel24Bit {
+    fn from([red, green, blue]: [u8; 3]) -> Self {
+        Self { red, green, blue }
     }
+}
 
-    /// Get the width of the image, in pixels.
-    pub fn get_width(&self) -> u32 {
-        self.get_raw_width().unsigned_abs()
+impl From<Pixel24Bit> for [u8; 3] {
+    fn from(pixel: Pixel24Bit) -> Self {
+        [pixel.red, pixel.green, pixel.blue]
     }
+}
 
-    /// Get the height of the image, in pixels.
-    pub fn get_height(&self) -> u32 {
-        self.get_raw_height().unsigned_abs()
+/// Format the pixel as an uppercase hex color, of its true red, green and blue channels (see
+/// [Pixel24Bit::to_rgb]).
+///
+/// The [LowerHex](fmt::LowerHex) and [UpperHex](fmt::UpperHex) formats print just the hex digits,
+/// prefixed with `#` in the alternate form.
+///
+/// ## Example
+///
+/// ```rust
+/// use bitmap_rs::Pixel24Bit;
+///
+/// let pixel = Pixel24Bit::from_rgb(76, 175, 80);
+/// assert_eq!(format!("{pixel}"), "#4CAF50");
+/// assert_eq!(format!("{pixel:x} {pixel:X} {pixel:#x}"), "4caf50 4CAF50 #4caf50");
+///
+/// // The fields are in the order that a bitmap file stores them in, so blue is printed first.
+/// let pixel = Pixel24Bit { red: 0, green: 0, blue: 255 };
+/// assert_eq!(pixel.to_string(), "#FF0000");
+/// ```
+impl fmt::Display for Pixel24Bit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:#X}")
     }
+}
 
-    /// Get the raw width of the image.
-    pub fn get_raw_width(&self) -> i32 {
-        self.information_header.width
+impl fmt::LowerHex for Pixel24Bit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prefix = if f.alternate() { "#" } else { "" };
+        let [red, green, blue] = self.to_rgb();
+        write!(f, "{prefix}{red:02x}{green:02x}{blue:02x}")
     }
+}
 
-    /// Get the raw height of the image.
-    ///
-    /// Negative means the image is drawn bottom-to-top, positive means the image is drawn
-    /// top-to-bottom.
-    pub fn get_raw_height(&self) -> i32 {
-        self.information_header.height
+impl fmt::UpperHex for Pixel24Bit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prefix = if f.alternate() { "#" } else { "" };
+        let [red, green, blue] = self.to_rgb();
+        write!(f, "{prefix}{red:02X}{green:02X}{blue:02X}")
     }
+}
 
-    /// Get the pixel at the given coordinates.
-    pub fn get_pixel_at(&self, x: u32, y: u32) -> Option<&P> {
+/// A 48bpp pixel, with 16 bits for each channel.
+///
+/// The channels are stored in the same order as a [Pixel24Bit], each as a little-endian `u16`.
+///
+/// ## Example
+///
+/// ```rust
+/// use bitmap_rs::{Bitmap, Pixel, Pixel24Bit, Pixel48Bit};
+///
+/// let pixels = vec![
+///     Pixel48Bit { red: 0, green: 0, blue: 0 },
+///     Pixel48Bit { red: 0x1234, green: 0xABCD, blue: 0x00FF },
+///     Pixel48Bit { red: 0xFFFF, green: 0xFFFF, blue: 0xFFFF },
+/// ];
+/// let bitmap = Bitmap::new_from_pixels(3, 1, pixels).unwrap();
+/// assert_eq!(bitmap.information_header.bits_per_pixel, 48);
+///
+/// let round_tripped: Bitmap<Pixel48Bit> = Bitmap::new_from_bytes(bitmap.to_bytes()).unwrap();
+/// assert_eq!(round_tripped.pixels[1].to_bytes(), vec![0x34, 0x12, 0xCD, 0xAB, 0xFF, 0x00]);
+/// assert!(round_tripped.pixels[0].is_black() && round_tripped.pixels[2].is_white());
+/// assert_eq!(round_tripped.to_bytes(), bitmap.to_bytes());
+///
+/// // A 48bpp bitmap can't be read as 24bpp pixels.
+/// assert!(Bitmap::<Pixel24Bit>::new_from_bytes(bitmap.to_bytes()).is_err());
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct Pixel48Bit {
+    pub red: u16,
+    pub green: u16,
+    pub blue: u16,
+}
+
+impl Pixel48Bit {
+    /// The factor that scales an 8-bit channel to 16 bits, so that 0xFF becomes 0xFFFF.
+    const CHANNEL_SCALE: u16 = 257;
+}
+
+impl Pixel for Pixel48Bit {
+    fn bits_per_pixel() -> u16 {
+        48
+    }
+
+    fn pixels_per_meter() -> i32 {
+        2835
+    }
+
+    fn new_from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let [red_low, red_high, green_low, green_high, blue_low, blue_high] = *array_from_slice(bytes)
+            .map_err(|_| IllegalParameter("expected exactly 6 bytes for a pixel"))?;
+
+        Ok(Self {
+            red: u16::from_le_bytes([red_low, red_high]),
+            green: u16::from_le_bytes([green_low, green_high]),
+            blue: u16::from_le_bytes([blue_low, blue_high]),
+        })
+    }
+
+    fn new_from_color(color: &Pixel24Bit) -> Result<Self, Error> {
+        Ok(Self {
+            red: u16::from(color.red) * Self::CHANNEL_SCALE,
+            green: u16::from(color.green) * Self::CHANNEL_SCALE,
+            blue: u16::from(color.blue) * Self::CHANNEL_SCALE,
+        })
+    }
+
+    fn is_black(&self) -> bool {
+        self.red == 0 && self.green == 0 && self.blue == 0
+    }
+
+    fn is_white(&self) -> bool {
+        self.red == u16::MAX && self.green == u16::MAX && self.blue == u16::MAX
+    }
+
+    fn is_near_black(&self, tolerance: u8) -> bool {
+        // The tolerance is in 8-bit units, so scale it to 16 bits.
+        let threshold = u16::from(tolerance) * Self::CHANNEL_SCALE;
+        self.red <= threshold && self.green <= threshold && self.blue <= threshold
+    }
+
+    fn is_near_white(&self, tolerance: u8) -> bool {
+        let threshold = u16::MAX - u16::from(tolerance) * Self::CHANNEL_SCALE;
+        self.red >= threshold && self.green >= threshold && self.blue >= threshold
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        [self.red.to_le_bytes(), self.green.to_le_bytes(), self.blue.to_le_bytes()].concat()
+    }
+
+    fn difference(&self, other: &Self) -> f64 {
+        let red = f64::from(other.red) - f64::from(self.red);
+        let green = f64::from(other.green) - f64::from(self.green);
+        let blue = f64::from(other.blue) - f64::from(self.blue);
+        math::sqrt(red * red + green * green + blue * blue)
+    }
+}
+
+impl PixelChannels for Pixel48Bit {
+    fn channels(&self) -> [u16; 4] {
+        [self.red, self.green, self.blue, 0]
+    }
+
+    fn from_channels([red, green, blue, _]: [u16; 4]) -> Self {
+        Self { red, green, blue }
+    }
+}
+
+impl ConvertPixel<Pixel24Bit> for Pixel48Bit {
+    fn convert(&self) -> Pixel24Bit {
+        // Round each channel to the nearest 8-bit value.
+        let narrow = |channel: u16| ((u32::from(channel) * 255 + 0x7FFF) / 0xFFFF) as u8;
+        Pixel24Bit { red: narrow(self.red), green: narrow(self.green), blue: narrow(self.blue) }
+    }
+}
+
+impl ConvertPixel<Pixel48Bit> for Pixel24Bit {
+    fn convert(&self) -> Pixel48Bit {
+        let widen = |channel: u8| u16::from(channel) * Pixel48Bit::CHANNEL_SCALE;
+        Pixel48Bit { red: widen(self.red), green: widen(self.green), blue: widen(self.blue) }
+    }
+}
+
+/// A 32bpp pixel, with 8 bits for each channel and 8 bits of alpha (where 0 is fully transparent
+/// and 255 is fully opaque).
+///
+/// The color channels are stored in the same order as a [Pixel24Bit], followed by the alpha
+/// channel. As a bitmap has no background, the pixel is considered black or white by its color
+/// composited over white (see [ConvertPixel]).
+///
+/// ## Example
+///
+/// ```rust
+/// use bitmap_rs::{Bitmap, ConvertPixel, Pixel, Pixel24Bit, Pixel32Bit};
+///
+/// let pixels = vec![
+///     Pixel32Bit { red: 0, green: 0, blue: 0, alpha: 255 },
+///     Pixel32Bit { red: 0, green: 0, blue: 255, alpha: 128 },
+///     Pixel32Bit { red: 0, green: 0, blue: 0, alpha: 0 },
+/// ];
+/// let bitmap = Bitmap::new_from_pixels(3, 1, pixels).unwrap();
+/// assert_eq!(bitmap.information_header.bits_per_pixel, 32);
+///
+/// let round_tripped: Bitmap<Pixel32Bit> = Bitmap::new_from_bytes(bitmap.to_bytes()).unwrap();
+/// assert_eq!(round_tripped.pixels[1].to_bytes(), vec![0, 0, 255, 128]);
+/// assert!(round_tripped.pixels[0].is_black() && round_tripped.pixels[2].is_white());
+/// assert_eq!(round_tripped.to_bytes(), bitmap.to_bytes());
+///
+/// // Half-transparent red over white is pink.
+/// let pink: Pixel24Bit = round_tripped.pixels[1].convert();
+/// assert_eq!(pink.to_rgb(), [255, 127, 127]);
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct Pixel32Bit {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+    pub alpha: u8,
+}
+
+impl Pixel for Pixel32Bit {
+    fn bits_per_pixel() -> u16 {
+        32
+    }
+
+    fn pixels_per_meter() -> i32 {
+        2835
+    }
+
+    fn new_from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let [red, green, blue, alpha] = *array_from_slice(bytes)
+            .map_err(|_| IllegalParameter("expected exactly 4 bytes for a pixel"))?;
+
+        Ok(Self { red, green, blue, alpha })
+    }
+
+    fn new_from_color(color: &Pixel24Bit) -> Result<Self, Error> {
+        Ok(color.convert())
+    }
+
+    fn is_black(&self) -> bool {
+        ConvertPixel::<Pixel24Bit>::convert(self).is_black()
+    }
+
+    fn is_white(&self) -> bool {
+        ConvertPixel::<Pixel24Bit>::convert(self).is_white()
+    }
+
+    fn is_near_black(&self, tolerance: u8) -> bool {
+        ConvertPixel::<Pixel24Bit>::convert(self).is_near_black(tolerance)
+    }
+
+    fn is_near_white(&self, tolerance: u8) -> bool {
+        ConvertPixel::<Pixel24Bit>::convert(self).is_near_white(tolerance)
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        vec![self.red, self.green, self.blue, self.alpha]
+    }
+
+    fn difference(&self, other: &Self) -> f64 {
+        let red = f64::from(other.red) - f64::from(self.red);
+        let green = f64::from(other.green) - f64::from(self.green);
+        let blue = f64::from(other.blue) - f64::from(self.blue);
+        let alpha = f64::from(other.alpha) - f64::from(self.alpha);
+        math::sqrt(red * red + green * green + blue * blue + alpha * alpha)
+    }
+}
+
+impl PixelChannels for Pixel32Bit {
+    fn channels(&self) -> [u16; 4] {
+        [self.red, self.green, self.blue, self.alpha].map(u16::from)
+    }
+
+    fn from_channels([red, green, blue, alpha]: [u16; 4]) -> Self {
+        Self { red: red as u8, green: green as u8, blue: blue as u8, alpha: alpha as u8 }
+    }
+}
+
+impl ConvertPixel<Pixel24Bit> for Pixel32Bit {
+    fn convert(&self) -> Pixel24Bit {
+        // Composite the pixel over white, rounding each channel to the nearest value.
+        let alpha = u32::from(self.alpha);
+        let over_white = |channel: u8| ((u32::from(channel) * alpha + 255 * (255 - alpha) + 127) / 255) as u8;
+        Pixel24Bit { red: over_white(self.red), green: over_white(self.green), blue: over_white(self.blue) }
+    }
+}
+
+impl ConvertPixel<Pixel32Bit> for Pixel24Bit {
+    fn convert(&self) -> Pixel32Bit {
+        Pixel32Bit { red: self.red, green: self.green, blue: self.blue, alpha: u8::MAX }
+    }
+}
+
+/// A pixel in an indexed bitmap, which refers to an entry in the color table of the bitmap
+/// rather than storing a color itself.
+///
+/// As the pixel doesn't know its color, it is never considered black or white, and the difference
+/// between two indexed pixels is the distance between their indices.
+#[derive(Copy, Clone, Debug)]
+pub struct IndexedPixel<const BITS: u16> {
+    pub index: u8,
+}
+
+/// A 1bpp (monochrome) pixel that indexes a color table of 2 colors.
+///
+/// ## Example
+///
+/// ```rust
+/// use bitmap_rs::{hex, Bitmap, Pixel1Bit};
+///
+/// // Create an 8x8 black and white checkerboard.
+/// let color_table = vec![hex!("#000000").unwrap(), hex!("#FFFFFF").unwrap()];
+/// let indices: Vec<u8> = (0..64).map(|i| ((i % 8 + i / 8) % 2) as u8).collect();
+/// let pixels = indices.iter().map(|&index| Pixel1Bit { index }).collect();
+/// let bitmap = Bitmap::new_from_indexed_pixels(8, 8, color_table, pixels).unwrap();
+///
+/// let decoded = Bitmap::<Pixel1Bit>::new_from_bytes(bitmap.to_bytes()).unwrap();
+/// assert_eq!(decoded.pixels.iter().map(|pixel| pixel.index).collect::<Vec<_>>(), indices);
+/// ```
+pub type Pixel1Bit = IndexedPixel<1>;
+
+/// A 4bpp pixel that indexes a color table of up to 16 colors.
+///
+/// ## Example
+///
+/// ```rust
+/// use bitmap_rs::{Bitmap, Pixel, Pixel24Bit, Pixel4Bit};
+///
+/// // Create a 5x3 image using a 16 color table, and its 24bpp equivalent.
+/// let color_table: Vec<Pixel24Bit> = (0..16)
+///     .map(|i| Pixel24Bit { red: i * 16, green: 255 - i * 16, blue: i * 5 })
+///     .collect();
+/// let indices: Vec<u8> = (0..15).map(|i| (i * 7 % 16) as u8).collect();
+///
+/// let pixels = indices.iter().map(|&index| Pixel4Bit { index }).collect();
+/// let indexed = Bitmap::new_from_indexed_pixels(5, 3, color_table.clone(), pixels).unwrap();
+///
+/// let pixels = indices.iter().map(|&index| color_table[index as usize]).collect();
+/// let equivalent = Bitmap::new_from_pixels(5, 3, pixels).unwrap();
+///
+/// let decoded = Bitmap::<Pixel24Bit>::new_from_bytes(indexed.to_bytes()).unwrap();
+/// for (pixel, expected_pixel) in decoded.pixels.iter().zip(equivalent.pixels.iter()) {
+///     assert_eq!(pixel.to_bytes(), expected_pixel.to_bytes());
+/// }
+/// ```
+pub type Pixel4Bit = IndexedPixel<4>;
+
+/// An 8bpp pixel that indexes a color table of up to 256 colors.
+pub type Pixel8Bit = IndexedPixel<8>;
+
+impl<const BITS: u16> Pixel for IndexedPixel<BITS> {
+    fn bits_per_pixel() -> u16 {
+        BITS
+    }
+
+    fn pixels_per_meter() -> i32 {
+        2835
+    }
+
+    fn new_from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != 1 {
+            return Err(IllegalParameter("expected exactly 1 byte for an indexed pixel"));
+        }
+
+        if u32::from(bytes[0]) >= 1 << BITS {
+            return Err(IllegalParameter("color table index is too large for the pixel format"));
+        }
+
+        Ok(Self { index: bytes[0] })
+    }
+
+    fn new_from_color(_: &Pixel24Bit) -> Result<Self, Error> {
+        Err(Unsupported("indexed pixels cannot be created from a color"))
+    }
+
+    fn is_black(&self) -> bool {
+        false
+    }
+
+    fn is_white(&self) -> bool {
+        false
+    }
+
+    fn is_near_black(&self, _: u8) -> bool {
+        false
+    }
+
+    fn is_near_white(&self, _: u8) -> bool {
+        false
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        vec![self.index]
+    }
+
+    fn difference(&self, other: &Self) -> f64 {
+        f64::from(self.index.abs_diff(other.index))
+    }
+}
+
+/// The masks of the color channels of each pixel in a [CompressionMethod::BiBitfields] bitmap.
+struct BitMasks {
+    red: u32,
+    green: u32,
+    blue: u32,
+}
+
+impl BitMasks {
+    /// The size of the bit masks when stored after a `BITMAPINFOHEADER`, in bytes.
+    const SIZE: usize = 12;
+
+    /// The default bit masks for a 16bpp bitmap, with 5 bits for each channel.
+    const RGB555: Self = Self { red: 0x7C00, green: 0x03E0, blue: 0x001F };
+
+    /// Read the bit masks of the bitmap file, if it uses [CompressionMethod::BiBitfields].
+    ///
+    /// The masks are part of the V4 and V5 DIB headers, but otherwise immediately follow the DIB
+    /// header.
+    fn new_from_bytes(bytes: &[u8], information_header: &InformationHeader) -> Result<Option<Self>, Error> {
+        if !matches!(information_header.compression_method, CompressionMethod::BiBitfields) {
+            return Ok(None);
+        }
+
+        if let Some(color_space) = &information_header.color_space {
+            return Ok(Some(Self {
+                red: color_space.red_mask,
+                green: color_space.green_mask,
+                blue: color_space.blue_mask,
+            }));
+        }
+
+        let start = Header::SIZE + information_header.size as usize;
+        let Some(masks) = bytes.get(start..start + Self::SIZE) else {
+            return Err(IllegalParameter("bitmap file is too short to contain the bit masks"));
+        };
+
+        Ok(Some(Self {
+            red: u32::from_le_bytes(*array_from_slice(&masks[0..4])?),
+            green: u32::from_le_bytes(*array_from_slice(&masks[4..8])?),
+            blue: u32::from_le_bytes(*array_from_slice(&masks[8..12])?),
+        }))
+    }
+
+    /// Unpack the given pixel value into a 24bpp pixel.
+    fn unpack(&self, value: u16) -> Pixel24Bit {
+        // Build the pixel in the same (blue, green, red) order that a 24bpp pixel is stored in, so
+        // that it's consistent with the other pixel formats.
+        Pixel24Bit {
+            red: Self::unpack_channel(value, self.blue),
+            green: Self::unpack_channel(value, self.green),
+            blue: Self::unpack_channel(value, self.red),
+        }
+    }
+
+    /// Extract the channel with the given mask from the pixel value, scaled up to 8 bits.
+    fn unpack_channel(value: u16, mask: u32) -> u8 {
+        let bit_count = mask.count_ones();
+        if bit_count == 0 {
+            return 0;
+        }
+
+        let maximum = (1u32 << bit_count) - 1;
+        let channel = (u32::from(value) & mask) >> mask.trailing_zeros();
+        ((channel * 255 + maximum / 2) / maximum) as u8
+    }
+}
+
+/// How a coordinate outside of an image is mapped onto the image when sampling it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeMode {
+    /// The coordinate is clamped to the nearest edge pixel.
+    Clamp,
+
+    /// The coordinate wraps around to the opposite edge, as if the image were tiled.
+    Wrap,
+}
+
+impl EdgeMode {
+    /// Map the normalized `value` onto one of `size` cells, or `None` if the value isn't finite or
+    /// there are no cells.
+    fn cell(&self, value: f64, size: u32) -> Option<u32> {
+        if !value.is_finite() || size == 0 {
+            return None;
+        }
+
+        // The cast saturates, which is fine as any value that large is clamped or wrapped anyway.
+        Some(self.resolve(math::floor(value * f64::from(size)) as i64, size))
+    }
+
+    /// Map the (possibly out-of-range) index of a cell onto one of `size` cells, where `size` must
+    /// be non-zero.
+    pub(crate) fn resolve(&self, cell: i64, size: u32) -> u32 {
+        match self {
+            EdgeMode::Clamp => cell.clamp(0, i64::from(size) - 1) as u32,
+            EdgeMode::Wrap => cell.rem_euclid(i64::from(size)) as u32,
+        }
+    }
+}
+
+/// Read the file header and DIB header from the start of the bytes of a bitmap file.
+fn read_headers(bytes: &[u8]) -> Result<(Header, InformationHeader), Error> {
+    if bytes.len() < Header::SIZE + InformationHeader::SIZE {
+        return Err(IllegalParameter("bitmap file is too short to contain the bitmap headers"));
+    }
+
+    let header = Header::new_from_bytes(&bytes[0..Header::SIZE])?;
+
+    // The DIB header may be one of the larger V4 or V5 variants, so check its size first.
+    let information_header_size = u32::from_le_bytes(*array_from_slice(&bytes[Header::SIZE..(Header::SIZE + 4)])?) as usize;
+    if bytes.len() < Header::SIZE + information_header_size {
+        return Err(IllegalParameter("bitmap file is too short to contain the bitmap headers"));
+    }
+
+    let information_header = InformationHeader::new_from_bytes(&bytes[Header::SIZE..(Header::SIZE + information_header_size)])?;
+    Ok((header, information_header))
+}
+
+/// A summary of a bitmap file, read from its headers without decoding its pixels (see
+/// [Bitmap::probe]).
+#[derive(Clone, Debug)]
+pub struct BitmapInfo {
+    /// The width of the image, in pixels.
+    pub width: u32,
+
+    /// The height of the image, in pixels.
+    pub height: u32,
+
+    /// Whether the rows are stored from the top of the image down (see [Bitmap::is_top_down]).
+    pub is_top_down: bool,
+
+    /// The number of bits used to represent each pixel.
+    pub bits_per_pixel: u16,
+
+    /// The compression method in use.
+    pub compression_method: CompressionMethod,
+
+    /// Whether the file has a color table, either because it declares the number of colors in it
+    /// or because it is an indexed (8bpp or less) bitmap, which always has one.
+    pub has_palette: bool,
+}
+
+/// Represents a bitmap image.
+///
+/// ## Example
+///
+/// ```rust
+/// use tempfile::NamedTempFile;
+/// use std::io::{BufWriter, Write};
+/// use std::iter::repeat_n;
+/// use bitmap_rs::{hex, Bitmap, Pixel24Bit};
+///
+/// // Create an image of 100px x 66px where each pixel is the color green.
+/// let width = 100;
+/// let height = 66;
+/// let pixels: Vec<Pixel24Bit> = repeat_n(
+///     hex!("#4CAF50").unwrap(),
+///     (width * height) as usize
+/// ).collect();
+///
+/// let bitmap = Bitmap::new_from_pixels(width, height, pixels).unwrap();
+///
+/// // Write the bitmap image data into a temporary file.
+/// let mut output_file = NamedTempFile::new().unwrap();
+/// let mut output_file_writer = BufWriter::new(output_file);
+/// output_file_writer.write_all(&bitmap.to_bytes()).unwrap();
+/// output_file_writer.flush().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Bitmap<P: Pixel> {
+    pub header: Header,
+    pub information_header: InformationHeader,
+
+    /// The color table of an indexed bitmap, or empty for a bitmap that stores colors directly.
+    pub color_table: Vec<Pixel24Bit>,
+
+    pub pixels: Vec<P>,
+}
+
+impl<P: Pixel + core::fmt::Debug> Bitmap<P> {
+    /// Construct a new [Bitmap] from the given dimensions and pixel array.
+    ///
+    /// The pixels are given in the order that they are stored in the file, so for a positive
+    /// height (the default for bitmap files) the first row is the bottom row of the image, and for
+    /// a negative height it is the top row (see [Bitmap::is_top_down]).
+    ///
+    /// The width must be positive and the height must be non-zero.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// assert!(Bitmap::new_from_pixels(2, -2, vec![rgb!(0, 0, 0); 4]).is_ok());
+    ///
+    /// assert!(Bitmap::new_from_pixels(0, 0, vec![rgb!(0, 0, 0); 0]).is_err());
+    /// assert!(Bitmap::new_from_pixels(-5, 10, vec![rgb!(0, 0, 0); 50]).is_err());
+    /// assert!(Bitmap::new_from_pixels(10, 0, vec![rgb!(0, 0, 0); 0]).is_err());
+    /// ```
+    pub fn new_from_pixels(width: i32, height: i32, pixels: Vec<P>) -> Result<Self, Error> {
+        Self::new_from_indexed_pixels(width, height, vec![], pixels)
+    }
+
+    /// Construct a new indexed [Bitmap] from the given dimensions, color table and pixel array.
+    ///
+    /// The color table is written to the file between the headers and the pixel data, so it
+    /// should be empty unless the pixels are [IndexedPixel]s.
+    pub fn new_from_indexed_pixels(width: i32, height: i32, color_table: Vec<Pixel24Bit>, pixels: Vec<P>) -> Result<Self, Error> {
+        Self::check_dimensions(width, height)?;
+
+        let unsigned_abs_height = height.unsigned_abs();
+        if pixels.len() != (width.unsigned_abs() * unsigned_abs_height) as usize {
+            return Err(IllegalParameter("pixel length is not equal to width * height"));
+        }
+
+        if color_table.len() > 256 {
+            return Err(IllegalParameter("color table cannot contain more than 256 colors"));
+        }
+
+        let mut information_header = InformationHeader::new::<P>(width, height);
+        information_header.color_palette_count = color_table.len() as u32;
+        let headers_size = (Header::SIZE + information_header.size as usize + color_table.len() * Self::COLOR_TABLE_ENTRY_SIZE) as u32;
+
+        let (_, padded_bytes_per_image) = Self::compute_padding(width.unsigned_abs(), unsigned_abs_height, P::bits_per_pixel())?;
+
+        Ok(Self {
+            header: Header::new(
+                headers_size + padded_bytes_per_image,
+                headers_size
+            ),
+            information_header,
+            color_table,
+            pixels
+        })
+    }
+
+    /// Construct a new [Bitmap] of the given dimensions by repeating the pattern across it,
+    /// starting from the first pixel, with the tiles at the right and bottom edges clipped to fit.
+    ///
+    /// The color table of the pattern is copied to the new bitmap.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// // A 2x2 checkerboard of black and white.
+    /// let black = rgb!(0, 0, 0);
+    /// let white = rgb!(255, 255, 255);
+    /// let pattern = Bitmap::new_from_pixels(2, 2, vec![black, white, white, black]).unwrap();
+    ///
+    /// let bitmap = Bitmap::tile(&pattern, 5, 5).unwrap();
+    /// assert_eq!((bitmap.get_width(), bitmap.get_height()), (5, 5));
+    ///
+    /// let reds: Vec<Vec<u8>> = bitmap.pixels.chunks(5).map(|row| row.iter().map(|pixel| pixel.red).collect()).collect();
+    /// assert_eq!(reds[0], vec![0, 255, 0, 255, 0]);
+    /// assert_eq!(reds[1], vec![255, 0, 255, 0, 255]);
+    /// assert_eq!(reds[4], vec![0, 255, 0, 255, 0]);
+    /// ```
+    pub fn tile(pattern: &Bitmap<P>, target_width: i32, target_height: i32) -> Result<Self, Error> where P: Clone {
+        let pattern_width = pattern.get_width() as usize;
+        let pattern_height = pattern.get_height() as usize;
+        let width = target_width.unsigned_abs() as usize;
+        let height = target_height.unsigned_abs() as usize;
+
+        if pattern.pixels.is_empty() && width * height > 0 {
+            return Err(IllegalParameter("cannot tile an empty pattern"));
+        }
+
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| pattern.pixels[(y % pattern_height) * pattern_width + x % pattern_width].clone())
+            .collect();
+
+        Self::new_from_indexed_pixels(target_width, target_height, pattern.color_table.clone(), pixels)
+    }
+
+    /// Construct a new [Bitmap] from the given bitmap file bytes.
+    ///
+    /// The length of the bytes is validated against the headers before any pixel data is read, so
+    /// a truncated file (or one with an offset pointing past the end of the file) is rejected with
+    /// an error rather than causing a panic.
+    ///
+    /// Any bytes after the pixel data are ignored, provided that they are either included in the
+    /// file size declared in the header (e.g., an ICC profile) or are a multiple of the padded row
+    /// size. Otherwise, the file is assumed to be corrupt and an error is returned.
+    ///
+    /// Indexed (1bpp, 4bpp or 8bpp) bitmaps, including those compressed with [CompressionMethod::BiRle8], can be
+    /// read either as [IndexedPixel]s (keeping the color table), or as any other pixel format, in
+    /// which case each pixel is expanded through the color table with [Pixel::new_from_color].
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{hex, Bitmap, Pixel24Bit};
+    ///
+    /// let pixels = vec![hex!("#4CAF50").unwrap(); 4];
+    /// let bytes = Bitmap::new_from_pixels(2, 2, pixels).unwrap().to_bytes();
+    ///
+    /// // A truncated pixel array is rejected.
+    /// let truncated = bytes[..bytes.len() - 1].to_vec();
+    /// assert!(Bitmap::<Pixel24Bit>::new_from_bytes(truncated).is_err());
+    ///
+    /// // As is an offset that points past the end of the file.
+    /// let mut bad_offset = bytes.clone();
+    /// bad_offset[10..14].copy_from_slice(&1024u32.to_le_bytes());
+    /// assert!(Bitmap::<Pixel24Bit>::new_from_bytes(bad_offset).is_err());
+    ///
+    /// // Stray trailing bytes are rejected...
+    /// let mut trailing = bytes.clone();
+    /// trailing.extend_from_slice(&[0, 0, 0]);
+    /// assert!(Bitmap::<Pixel24Bit>::new_from_bytes(trailing).is_err());
+    ///
+    /// // ...but trailing metadata that is declared in the file size (e.g., an ICC profile) is not.
+    /// let mut icc_profile = bytes.clone();
+    /// icc_profile.extend_from_slice(b"\0\0\x02\x0cADBE\x02\x10\0\0mntrRGB XYZ ");
+    /// let size = icc_profile.len() as u32;
+    /// icc_profile[2..6].copy_from_slice(&size.to_le_bytes());
+    /// let bitmap = Bitmap::<Pixel24Bit>::new_from_bytes(icc_profile).unwrap();
+    /// assert_eq!(bitmap.pixels.len(), 4);
+    /// ```
+    ///
+    /// Files with a `BITMAPV4HEADER` or `BITMAPV5HEADER` (as exported by most modern tools, such
+    /// as Photoshop) can also be read:
+    ///
+    /// ```rust
+    /// use bitmap_rs::{hex, Bitmap, InformationHeader, Pixel24Bit};
+    ///
+    /// let pixels = vec![hex!("#4CAF50").unwrap(); 4];
+    /// let mut bytes = Bitmap::new_from_pixels(2, 2, pixels).unwrap().to_bytes();
+    ///
+    /// // Extend the DIB header to a V5 header with the sRGB color space.
+    /// let extra_size = InformationHeader::V5_SIZE - InformationHeader::SIZE;
+    /// let mut extension = vec![0u8; extra_size];
+    /// extension[16..20].copy_from_slice(b"BGRs");
+    /// bytes.splice(54..54, extension);
+    /// let size = bytes.len() as u32;
+    /// bytes[2..6].copy_from_slice(&size.to_le_bytes());
+    /// bytes[10..14].copy_from_slice(&(54 + extra_size as u32).to_le_bytes());
+    /// bytes[14..18].copy_from_slice(&(InformationHeader::V5_SIZE as u32).to_le_bytes());
+    ///
+    /// let bitmap = Bitmap::<Pixel24Bit>::new_from_bytes(bytes).unwrap();
+    /// let color_space = bitmap.information_header.color_space.as_ref().unwrap();
+    /// assert_eq!(&color_space.color_space_type.to_le_bytes(), b"BGRs");
+    /// assert!(color_space.profile.is_some());
+    /// assert_eq!(bitmap.pixels.len(), 4);
+    /// ```
+    pub fn new_from_bytes(bytes: Vec<u8>) -> Result<Bitmap<P>, Error> {
+        let (header, information_header) = read_headers(&bytes)?;
+        let information_header_size = information_header.size as usize;
+
+        if (header.size as usize) > bytes.len() {
+            return Err(IllegalParameter("bitmap file is shorter than the size declared in its header"));
+        }
+
+        if (header.offset as usize) < Header::SIZE + information_header_size || (header.offset as usize) > bytes.len() {
+            return Err(IllegalParameter("bitmap pixel data offset points outside of the file"));
+        }
+
+        let bit_masks = BitMasks::new_from_bytes(&bytes, &information_header)?;
+        let color_table = Self::read_color_table(&bytes, &information_header, bit_masks.is_some(), header.offset as usize)?;
+
+        let width = information_header.width.unsigned_abs();
+        let height = information_header.height.unsigned_abs();
+        let bits_per_pixel = information_header.bits_per_pixel;
+
+        let pixels = match information_header.compression_method {
+            CompressionMethod::BiRle8 => {
+                let indices = decode_rle8(&bytes[(header.offset as usize)..], width, height)?;
+                Self::pixels_from_indices(&indices, bits_per_pixel, &color_table)?
+            },
+
+            CompressionMethod::BiRgb | CompressionMethod::BiBitfields => {
+                let rows = Self::pixel_rows(&bytes, &header, width, height, bits_per_pixel)?;
+
+                if bits_per_pixel <= 8 {
+                    let indices: Vec<u8> = rows.flat_map(|row| Self::unpack_indices(row, width, bits_per_pixel)).collect();
+                    Self::pixels_from_indices(&indices, bits_per_pixel, &color_table)?
+                } else if bits_per_pixel == 16 {
+                    // Without bit masks, 16bpp pixels default to 5 bits for each channel.
+                    let bit_masks = bit_masks.unwrap_or(BitMasks::RGB555);
+
+                    rows.flat_map(|row| row.chunks_exact(2))
+                        .map(|pixel| P::new_from_color(&bit_masks.unpack(u16::from_le_bytes([pixel[0], pixel[1]]))))
+                        .collect::<Result<Vec<P>, Error>>()?
+                } else {
+                    if bits_per_pixel != P::bits_per_pixel() {
+                        return Err(Unsupported("bitmap bits per pixel does not match the requested pixel format"));
+                    }
+
+                    let bytes_per_pixel = bits_per_pixel.div_ceil(8) as usize;
+                    let mut pixels = Vec::with_capacity((width * height) as usize);
+                    let mut has_bad_pixel = false;
+
+                    rows.for_each(|row| {
+                        row.chunks_exact(bytes_per_pixel).for_each(|pixel| {
+                            if let Ok(pixel) = P::new_from_bytes(pixel).map_err(|_| has_bad_pixel = true) {
+                                pixels.push(pixel);
+                            }
+                        });
+                    });
+
+                    if has_bad_pixel {
+                        return Err(IllegalParameter("bad pixel data"));
+                    }
+
+                    pixels
+                }
+            },
+        };
+
+        // If the pixels were decompressed or expanded through the color table, they no longer
+        // match the headers that were read, so the bitmap is rebuilt from the pixels instead.
+        let is_expanded = bits_per_pixel != P::bits_per_pixel();
+        if is_expanded || !matches!(information_header.compression_method, CompressionMethod::BiRgb) {
+            let color_table = if is_expanded { vec![] } else { color_table };
+            let mut bitmap = Self::new_from_indexed_pixels(information_header.width, information_header.height, color_table, pixels)?;
+            bitmap.information_header.horizontal_resolution = information_header.horizontal_resolution;
+            bitmap.information_header.vertical_resolution = information_header.vertical_resolution;
+            return Ok(bitmap);
+        }
+
+        Ok(Bitmap {
+            header,
+            information_header,
+            color_table,
+            pixels
+        })
+    }
+
+    /// The size of each entry in the color table of a bitmap file, in bytes.
+    const COLOR_TABLE_ENTRY_SIZE: usize = 4;
+
+    /// Read the color table that sits between the headers and the pixel data of the bitmap file.
+    fn read_color_table(bytes: &[u8], information_header: &InformationHeader, has_bit_masks: bool, offset: usize) -> Result<Vec<Pixel24Bit>, Error> {
+        let color_count = match information_header.color_palette_count {
+            0 if information_header.bits_per_pixel <= 8 => 1 << information_header.bits_per_pixel,
+            color_count => color_count as usize,
+        };
+
+        // The color table follows the bit masks, if they're stored after the DIB header.
+        let mut start = Header::SIZE + information_header.size as usize;
+        if has_bit_masks && information_header.color_space.is_none() {
+            start += BitMasks::SIZE;
+        }
+
+        let end = start + color_count * Self::COLOR_TABLE_ENTRY_SIZE;
+        if end > offset {
+            return Err(IllegalParameter("bitmap color table overlaps the pixel data"));
+        }
+
+        // Each entry is stored in the same order as a pixel, followed by a reserved byte.
+        bytes[start..end]
+            .chunks_exact(Self::COLOR_TABLE_ENTRY_SIZE)
+            .map(|entry| Pixel24Bit::new_from_bytes(&entry[0..3]))
+            .collect()
+    }
+
+    /// Split the uncompressed pixel data of the bitmap file into rows (with the padding removed),
+    /// after validating that the length of the file is consistent with the image dimensions.
+    fn pixel_rows<'a>(bytes: &'a [u8], header: &Header, width: u32, height: u32, bits_per_pixel: u16) -> Result<impl Iterator<Item = &'a [u8]>, Error> {
+        let (padding_bytes_per_row, padded_bytes_per_image) = Self::compute_padding(width, height, bits_per_pixel)?;
+        let bytes_per_row = (width as usize * bits_per_pixel as usize).div_ceil(8);
+        let bytes_per_padded_row = bytes_per_row + padding_bytes_per_row as usize;
+
+        if bytes.len() - (header.offset as usize) < padded_bytes_per_image as usize {
+            return Err(IllegalParameter("bitmap pixel data is shorter than the image dimensions require"));
+        }
+
+        // Some writers append metadata (such as an ICC profile) after the pixel data. That's fine
+        // if the file size in the header accounts for it, or if it's made up of whole padded rows,
+        // but anything else suggests the pixel data itself is corrupt.
+        let pixel_data_end = header.offset as usize + padded_bytes_per_image as usize;
+        let trailing_byte_count = bytes.len() - pixel_data_end;
+        if !trailing_byte_count.is_multiple_of(bytes_per_padded_row) && header.size as usize != bytes.len() {
+            return Err(IllegalParameter("unexpected trailing bytes after the bitmap pixel data"));
+        }
+
+        Ok(bytes[(header.offset as usize)..pixel_data_end]
+            .chunks_exact(bytes_per_padded_row)
+            .map(move |row| &row[0..bytes_per_row]))
+    }
+
+    /// Unpack the color table indices from a row of an indexed bitmap, where each byte may contain
+    /// several indices (starting from the most significant bits).
+    fn unpack_indices(row: &[u8], width: u32, bits_per_pixel: u16) -> impl Iterator<Item = u8> {
+        let bits_per_pixel = bits_per_pixel as usize;
+        let mask = ((1u16 << bits_per_pixel) - 1) as u8;
+
+        (0..width as usize).map(move |x| {
+            let bit = x * bits_per_pixel;
+            (row[bit / 8] >> (8 - bits_per_pixel - bit % 8)) & mask
+        })
+    }
+
+    /// Serialize a row of pixels, packing several indices into each byte for indexed pixel formats
+    /// with fewer than 8 bits per pixel.
+    fn pack_row(row: &[P]) -> Vec<u8> {
+        let bits_per_pixel = P::bits_per_pixel() as usize;
+        if bits_per_pixel >= 8 {
+            return row.iter().flat_map(Pixel::to_bytes).collect();
+        }
+
+        let mut bytes = vec![0u8; (row.len() * bits_per_pixel).div_ceil(8)];
+        for (x, pixel) in row.iter().enumerate() {
+            let bit = x * bits_per_pixel;
+            bytes[bit / 8] |= pixel.to_bytes()[0] << (8 - bits_per_pixel - bit % 8);
+        }
+
+        bytes
+    }
+
+    /// Convert the color table indices of an indexed bitmap into pixels; either by keeping the
+    /// indices (if the pixel format is indexed with the same bits per pixel) or by expanding them
+    /// through the color table.
+    fn pixels_from_indices(indices: &[u8], bits_per_pixel: u16, color_table: &[Pixel24Bit]) -> Result<Vec<P>, Error> {
+        if bits_per_pixel == P::bits_per_pixel() {
+            return indices.iter().map(|&index| P::new_from_bytes(&[index])).collect();
+        }
+
+        indices.iter()
+            .map(|&index| {
+                let color = color_table.get(index as usize)
+                    .ok_or(IllegalParameter("pixel refers to a color that is not in the color table"))?;
+
+                P::new_from_color(color)
+            })
+            .collect()
+    }
+
+    /// Get the width of the image, in pixels.
+    pub fn get_width(&self) -> u32 {
+        self.get_raw_width().unsigned_abs()
+    }
+
+    /// Get the height of the image, in pixels.
+    pub fn get_height(&self) -> u32 {
+        self.get_raw_height().unsigned_abs()
+    }
+
+    /// Get the raw width of the image.
+    pub fn get_raw_width(&self) -> i32 {
+        self.information_header.width
+    }
+
+    /// Get the raw height of the image.
+    ///
+    /// Negative means the rows are stored top-to-bottom, positive means the rows are stored
+    /// bottom-to-top.
+    pub fn get_raw_height(&self) -> i32 {
+        self.information_header.height
+    }
+
+    /// Whether the rows of the image are stored top-to-bottom (i.e., the height is negative), so
+    /// that the first row of [Bitmap::pixels] is the top row of the image.
+    pub fn is_top_down(&self) -> bool {
+        self.get_raw_height() < 0
+    }
+
+    /// Set whether the rows of the image are stored top-to-bottom, reordering the rows so that the
+    /// image itself is unchanged.
+    ///
+    /// As the pixel coordinates are in the order that the rows are stored, this moves each pixel
+    /// to the opposite row.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// let pixels = (0..6).map(|i| rgb!(i, 0, 0)).collect();
+    /// let mut bitmap = Bitmap::new_from_pixels(2, 3, pixels).unwrap();
+    /// let original = bitmap.to_bytes();
+    ///
+    /// // The last row of a bottom-up image is the top row.
+    /// assert!(!bitmap.is_top_down());
+    /// let top_left = bitmap.get_pixel_at(0, 2).unwrap().red;
+    ///
+    /// bitmap.set_top_down(true);
+    /// assert!(bitmap.is_top_down());
+    /// assert_eq!(bitmap.get_raw_height(), -3);
+    /// assert_eq!(bitmap.get_pixel_at(0, 0).unwrap().red, top_left);
+    ///
+    /// // Toggling back restores the original bitmap.
+    /// bitmap.set_top_down(false);
+    /// assert_eq!(bitmap.get_pixel_at(0, 0).unwrap().red, 0);
+    /// assert_eq!(bitmap.to_bytes(), original);
+    /// ```
+    pub fn set_top_down(&mut self, top_down: bool) {
+        if self.is_top_down() == top_down {
+            return;
+        }
+
+        self.information_header.height = -self.information_header.height;
+
+        // Reverse the order of the rows, keeping the order of the pixels within each row.
+        let width = self.get_width() as usize;
+        self.pixels.reverse();
+        for row in self.pixels.chunks_exact_mut(width.max(1)) {
+            row.reverse();
+        }
+    }
+
+    /// Get the index in [Bitmap::pixels] of the pixel at the given coordinates, or `None` if the
+    /// coordinates are out-of-bounds.
+    ///
+    /// The pixels are stored row by row in the order that they appear in the file, so `(0, 0)` is
+    /// the first pixel in the file (the bottom-left pixel of a bottom-up bitmap) and the pixel at
+    /// `(x, y)` is at index `y * width + x`. [Bitmap::coords_of] is the inverse.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// let bitmap = Bitmap::new_from_pixels(4, 3, vec![rgb!(0, 0, 0); 12]).unwrap();
+    /// assert_eq!(bitmap.index_of(1, 2), Some(9));
+    /// assert_eq!(bitmap.index_of(4, 0), None);
+    ///
+    /// for index in 0..bitmap.pixels.len() {
+    ///     let (x, y) = bitmap.coords_of(index);
+    ///     assert_eq!(bitmap.index_of(x, y), Some(index));
+    /// }
+    /// ```
+    pub fn index_of(&self, x: u32, y: u32) -> Option<usize> {
         let width = self.get_width();
         let height = self.get_height();
 
@@ -432,34 +1923,483 @@ impl<P: Pixel + std::fmt::Debug> Bitmap<P> {
             return None;
         }
 
-        Some(&self.pixels[((y * width) + x) as usize])
+        Some(y as usize * width as usize + x as usize)
+    }
+
+    /// Get the `(x, y)` coordinates of the pixel at the given index in [Bitmap::pixels].
+    ///
+    /// This is the inverse of [Bitmap::index_of]; the index is not checked against the number of
+    /// pixels.
+    pub fn coords_of(&self, index: usize) -> (u32, u32) {
+        let width = self.get_width() as usize;
+        (index.checked_rem(width).unwrap_or(0) as u32, index.checked_div(width).unwrap_or(0) as u32)
+    }
+
+    /// Get the pixel at the given coordinates.
+    pub fn get_pixel_at(&self, x: u32, y: u32) -> Option<&P> {
+        self.index_of(x, y).map(|index| &self.pixels[index])
+    }
+
+    /// Get the pixel at the given coordinates, returning an error that identifies the coordinate
+    /// if it is out-of-bounds.
+    ///
+    /// Prefer [Bitmap::get_pixel_at] in hot paths where the error would be discarded anyway.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{hex, Bitmap};
+    ///
+    /// let bitmap = Bitmap::new_from_pixels(2, 2, vec![hex!("#4CAF50").unwrap(); 4]).unwrap();
+    /// assert!(bitmap.try_get_pixel_at(1, 1).is_ok());
+    ///
+    /// let err = bitmap.try_get_pixel_at(2, 1).unwrap_err();
+    /// assert_eq!(err.to_string(), "coordinate out of bounds: (2, 1)");
+    /// ```
+    pub fn try_get_pixel_at(&self, x: u32, y: u32) -> Result<&P, Error> {
+        self.get_pixel_at(x, y).ok_or(CoordinateOutOfBounds(x, y))
+    }
+
+    /// Get the coordinates of the pixel at the normalized `(x, y)` coordinate, where `(0.0, 0.0)`
+    /// is the first pixel and coordinates in `[0.0, 1.0)` cover the whole image (in the same
+    /// orientation as [Bitmap::get_pixel_at]).
+    ///
+    /// Coordinates outside of that range are mapped onto the image according to `edge`. Returns
+    /// `None` if either coordinate isn't finite, or the image has no pixels.
+    pub fn normalized_to_coords(&self, x: f64, y: f64, edge: EdgeMode) -> Option<(u32, u32)> {
+        Some((edge.cell(x, self.get_width())?, edge.cell(y, self.get_height())?))
+    }
+
+    /// Get the pixel at the normalized `(x, y)` coordinate (see [Bitmap::normalized_to_coords]).
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap, EdgeMode};
+    ///
+    /// let bitmap = Bitmap::new_from_pixels(2, 2, vec![rgb!(0, 0, 0), rgb!(1, 1, 1), rgb!(2, 2, 2), rgb!(3, 3, 3)]).unwrap();
+    /// assert_eq!(bitmap.sample_normalized(0.0, 0.0, EdgeMode::Clamp).unwrap().red, 0);
+    /// assert_eq!(bitmap.sample_normalized(0.999, 0.999, EdgeMode::Clamp).unwrap().red, 3);
+    /// assert_eq!(bitmap.sample_normalized(0.5, 0.25, EdgeMode::Clamp).unwrap().red, 1);
+    ///
+    /// // Out-of-range coordinates are clamped to the edge, or wrap around.
+    /// assert_eq!(bitmap.sample_normalized(1.5, -0.25, EdgeMode::Clamp).unwrap().red, 1);
+    /// assert_eq!(bitmap.sample_normalized(1.25, -0.25, EdgeMode::Wrap).unwrap().red, 2);
+    ///
+    /// assert!(bitmap.sample_normalized(f64::NAN, 0.0, EdgeMode::Clamp).is_none());
+    /// ```
+    pub fn sample_normalized(&self, x: f64, y: f64, edge: EdgeMode) -> Option<&P> {
+        let (x, y) = self.normalized_to_coords(x, y, edge)?;
+        self.get_pixel_at(x, y)
+    }
+
+    /// Find the location of every pixel in this bitmap that exactly equals the given color, in
+    /// order.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{hex, rgb, Bitmap};
+    ///
+    /// let green = hex!("#4CAF50").unwrap();
+    /// let mut pixels = vec![rgb!(0, 0, 0); 12];
+    /// pixels[1] = green;
+    /// pixels[6] = green;
+    /// pixels[11] = green;
+    /// let bitmap = Bitmap::new_from_pixels(4, 3, pixels).unwrap();
+    ///
+    /// assert_eq!(bitmap.positions_of(&green), vec![(1, 0), (2, 1), (3, 2)]);
+    /// assert!(bitmap.positions_of(&rgb!(255, 255, 255)).is_empty());
+    /// ```
+    pub fn positions_of(&self, color: &P) -> Vec<(u32, u32)> {
+        let color = color.to_bytes();
+
+        self.pixels.iter()
+            .enumerate()
+            .filter(|(_, pixel)| pixel.to_bytes() == color)
+            .map(|(i, _)| self.coords_of(i))
+            .collect()
+    }
+
+    /// Create a new bitmap by applying the function to each pixel and its `(x, y)` coordinates.
+    ///
+    /// The headers and color table are copied from this bitmap.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// let bitmap = Bitmap::new_from_pixels(4, 2, vec![rgb!(0, 0, 0); 8]).unwrap();
+    ///
+    /// // A horizontal gradient from black to red.
+    /// let gradient = bitmap.map_pixels(|x, _, _| rgb!((x * 85) as u8, 0, 0));
+    /// assert_eq!(gradient.pixels.iter().map(|pixel| pixel.to_rgb()[0]).collect::<Vec<_>>(), vec![0, 85, 170, 255, 0, 85, 170, 255]);
+    /// ```
+    pub fn map_pixels<F: Fn(u32, u32, &P) -> P>(&self, f: F) -> Self {
+        let pixels = self.pixels.iter()
+            .enumerate()
+            .map(|(i, pixel)| {
+                let (x, y) = self.coords_of(i);
+                f(x, y, pixel)
+            })
+            .collect();
+
+        Self {
+            header: self.header.clone(),
+            information_header: self.information_header.clone(),
+            color_table: self.color_table.clone(),
+            pixels,
+        }
+    }
+
+    /// Create a new bitmap in another pixel format, by converting each pixel with
+    /// [ConvertPixel].
+    ///
+    /// The dimensions, orientation and resolution are copied from this bitmap.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap, Pixel24Bit, Pixel48Bit};
+    ///
+    /// let bitmap = Bitmap::new_from_pixels(2, -1, vec![rgb!(0, 0, 0), rgb!(76, 175, 80)]).unwrap();
+    /// let wide: Bitmap<Pixel48Bit> = bitmap.convert_to().unwrap();
+    /// assert_eq!(wide.information_header.bits_per_pixel, 48);
+    /// assert!(wide.is_top_down());
+    ///
+    /// let narrow = wide.convert_to::<Pixel24Bit>().unwrap();
+    /// assert_eq!(narrow.to_bytes(), bitmap.to_bytes());
+    /// ```
+    pub fn convert_to<To: Pixel + core::fmt::Debug>(&self) -> Result<Bitmap<To>, Error> where P: ConvertPixel<To> {
+        let pixels = self.pixels.iter().map(ConvertPixel::convert).collect();
+
+        let mut bitmap = Bitmap::new_from_pixels(self.get_raw_width(), self.get_raw_height(), pixels)?;
+        bitmap.information_header.horizontal_resolution = self.information_header.horizontal_resolution;
+        bitmap.information_header.vertical_resolution = self.information_header.vertical_resolution;
+        Ok(bitmap)
+    }
+
+    /// Apply the function to each pixel and its `(x, y)` coordinates in place, without allocating
+    /// a new bitmap as [Bitmap::map_pixels] does.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap, Pixel24Bit};
+    ///
+    /// let mut bitmap = Bitmap::new_from_pixels(4, 2, vec![rgb!(10, 20, 30); 8]).unwrap();
+    /// let mapped = bitmap.map_pixels(|x, y, pixel| Pixel24Bit { red: pixel.red + x as u8, green: pixel.green + y as u8, ..*pixel });
+    ///
+    /// bitmap.map_pixels_mut(|x, y, pixel| {
+    ///     pixel.red += x as u8;
+    ///     pixel.green += y as u8;
+    /// });
+    /// assert_eq!(bitmap.to_bytes(), mapped.to_bytes());
+    /// ```
+    pub fn map_pixels_mut<F: FnMut(u32, u32, &mut P)>(&mut self, mut f: F) {
+        for i in 0..self.pixels.len() {
+            let (x, y) = self.coords_of(i);
+            f(x, y, &mut self.pixels[i]);
+        }
+    }
+
+    /// Replace every pixel within `tolerance` of the `from` color (by [Pixel::difference]) with
+    /// the `to` color, where a tolerance of zero replaces only exact matches.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{hex, rgb, Bitmap, Pixel24Bit};
+    ///
+    /// // A slightly noisy green background, with a darker green logo in the middle.
+    /// let background = hex!("#4CAF50").unwrap();
+    /// let logo = rgb!(0, 100, 0);
+    /// let pixels = (0..25)
+    ///     .map(|i| match i {
+    ///         12 => logo,
+    ///         _ => Pixel24Bit { red: background.red + (i % 3) as u8, ..background },
+    ///     })
+    ///     .collect();
+    /// let mut bitmap = Bitmap::new_from_pixels(5, 5, pixels).unwrap();
+    ///
+    /// let white = rgb!(255, 255, 255);
+    /// bitmap.replace_color(background, white, 10.0);
+    ///
+    /// assert_eq!(bitmap.positions_of(&white).len(), 24);
+    /// assert_eq!(bitmap.positions_of(&logo), vec![(2, 2)]);
+    /// ```
+    pub fn replace_color(&mut self, from: P, to: P, tolerance: f64) where P: Clone {
+        for pixel in &mut self.pixels {
+            if pixel.difference(&from) <= tolerance {
+                *pixel = to.clone();
+            }
+        }
+    }
+
+    /// Draw a grid over this bitmap, by setting every pixel in every `spacing`th row and column
+    /// (starting from the first) to `color`.
+    ///
+    /// A spacing of 0 draws nothing.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// let red = rgb!(255, 0, 0);
+    /// let mut bitmap = Bitmap::new_from_pixels(7, 5, vec![rgb!(0, 0, 0); 35]).unwrap();
+    /// bitmap.draw_grid(3, red);
+    ///
+    /// let mut grid = bitmap.positions_of(&red);
+    /// grid.sort();
+    /// let expected: Vec<(u32, u32)> = (0..7).flat_map(|x| (0..5).map(move |y| (x, y)))
+    ///     .filter(|(x, y)| x % 3 == 0 || y % 3 == 0)
+    ///     .collect();
+    /// assert_eq!(grid, expected);
+    /// ```
+    pub fn draw_grid(&mut self, spacing: u32, color: P) where P: Clone {
+        if spacing == 0 {
+            return;
+        }
+
+        self.map_pixels_mut(|x, y, pixel| {
+            if x.is_multiple_of(spacing) || y.is_multiple_of(spacing) {
+                *pixel = color.clone();
+            }
+        });
+    }
+
+    /// Find the bounds of the content of this bitmap: the pixels that differ from `background` by
+    /// more than `tolerance`.
+    ///
+    /// The bounds are returned as `(min_x, min_y, max_x, max_y)`, inclusive, or [None] if every
+    /// pixel is background.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// // A 2x3 red square on a white field.
+    /// let white = rgb!(255, 255, 255);
+    /// let pixels = (0..64).map(|i| if (3..5).contains(&(i % 8)) && (2..5).contains(&(i / 8)) { rgb!(255, 0, 0) } else { white }).collect();
+    /// let bitmap = Bitmap::new_from_pixels(8, 8, pixels).unwrap();
+    ///
+    /// assert_eq!(bitmap.content_bounds(white, 0.0), Some((3, 2, 4, 4)));
+    /// assert_eq!(bitmap.content_bounds(rgb!(255, 128, 128), 200.0), None);
+    /// ```
+    pub fn content_bounds(&self, background: P, tolerance: f64) -> Option<(u32, u32, u32, u32)> {
+        self.pixels.iter()
+            .enumerate()
+            .filter(|(_, pixel)| pixel.difference(&background) > tolerance)
+            .map(|(i, _)| self.coords_of(i))
+            .fold(None, |bounds, (x, y)| match bounds {
+                Some((min_x, min_y, max_x, max_y)) => Some((x.min(min_x), y.min(min_y), x.max(max_x), y.max(max_y))),
+                None => Some((x, y, x, y)),
+            })
+    }
+
+    /// Crop away the border of this bitmap that is within `tolerance` of `background`, leaving
+    /// the smallest region that contains all of the content (see [Bitmap::content_bounds]).
+    ///
+    /// A bitmap that is entirely background is returned unchanged. The orientation and color
+    /// table are copied from this bitmap.
+    ///
+    /// Returns an error if the headers can't be recomputed for the trimmed region (see
+    /// [Bitmap::recompute_headers]), e.g., if the color table has been filled beyond 256 colors.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// // A 3x2 logo with 2 pixels of white padding on every side.
+    /// let white = rgb!(255, 255, 255);
+    /// let logo = [rgb!(255, 0, 0), rgb!(0, 255, 0), rgb!(0, 0, 255), white, rgb!(0, 0, 0), rgb!(255, 0, 0)];
+    /// let pixels = (0..42)
+    ///     .map(|i| {
+    ///         let (x, y) = (i % 7, i / 7);
+    ///         if (2..5).contains(&x) && (2..4).contains(&y) { logo[(y - 2) * 3 + x - 2] } else { white }
+    ///     })
+    ///     .collect();
+    /// let bitmap = Bitmap::new_from_pixels(7, 6, pixels).unwrap();
+    ///
+    /// let trimmed = bitmap.auto_trim(white, 0.0).unwrap();
+    /// assert_eq!((trimmed.get_width(), trimmed.get_height()), (3, 2));
+    /// assert_eq!(trimmed.to_bytes(), Bitmap::new_from_pixels(3, 2, logo.to_vec()).unwrap().to_bytes());
+    ///
+    /// let blank = Bitmap::new_from_pixels(4, 4, vec![white; 16]).unwrap();
+    /// assert_eq!(blank.auto_trim(white, 0.0).unwrap().to_bytes(), blank.to_bytes());
+    ///
+    /// let mut bitmap = bitmap;
+    /// bitmap.color_table = vec![white; 257];
+    /// assert!(bitmap.auto_trim(white, 0.0).is_err());
+    /// ```
+    pub fn auto_trim(&self, background: P, tolerance: f64) -> Result<Self, Error> where P: Clone {
+        let Some((min_x, min_y, max_x, max_y)) = self.content_bounds(background, tolerance) else {
+            return Ok(self.map_pixels(|_, _, pixel| pixel.clone()));
+        };
+
+        let (width, height) = (max_x - min_x + 1, max_y - min_y + 1);
+        let pixels = self.pixels.chunks_exact(self.get_width() as usize)
+            .skip(min_y as usize)
+            .take(height as usize)
+            .flat_map(|row| row[min_x as usize..=max_x as usize].iter().cloned())
+            .collect();
+
+        let mut trimmed = Self {
+            header: self.header.clone(),
+            information_header: self.information_header.clone(),
+            color_table: self.color_table.clone(),
+            pixels,
+        };
+
+        let height = if self.is_top_down() { -(height as i32) } else { height as i32 };
+        trimmed.recompute_headers(width as i32, height)?;
+        Ok(trimmed)
+    }
+
+    /// Get each distinct color in this bitmap, in the order that it first appears, with the
+    /// location of its first pixel.
+    ///
+    /// Searching these colors in order for the closest match finds the same location as
+    /// [Bitmap::find_pixel_by_closest_match], but without scanning every pixel of large regions of
+    /// the same color.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// let (black, white) = (rgb!(0, 0, 0), rgb!(255, 255, 255));
+    /// let bitmap = Bitmap::new_from_pixels(3, 2, vec![black, black, white, white, black, white]).unwrap();
+    ///
+    /// let unique: Vec<(u32, u32)> = bitmap.unique_palette().into_iter().map(|(_, location)| location).collect();
+    /// assert_eq!(unique, vec![(0, 0), (2, 0)]);
+    /// ```
+    pub fn unique_palette(&self) -> Vec<(P, (u32, u32))> where P: Clone {
+        let mut seen_colors: BTreeSet<Vec<u8>> = BTreeSet::new();
+
+        self.pixels.iter()
+            .enumerate()
+            .filter(|(_, pixel)| seen_colors.insert(pixel.to_bytes()))
+            .map(|(i, pixel)| (pixel.clone(), self.coords_of(i)))
+            .collect()
     }
 
     /// Find the location of the pixel in this bitmap with the closest match to the specified other
     /// pixel.
     pub fn find_pixel_by_closest_match(&self, other: &P) -> Option<(u32, u32)> {
-        let width = self.get_width();
+        self.find_pixel_by_closest_match_with(other, P::difference)
+    }
 
+    /// Find the location of the pixel in this bitmap with the closest match to the specified other
+    /// pixel, measuring the difference between pixels with the given function.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap, Pixel24Bit};
+    ///
+    /// let palette = Bitmap::new_from_pixels(2, 1, vec![rgb!(128, 138, 128), rgb!(140, 128, 128)]).unwrap();
+    /// let gray = rgb!(128, 128, 128);
+    ///
+    /// assert_eq!(palette.find_pixel_by_closest_match(&gray), Some((0, 0)));
+    /// assert_eq!(palette.find_pixel_by_closest_match_with(&gray, Pixel24Bit::difference_redmean), Some((1, 0)));
+    /// ```
+    pub fn find_pixel_by_closest_match_with(&self, other: &P, difference: impl Fn(&P, &P) -> f64) -> Option<(u32, u32)> {
         let mut best_match_difference: f64 = f64::INFINITY;
         let mut best_match_location: Option<(u32, u32)> = None;
 
-        for (y, row) in self.pixels.chunks_exact(width as usize).enumerate() {
-            for (x, current_pixel) in row.iter().enumerate() {
-                let new_difference = current_pixel.difference(other);
-                if new_difference < best_match_difference {
-                    best_match_difference = new_difference;
-                    best_match_location = Some((x as u32, y as u32));
-                }
+        for (i, current_pixel) in self.pixels.iter().enumerate() {
+            let new_difference = difference(current_pixel, other);
+            if new_difference < best_match_difference {
+                best_match_difference = new_difference;
+                best_match_location = Some(self.coords_of(i));
             }
         }
 
         best_match_location
     }
 
-    fn compute_padding(pixel_count: u32, unsigned_abs_height: u32) -> (u32, u32) {
+    /// Find the location of the pixel in this bitmap with the closest match to the specified other
+    /// pixel, breaking ties between equally close pixels by choosing the one nearest to `prefer`
+    /// (rather than the first).
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// // The same red in the first and last pixels.
+    /// let red = rgb!(255, 0, 0);
+    /// let palette = Bitmap::new_from_pixels(3, 3, (0..9).map(|i| if i == 0 || i == 8 { red } else { rgb!(0, 0, 0) }).collect()).unwrap();
+    ///
+    /// assert_eq!(palette.find_pixel_by_closest_match(&red), Some((0, 0)));
+    /// assert_eq!(palette.find_pixel_by_closest_match_biased(&red, (2, 1)), Some((2, 2)));
+    /// assert_eq!(palette.find_pixel_by_closest_match_biased(&red, (1, 1)), Some((0, 0)));
+    /// ```
+    pub fn find_pixel_by_closest_match_biased(&self, other: &P, prefer: (u32, u32)) -> Option<(u32, u32)> {
+        self.find_pixel_by_closest_match_biased_with(other, prefer, P::difference)
+    }
+
+    /// Find the location of the pixel in this bitmap with the closest match to the specified other
+    /// pixel (measuring the difference between pixels with the given function), breaking ties by
+    /// choosing the pixel nearest to `prefer`.
+    pub fn find_pixel_by_closest_match_biased_with(&self, other: &P, prefer: (u32, u32), difference: impl Fn(&P, &P) -> f64) -> Option<(u32, u32)> {
+        // The squared Euclidean distance between the location and the preferred location.
+        let distance_to_preferred = |(x, y): (u32, u32)| {
+            let (dx, dy) = (u64::from(x.abs_diff(prefer.0)), u64::from(y.abs_diff(prefer.1)));
+            dx * dx + dy * dy
+        };
+
+        let mut best_match: Option<(f64, u64, (u32, u32))> = None;
+
+        for (i, current_pixel) in self.pixels.iter().enumerate() {
+            let new_difference = difference(current_pixel, other);
+            if new_difference.is_nan() || best_match.is_some_and(|(best_difference, _, _)| new_difference > best_difference) {
+                continue;
+            }
+
+            let location = self.coords_of(i);
+            let distance = distance_to_preferred(location);
+            let is_better = match best_match {
+                Some((best_difference, best_distance, _)) => new_difference < best_difference || distance < best_distance,
+                None => new_difference < f64::INFINITY,
+            };
+
+            if is_better {
+                best_match = Some((new_difference, distance, location));
+            }
+        }
+
+        best_match.map(|(_, _, location)| location)
+    }
+
+    /// Check that the dimensions of a new bitmap have a positive width and a non-zero height.
+    fn check_dimensions(width: i32, height: i32) -> Result<(), Error> {
+        if width <= 0 {
+            return Err(IllegalParameter("bitmap width must be positive"));
+        }
+
+        if height == 0 {
+            return Err(IllegalParameter("bitmap height must be non-zero"));
+        }
+
+        Ok(())
+    }
+
+    /// Compute the number of bytes of padding after each row of pixels, and the size of the
+    /// padded pixel data, for an image of the given dimensions.
+    fn compute_padding(width: u32, unsigned_abs_height: u32, bits_per_pixel: u16) -> Result<(u32, u32), Error> {
+        if unsigned_abs_height == 0 {
+            return Err(IllegalParameter("bitmap height must be non-zero"));
+        }
+
         // Each row must begin at a memory address that is a multiple of four.
-        let bytes_per_image = pixel_count * (P::bits_per_pixel() as u32).div_ceil(8);
-        let bytes_per_row = bytes_per_image / unsigned_abs_height;
+        let bytes_per_row = u32::try_from((u64::from(width) * u64::from(bits_per_pixel)).div_ceil(8))
+            .map_err(|_| IllegalParameter("bitmap is too wide"))?;
 
         // The padding is the amount needed to ensure the number of bytes per row is divisible by 4.
         let row_remainder = bytes_per_row % 4;
@@ -471,32 +2411,322 @@ impl<P: Pixel + std::fmt::Debug> Bitmap<P> {
 
         // Re-compute the row and image with the padding applied.
         let bytes_per_padded_row = bytes_per_row + padding_bytes_per_row;
-        let bytes_per_padded_image = bytes_per_padded_row * unsigned_abs_height;
-        (padding_bytes_per_row, bytes_per_padded_image)
+        let bytes_per_padded_image = bytes_per_padded_row.checked_mul(unsigned_abs_height)
+            .ok_or(IllegalParameter("bitmap is too large"))?;
+        Ok((padding_bytes_per_row, bytes_per_padded_image))
+    }
+
+    /// Check that the headers of the bitmap are consistent with its pixels and color table, i.e.,
+    /// that [Bitmap::to_bytes] will produce a valid bitmap file.
+    ///
+    /// This is useful after modifying the public fields of the bitmap directly.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// let mut bitmap = Bitmap::new_from_pixels(3, 2, vec![rgb!(0, 0, 0); 6]).unwrap();
+    /// assert!(bitmap.validate().is_ok());
+    ///
+    /// // A pixel is removed without updating the headers.
+    /// bitmap.pixels.pop();
+    /// assert!(bitmap.validate().is_err());
+    /// bitmap.pixels.push(rgb!(0, 0, 0));
+    ///
+    /// // The file size no longer matches the size of the pixel data.
+    /// bitmap.header.size += 4;
+    /// assert!(bitmap.validate().is_err());
+    /// bitmap.header.size -= 4;
+    ///
+    /// bitmap.information_header.color_plane_count = 2;
+    /// assert!(bitmap.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), Error> {
+        let width = self.get_width();
+        let height = self.get_height();
+        if self.pixels.len() as u64 != u64::from(width) * u64::from(height) {
+            return Err(IllegalParameter("pixel length is not equal to width * height"));
+        }
+
+        if self.information_header.color_plane_count != 1 {
+            return Err(IllegalParameter("color plane count must be 1"));
+        }
+
+        let information_header_size = self.information_header.to_bytes().len();
+        if self.information_header.size as usize != information_header_size {
+            return Err(IllegalParameter("information header size does not match the information header"));
+        }
+
+        let headers_size = Header::SIZE + information_header_size + self.color_table.len() * Self::COLOR_TABLE_ENTRY_SIZE;
+
+        if self.header.offset as usize != headers_size {
+            return Err(IllegalParameter("pixel data offset does not match the size of the headers and color table"));
+        }
+
+        let (_, padded_image_size) = Self::compute_padding(width, height, P::bits_per_pixel())?;
+
+        if self.header.size as u64 != headers_size as u64 + u64::from(padded_image_size) {
+            return Err(IllegalParameter("file size does not match the size of the headers and pixel data"));
+        }
+
+        Ok(())
+    }
+
+    /// Recompute the headers of the bitmap for the given dimensions, from its current pixels and
+    /// color table, after modifying them directly.
+    ///
+    /// The dimensions are interpreted in the same way as [Bitmap::new_from_pixels], and the pixel
+    /// data is assumed to be uncompressed.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// let mut bitmap = Bitmap::new_from_pixels(2, 2, vec![rgb!(255, 0, 0); 4]).unwrap();
+    ///
+    /// // Add a row of pixels, then fix up the headers.
+    /// bitmap.pixels.extend([rgb!(0, 0, 255); 2]);
+    /// assert!(bitmap.validate().is_err());
+    /// bitmap.recompute_headers(2, 3).unwrap();
+    /// assert!(bitmap.validate().is_ok());
+    ///
+    /// let mut pixels = vec![rgb!(255, 0, 0); 4];
+    /// pixels.extend([rgb!(0, 0, 255); 2]);
+    /// assert_eq!(bitmap.to_bytes(), Bitmap::new_from_pixels(2, 3, pixels).unwrap().to_bytes());
+    ///
+    /// assert!(bitmap.recompute_headers(4, 4).is_err());
+    /// ```
+    pub fn recompute_headers(&mut self, width: i32, height: i32) -> Result<(), Error> {
+        Self::check_dimensions(width, height)?;
+
+        let unsigned_abs_height = height.unsigned_abs();
+        if self.pixels.len() as u64 != u64::from(width.unsigned_abs()) * u64::from(unsigned_abs_height) {
+            return Err(IllegalParameter("pixel length is not equal to width * height"));
+        }
+
+        if self.color_table.len() > 256 {
+            return Err(IllegalParameter("color table cannot contain more than 256 colors"));
+        }
+
+        self.information_header.width = width;
+        self.information_header.height = height;
+        self.information_header.raw_image_size = 0;
+        self.information_header.color_palette_count = self.color_table.len() as u32;
+
+        let headers_size = (Header::SIZE + self.information_header.to_bytes().len() + self.color_table.len() * Self::COLOR_TABLE_ENTRY_SIZE) as u32;
+        let (_, padded_bytes_per_image) = Self::compute_padding(width.unsigned_abs(), unsigned_abs_height, P::bits_per_pixel())?;
+
+        self.header.size = headers_size + padded_bytes_per_image;
+        self.header.offset = headers_size;
+        Ok(())
     }
 
+    /// Serialize the bitmap as a bitmap file, with the pixel data uncompressed.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap, Pixel24Bit};
+    ///
+    /// // Round-trip images that are a single pixel, a single column and a single row.
+    /// for (width, height) in [(1, 1), (1, 100), (100, 1)] {
+    ///     let pixels: Vec<Pixel24Bit> = (0..width * height).map(|i| rgb!(i as u8, 0, 0)).collect();
+    ///     let bytes = Bitmap::new_from_pixels(width, height, pixels.clone()).unwrap().to_bytes();
+    ///
+    ///     let bitmap = Bitmap::<Pixel24Bit>::new_from_bytes(bytes).unwrap();
+    ///     assert_eq!((bitmap.get_width(), bitmap.get_height()), (width as u32, height as u32));
+    ///     assert_eq!(bitmap.pixels.iter().map(|pixel| pixel.red).collect::<Vec<_>>(), pixels.iter().map(|pixel| pixel.red).collect::<Vec<_>>());
+    /// }
+    /// ```
+    ///
+    /// Each row is the width of the image, padded to a multiple of 4 bytes, even when the image
+    /// isn't square:
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// let pixels = (0..6).map(|i| rgb!(i, i, i)).collect();
+    /// let bytes = Bitmap::new_from_pixels(3, 2, pixels).unwrap().to_bytes();
+    /// assert_eq!(bytes[54..], [
+    ///     0, 0, 0, 1, 1, 1, 2, 2, 2, 0, 0, 0,
+    ///     3, 3, 3, 4, 4, 4, 5, 5, 5, 0, 0, 0,
+    /// ]);
+    /// ```
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = vec![0; Header::SIZE];
 
         // Apply the headers.
         bytes.copy_from_slice(&self.header.to_bytes());
         bytes.append(&mut self.information_header.to_bytes());
+        bytes.append(&mut self.color_table_to_bytes());
 
-        // Compute the padded pixel bytes.
-        let (padding_per_row, _) = Self::compute_padding(self.pixels.len() as u32, self.information_header.height.unsigned_abs());
+        bytes.extend(self.row_bytes().flatten());
+        bytes
+    }
+
+    /// Write the bitmap file (as given by [Bitmap::to_bytes]) to the writer, returning the number
+    /// of bytes written.
+    ///
+    /// If the writer fails, the [Error::Io] reports how many bytes were written before it did.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap, Error};
+    /// use std::io::{self, Cursor, Write};
+    ///
+    /// /// A writer that fails once it has been given `limit` bytes.
+    /// struct FailAfter(Cursor<Vec<u8>>, usize);
+    ///
+    /// impl Write for FailAfter {
+    ///     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    ///         let remaining = self.1 - self.0.get_ref().len();
+    ///         if remaining == 0 {
+    ///             return Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"));
+    ///         }
+    ///         self.0.write(&buf[..buf.len().min(remaining)])
+    ///     }
+    ///
+    ///     fn flush(&mut self) -> io::Result<()> {
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let bitmap = Bitmap::new_from_pixels(2, 2, vec![rgb!(1, 2, 3); 4]).unwrap();
+    /// assert_eq!(bitmap.write_to(Cursor::new(vec![])).unwrap(), bitmap.to_bytes().len());
+    ///
+    /// let err = bitmap.write_to(FailAfter(Cursor::new(vec![]), 20)).unwrap_err();
+    /// assert!(matches!(err, Error::Io(20, _)));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_to(&self, writer: impl std::io::Write) -> Result<usize, Error> {
+        use std::io::Write;
+
+        let mut writer = CountingWriter { inner: writer, written: 0 };
+        match writer.write_all(&self.to_bytes()).and_then(|()| writer.flush()) {
+            Ok(()) => Ok(writer.written),
+            Err(err) => Err(Error::Io(writer.written, err)),
+        }
+    }
 
-        bytes.append(&mut self.pixels
-            .chunks_exact(self.information_header.height.unsigned_abs() as usize)
-            .flat_map(|row| {
-                let row_bytes: Vec<u8> = row.iter()
-                    .flat_map(Pixel::to_bytes)
-                    .chain(repeat_n(0u8, padding_per_row as usize))
-                    .collect();
+    /// Write the bitmap file (as given by [Bitmap::to_bytes]) to the file at `path` through a
+    /// buffer, creating or truncating it, and returning the number of bytes written.
+    ///
+    /// If the write fails (e.g., because the disk is full), the [Error::Io] reports how many bytes
+    /// reached the file before it did, rather than how many were buffered.
+    #[cfg(feature = "std")]
+    pub fn write_to_file_buffered(&self, path: impl AsRef<std::path::Path>) -> Result<usize, Error> {
+        use std::io::{BufWriter, Write};
+
+        let file = std::fs::File::create(path).map_err(|err| Error::Io(0, err))?;
+        let mut file = CountingWriter { inner: file, written: 0 };
+
+        let mut writer = BufWriter::new(&mut file);
+        let result = writer.write_all(&self.to_bytes()).and_then(|()| writer.flush());
+        // Discard anything left in the buffer, rather than retrying the write when it is dropped.
+        let _ = writer.into_parts();
+
+        match result {
+            Ok(()) => Ok(file.written),
+            Err(err) => Err(Error::Io(file.written, err)),
+        }
+    }
 
+    /// Get the bytes of each row of pixels as they are stored in the file (i.e., in the order that
+    /// the rows appear in the file), with each row padded to a multiple of four bytes.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// let bitmap = Bitmap::new_from_pixels(3, 2, vec![rgb!(1, 2, 3); 6]).unwrap();
+    ///
+    /// // Each row of 9 bytes of pixels is padded to 12 bytes.
+    /// let rows: Vec<Vec<u8>> = bitmap.row_bytes().collect();
+    /// assert_eq!(rows.len(), 2);
+    /// assert!(rows.iter().all(|row| row.len() == 12));
+    ///
+    /// let bytes = bitmap.to_bytes();
+    /// assert_eq!(rows.concat(), &bytes[bitmap.header.offset as usize..]);
+    /// ```
+    pub fn row_bytes(&self) -> impl Iterator<Item = Vec<u8>> + '_ {
+        // An image with no rows has no padding.
+        let padding_per_row = Self::compute_padding(self.get_width(), self.get_height(), P::bits_per_pixel())
+            .map_or(0, |(padding_per_row, _)| padding_per_row);
+
+        self.pixels
+            .chunks_exact((self.get_width() as usize).max(1))
+            .map(move |row| {
+                let mut row_bytes = Self::pack_row(row);
+                row_bytes.extend(repeat_n(0u8, padding_per_row as usize));
                 row_bytes
             })
-            .collect());
+    }
 
-        bytes
+    /// Serialize an 8bpp indexed bitmap to bytes, compressing the pixel data with
+    /// [CompressionMethod::BiRle8].
+    ///
+    /// This is only supported for bottom-up bitmaps (i.e., those with a positive height), as
+    /// RLE8-compressed bitmaps cannot be top-down.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{hex, Bitmap, Pixel8Bit};
+    ///
+    /// // Create an image made up of long horizontal stripes, with a short pattern in the first row.
+    /// let color_table = vec![hex!("#FF0000").unwrap(), hex!("#00FF00").unwrap(), hex!("#0000FF").unwrap()];
+    /// let indices: Vec<u8> = (0..64 * 8).map(|i| if i < 5 { (i % 3) as u8 } else { ((i / 64) % 3) as u8 }).collect();
+    /// let pixels = indices.iter().map(|&index| Pixel8Bit { index }).collect();
+    /// let bitmap = Bitmap::new_from_indexed_pixels(64, 8, color_table, pixels).unwrap();
+    ///
+    /// let compressed = bitmap.to_bytes_rle8().unwrap();
+    /// assert!(compressed.len() < bitmap.to_bytes().len());
+    ///
+    /// let decoded = Bitmap::<Pixel8Bit>::new_from_bytes(compressed).unwrap();
+    /// assert_eq!(decoded.pixels.iter().map(|pixel| pixel.index).collect::<Vec<_>>(), indices);
+    /// ```
+    pub fn to_bytes_rle8(&self) -> Result<Vec<u8>, Error> {
+        if P::bits_per_pixel() != 8 || self.color_table.is_empty() {
+            return Err(Unsupported("RLE8 compression is only supported for 8bpp indexed bitmaps"));
+        }
+
+        if self.get_raw_height() < 0 {
+            return Err(Unsupported("RLE8 compression is only supported for bottom-up bitmaps"));
+        }
+
+        let indices: Vec<u8> = self.pixels.iter().flat_map(Pixel::to_bytes).collect();
+        let mut data = encode_rle8(indices.chunks_exact(self.get_width() as usize));
+
+        let mut information_header = InformationHeader::new::<P>(self.get_raw_width(), self.get_raw_height());
+        information_header.compression_method = CompressionMethod::BiRle8;
+        information_header.raw_image_size = data.len() as u32;
+        information_header.horizontal_resolution = self.information_header.horizontal_resolution;
+        information_header.vertical_resolution = self.information_header.vertical_resolution;
+        information_header.color_palette_count = self.color_table.len() as u32;
+
+        let mut color_table = self.color_table_to_bytes();
+        let offset = Header::SIZE + information_header.size as usize + color_table.len();
+        let header = Header::new((offset + data.len()) as u32, offset as u32);
+
+        let mut bytes = header.to_bytes().to_vec();
+        bytes.append(&mut information_header.to_bytes());
+        bytes.append(&mut color_table);
+        bytes.append(&mut data);
+        Ok(bytes)
+    }
+
+    /// Serialize the color table, with each entry followed by a reserved byte.
+    fn color_table_to_bytes(&self) -> Vec<u8> {
+        self.color_table.iter()
+            .flat_map(|color| {
+                let mut entry = color.to_bytes();
+                entry.push(0);
+                entry
+            })
+            .collect()
     }
 }
@@ -1,19 +1,31 @@
-use std::error::Error as StdError;
-use std::fmt::{Display, Formatter};
+use core::fmt::{Display, Formatter};
 
+/// An error from decoding, encoding or editing a bitmap.
+///
+/// The variants depend on the enabled features (e.g., [Error::Io] needs `std`), so a match on the
+/// error must have a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     Unsupported(&'static str),
     IllegalParameter(&'static str),
+    CoordinateOutOfBounds(u32, u32),
+    /// Writing the bitmap failed, after the given number of bytes had been written.
+    #[cfg(feature = "std")]
+    Io(usize, std::io::Error),
 }
 
 impl Display for Error {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         match self {
             Error::Unsupported(msg) => write!(f, "unsupported: {msg}"),
-            Error::IllegalParameter(msg) => write!(f, "illegal parameter: {msg}"), 
+            Error::IllegalParameter(msg) => write!(f, "illegal parameter: {msg}"),
+            Error::CoordinateOutOfBounds(x, y) => write!(f, "coordinate out of bounds: ({x}, {y})"),
+            #[cfg(feature = "std")]
+            Error::Io(written, err) => write!(f, "io error after writing {written} bytes: {err}"),
         }
     }
 }
 
-impl StdError for Error {}
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
@@ -0,0 +1,109 @@
+use crate::Error::IllegalParameter;
+use crate::{hsv_to_rgb, Bitmap, Error, Pixel24Bit};
+use alloc::vec::Vec;
+
+/// The direction that [Bitmap::gradient] blends along.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GradientDirection {
+    /// From the left column to the right column.
+    Horizontal,
+    /// From the top row to the bottom row.
+    Vertical,
+    /// From the top-left corner to the bottom-right corner.
+    Diagonal,
+}
+
+impl Bitmap<Pixel24Bit> {
+    /// Create a `width` x `height` image (stored top-down) that blends linearly from `start` to
+    /// `end` in the given direction (see [Pixel24Bit::blend]), so that the first column, row or
+    /// corner is `start` and the last is `end`.
+    ///
+    /// Returns an error if either dimension is zero or too large for a bitmap.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap, GradientDirection};
+    ///
+    /// let gradient = Bitmap::gradient(5, 2, rgb!(0, 0, 0), rgb!(200, 100, 0), GradientDirection::Horizontal).unwrap();
+    /// let column = |x| gradient.get_pixel_at(x, 1).map(|pixel| pixel.to_rgb());
+    ///
+    /// assert_eq!(column(0), Some([0, 0, 0]));
+    /// assert_eq!(column(2), Some([100, 50, 0]));
+    /// assert_eq!(column(4), Some([200, 100, 0]));
+    ///
+    /// assert!(Bitmap::gradient(0, 2, rgb!(0, 0, 0), rgb!(0, 0, 0), GradientDirection::Vertical).is_err());
+    /// ```
+    pub fn gradient(width: u32, height: u32, start: Pixel24Bit, end: Pixel24Bit, direction: GradientDirection) -> Result<Self, Error> {
+        let (Ok(signed_width), Ok(signed_height)) = (i32::try_from(width), i32::try_from(height)) else {
+            return Err(IllegalParameter("gradient dimensions are too large"));
+        };
+
+        // The number of steps along the gradient, and how far along it each pixel is.
+        let (steps, position): (u32, fn(u32, u32) -> u32) = match direction {
+            GradientDirection::Horizontal => (width.saturating_sub(1), |x, _| x),
+            GradientDirection::Vertical => (height.saturating_sub(1), |_, y| y),
+            GradientDirection::Diagonal => (width.saturating_sub(1) + height.saturating_sub(1), |x, y| x + y),
+        };
+
+        let pixels: Vec<Pixel24Bit> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| match steps {
+                0 => start,
+                steps => start.blend(&end, f64::from(position(x, y)) / f64::from(steps)),
+            })
+            .collect();
+
+        Bitmap::new_from_pixels(signed_width, -signed_height, pixels)
+    }
+
+    /// Create a `width` x `height` image (stored top-down) of the full color spectrum, with the
+    /// hue increasing from 0.0 in the left column towards 1.0 across the image (see
+    /// [hsv_to_rgb]).
+    ///
+    /// Down the top half of the image, the saturation rises from 0.0 (white) to 1.0 at full value,
+    /// and down the bottom half the value falls from 1.0 to 0.0 (black) at full saturation, so the
+    /// middle row (of an odd height) holds the pure hues.
+    ///
+    /// Returns an error if either dimension is zero or too large for a bitmap.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::Bitmap;
+    ///
+    /// let palette = Bitmap::hsv_palette(6, 5).unwrap();
+    /// let color = |x, y| palette.get_pixel_at(x, y).map(|pixel| pixel.to_rgb());
+    ///
+    /// assert_eq!(color(0, 0), Some([255, 255, 255]));
+    /// assert_eq!(color(0, 1), Some([255, 128, 128]));
+    /// assert_eq!(color(0, 2), Some([255, 0, 0]));
+    /// assert_eq!(color(2, 2), Some([0, 255, 0]));
+    /// assert_eq!(color(4, 2), Some([0, 0, 255]));
+    /// assert_eq!(color(4, 3), Some([0, 0, 128]));
+    /// assert_eq!(color(3, 4), Some([0, 0, 0]));
+    ///
+    /// // The pure red in the file stores its red last, after the blue and green.
+    /// let bytes = palette.to_bytes();
+    /// let offset = bytes[10] as usize;
+    /// let row_size = 6 * 3 + 2;
+    /// assert_eq!(bytes[offset + 2 * row_size..][..3], [0, 0, 255]);
+    /// ```
+    pub fn hsv_palette(width: u32, height: u32) -> Result<Self, Error> {
+        let (Ok(signed_width), Ok(signed_height)) = (i32::try_from(width), i32::try_from(height)) else {
+            return Err(IllegalParameter("palette dimensions are too large"));
+        };
+
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let hue = f64::from(x) / f64::from(width);
+                let t = if height > 1 { f64::from(y) / f64::from(height - 1) } else { 0.5 };
+                let (saturation, value) = if t <= 0.5 { (t * 2.0, 1.0) } else { (1.0, 2.0 - t * 2.0) };
+                hsv_to_rgb(hue, saturation, value)
+            })
+            .collect::<Result<Vec<Pixel24Bit>, Error>>()?;
+
+        Bitmap::new_from_pixels(signed_width, -signed_height, pixels)
+    }
+}
@@ -5,3 +5,24 @@ use crate::Error::IllegalParameter;
 pub(crate) fn array_from_slice<T, const L: usize>(slice: &[T]) -> Result<&[T; L], Error> {
     slice.try_into().map_err(|_| IllegalParameter("slice length mismatch"))
 }
+
+/// A writer that counts the bytes that the inner writer has accepted, so that the position of a
+/// failed write can be reported.
+#[cfg(feature = "std")]
+pub(crate) struct CountingWriter<W> {
+    pub(crate) inner: W,
+    pub(crate) written: usize,
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> std::io::Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
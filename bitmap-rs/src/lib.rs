@@ -1,8 +1,22 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 mod bitmap;
 mod error;
 mod macros;
 mod helpers;
+mod rle;
+mod quantize;
+mod adjust;
+mod analysis;
+mod sample;
+mod generate;
+mod png;
+mod math;
 
+pub use analysis::palette_coverage;
 pub use bitmap::*;
 pub use error::*;
+pub use generate::GradientDirection;
 pub use macros::*;
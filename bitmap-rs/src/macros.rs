@@ -1,9 +1,21 @@
 use crate::{Error, Pixel24Bit};
 use crate::Error::IllegalParameter;
 
+/// Create a [Pixel24Bit] from the red, green and blue channels of a color (see
+/// [Pixel24Bit::from_rgb]).
+///
+/// ## Example
+///
+/// ```rust
+/// use bitmap_rs::{hex, rgb};
+///
+/// assert_eq!(format!("{}", rgb!(76, 175, 80)), "#4CAF50");
+/// assert_eq!(rgb!(76, 175, 80).to_rgb(), [76, 175, 80]);
+/// assert_eq!(format!("{}", hex!("#4CAF50").unwrap()), "#4CAF50");
+/// ```
 #[macro_export]
 macro_rules! rgb {
-    ($r:expr, $g:expr, $b:expr) => { bitmap_rs::Pixel24Bit { red: $r, green: $g, blue: $b } };
+    ($r:expr, $g:expr, $b:expr) => { bitmap_rs::Pixel24Bit::from_rgb($r, $g, $b) };
 }
 
 const fn hex_digit_to_u8(digit: u8) -> u8 {
@@ -32,6 +44,8 @@ const fn hex_digits_to_u8(msn: u8, lsn: u8) -> u8 {
     hex_digit_to_u8(msn) << 4 | hex_digit_to_u8(lsn)
 }
 
+/// Parse a hex color of the form `#RRGGBB` into a [Pixel24Bit] (see [Pixel24Bit::from_rgb]), the
+/// inverse of its [Display](core::fmt::Display) format.
 pub const fn hex_to_rgb(hex: &str) -> Result<Pixel24Bit, Error> {
     let as_bytes = hex.as_bytes();
 
@@ -39,11 +53,11 @@ pub const fn hex_to_rgb(hex: &str) -> Result<Pixel24Bit, Error> {
         return Err(IllegalParameter("expected '#AAAAAA' where A is a hexadecimal digit."));
     }
 
-    Ok(Pixel24Bit {
-        red: hex_digits_to_u8(as_bytes[1], as_bytes[2]),
-        green: hex_digits_to_u8(as_bytes[3], as_bytes[4]),
-        blue: hex_digits_to_u8(as_bytes[5], as_bytes[6]),
-    })
+    Ok(Pixel24Bit::from_rgb(
+        hex_digits_to_u8(as_bytes[1], as_bytes[2]),
+        hex_digits_to_u8(as_bytes[3], as_bytes[4]),
+        hex_digits_to_u8(as_bytes[5], as_bytes[6]),
+    ))
 }
 
 #[macro_export]
@@ -51,7 +65,8 @@ macro_rules! hex {
     ($hex:expr) => { const { bitmap_rs::hex_to_rgb($hex) } };
 }
 
-/// Convert hue, saturation and value to red, green and blue.
+/// Convert hue, saturation and value to red, green and blue, as a [Pixel24Bit] (see
+/// [Pixel24Bit::from_rgb]).
 ///
 /// This function will return an error if the hue, saturation or value are outside of the permitted
 /// domain.
@@ -71,7 +86,7 @@ pub const fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> Result<Pixel24
     let hue = hue * 360f64;
 
     let c = value * saturation;
-    let x = c * (1f64 - ((((hue / 60f64) as i8) % 2) - 1).abs() as f64);
+    let x = c * (1f64 - ((hue / 60f64) % 2f64 - 1f64).abs());
     let m = value - c;
 
     let (r, g, b) = match hue {
@@ -84,11 +99,85 @@ pub const fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> Result<Pixel24
         _ => { return Err(IllegalParameter("hue exceeded range [0, 360)")); }
     };
 
-    Ok(Pixel24Bit {
-        red: ((r + m) * 255.0) as u8,
-        green: ((g + m) * 255.0) as u8,
-        blue: ((b + m) * 255.0) as u8,
-    })
+    // Round each channel to the nearest value (the channels are never negative).
+    Ok(Pixel24Bit::from_rgb(
+        ((r + m) * 255.0 + 0.5) as u8,
+        ((g + m) * 255.0 + 0.5) as u8,
+        ((b + m) * 255.0 + 0.5) as u8,
+    ))
+}
+
+/// Convert the red, green and blue of a [Pixel24Bit] (see [Pixel24Bit::to_rgb]) to hue,
+/// saturation and value, in the same domains as
+/// [hsv_to_rgb] (so the result can always be converted back).
+///
+/// - `hue`: 0.0 <= hue < 1.0 (0.0 for grays, which have no hue)
+/// - `saturation`: 0.0 <= saturation <= 1.0
+/// - `value`: 0.0 <= value <= 1.0
+///
+/// The formula used is from rapidtables.com:
+/// https://www.rapidtables.com/convert/color/rgb-to-hsv.html
+///
+/// ## Example
+///
+/// ```rust
+/// use bitmap_rs::{hex, hsv_to_rgb, rgb, rgb_to_hsv, Pixel24Bit};
+///
+/// let (hue, saturation, value) = rgb_to_hsv(&Pixel24Bit::from_rgb(0, 255, 255));
+/// assert!((hue - 0.5).abs() < 1e-9 && (saturation - 1.0).abs() < 1e-9 && (value - 1.0).abs() < 1e-9);
+///
+/// // A pixel whose last byte in the file (its red) is the brightest has a red hue.
+/// let (hue, _, _) = rgb_to_hsv(&Pixel24Bit::from([0, 64, 255]));
+/// assert!((hue - 15.0 / 360.0).abs() < 1e-3, "{hue}");
+/// assert_eq!(hsv_to_rgb(1.0 / 3.0, 1.0, 1.0).unwrap().to_rgb(), [0, 255, 0]);
+/// assert_eq!(<[u8; 3]>::from(hsv_to_rgb(0.0, 1.0, 1.0).unwrap()), [0, 0, 255]);
+///
+/// let colors = [
+///     rgb!(0, 0, 0), rgb!(255, 255, 255), rgb!(128, 128, 128), rgb!(255, 0, 0), rgb!(0, 255, 0),
+///     rgb!(0, 0, 255), rgb!(255, 0, 255), hex!("#4CAF50").unwrap(), hex!("#F44336").unwrap(), hex!("#2196F3").unwrap(),
+/// ];
+/// for color in colors {
+///     let (hue, saturation, value) = rgb_to_hsv(&color);
+///     let round_tripped = hsv_to_rgb(hue, saturation, value).unwrap();
+///     assert_eq!(
+///         (round_tripped.red, round_tripped.green, round_tripped.blue),
+///         (color.red, color.green, color.blue)
+///     );
+/// }
+/// ```
+pub const fn rgb_to_hsv(pixel: &Pixel24Bit) -> (f64, f64, f64) {
+    let [red, green, blue] = pixel.to_rgb();
+    let red = red as f64 / 255.0;
+    let green = green as f64 / 255.0;
+    let blue = blue as f64 / 255.0;
+
+    let max = red.max(green).max(blue);
+    let min = red.min(green).min(blue);
+    let delta = max - min;
+
+    // The hue (in degrees) is undefined for grays, so use zero.
+    let mut hue = if delta == 0.0 {
+        0.0
+    } else if max == red {
+        60.0 * ((green - blue) / delta % 6.0)
+    } else if max == green {
+        60.0 * ((blue - red) / delta + 2.0)
+    } else {
+        60.0 * ((red - green) / delta + 4.0)
+    };
+
+    if hue < 0.0 {
+        hue += 360.0;
+    }
+
+    // Normalize the hue to [0.0, 1.0), which rounding can otherwise push up to 1.0.
+    hue /= 360.0;
+    if hue >= 1.0 {
+        hue -= 1.0;
+    }
+
+    let saturation = if max == 0.0 { 0.0 } else { delta / max };
+    (hue, saturation, max)
 }
 
 #[macro_export]
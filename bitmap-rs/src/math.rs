@@ -0,0 +1,55 @@
+//! Floating-point functions that are provided by `std`, but not by `core`, so that they can be
+//! used without `std` (with the implementations from `libm`, behind the `libm` feature).
+
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("bitmap-rs needs either the `std` or the `libm` feature for its floating-point functions");
+
+#[cfg(feature = "std")]
+pub(crate) fn sqrt(x: f64) -> f64 {
+    x.sqrt()
+}
+
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+pub(crate) fn sqrt(x: f64) -> f64 {
+    libm::sqrt(x)
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn powf(x: f64, y: f64) -> f64 {
+    x.powf(y)
+}
+
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+pub(crate) fn powf(x: f64, y: f64) -> f64 {
+    libm::pow(x, y)
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn round(x: f64) -> f64 {
+    x.round()
+}
+
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+pub(crate) fn round(x: f64) -> f64 {
+    libm::round(x)
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn floor(x: f64) -> f64 {
+    x.floor()
+}
+
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+pub(crate) fn floor(x: f64) -> f64 {
+    libm::floor(x)
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn log10(x: f64) -> f64 {
+    x.log10()
+}
+
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+pub(crate) fn log10(x: f64) -> f64 {
+    libm::log10(x)
+}
@@ -0,0 +1,106 @@
+use crate::{Bitmap, Pixel24Bit};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The eight bytes that every PNG file starts with.
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// The largest number of bytes that an uncompressed (stored) deflate block can hold.
+const MAX_STORED_BLOCK_SIZE: usize = 0xFFFF;
+
+/// Compute the CRC-32 (as used by PNG and zlib) of the bytes.
+fn crc32<'a>(bytes: impl IntoIterator<Item = &'a u8>) -> u32 {
+    !bytes.into_iter().fold(u32::MAX, |crc, &byte| {
+        (0..8).fold(crc ^ u32::from(byte), |crc, _| if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 })
+    })
+}
+
+/// Compute the Adler-32 checksum (as used by zlib) of the bytes.
+fn adler32(bytes: &[u8]) -> u32 {
+    let (a, b) = bytes.iter().fold((1u32, 0u32), |(a, b), &byte| {
+        let a = (a + u32::from(byte)) % 65521;
+        (a, (b + a) % 65521)
+    });
+    (b << 16) | a
+}
+
+/// Append a PNG chunk of the given type, with its length and CRC.
+fn write_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    png.extend(chunk_type);
+    png.extend(data);
+    png.extend(crc32(chunk_type.iter().chain(data)).to_be_bytes());
+}
+
+/// Wrap the data in a zlib stream of uncompressed (stored) deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut zlib = vec![0x78, 0x01];
+
+    let blocks: Vec<&[u8]> = if data.is_empty() { vec![&[]] } else { data.chunks(MAX_STORED_BLOCK_SIZE).collect() };
+    for (i, block) in blocks.iter().enumerate() {
+        // Only the last block is marked as final.
+        zlib.push(u8::from(i == blocks.len() - 1));
+
+        let length = block.len() as u16;
+        zlib.extend(length.to_le_bytes());
+        zlib.extend((!length).to_le_bytes());
+        zlib.extend(*block);
+    }
+
+    zlib.extend(adler32(data).to_be_bytes());
+    zlib
+}
+
+impl Bitmap<Pixel24Bit> {
+    /// Encode the image as an 8-bit RGB PNG file, with the rows from the top of the image down
+    /// whichever order they are stored in (see [Bitmap::is_top_down]), and the true red, green
+    /// and blue channels of each pixel (see [Pixel24Bit::to_rgb]).
+    ///
+    /// The image data isn't compressed (it is stored in uncompressed deflate blocks), which keeps
+    /// the encoder small, at the cost of a file about as large as the equivalent bitmap.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{Bitmap, Pixel24Bit};
+    ///
+    /// let bitmap = Bitmap::new_from_pixels(3, 2, vec![Pixel24Bit::from_rgb(1, 2, 3); 6]).unwrap();
+    /// let png = bitmap.to_png();
+    ///
+    /// assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    /// // The header chunk gives the width and height.
+    /// assert_eq!(&png[12..16], b"IHDR");
+    /// assert_eq!(&png[16..24], &[0, 0, 0, 3, 0, 0, 0, 2]);
+    /// // The first pixel follows the image data's zlib and block headers, and the row's filter type.
+    /// assert_eq!(&png[37..41], b"IDAT");
+    /// assert_eq!(&png[49..52], &[1, 2, 3]);
+    /// assert!(png.ends_with(b"IEND\xae\x42\x60\x82"));
+    /// ```
+    pub fn to_png(&self) -> Vec<u8> {
+        let (width, height) = (self.get_width(), self.get_height());
+
+        let mut rows: Vec<&[Pixel24Bit]> = self.pixels.chunks(width.max(1) as usize).collect();
+        if !self.is_top_down() {
+            rows.reverse();
+        }
+
+        // Each row is preceded by its filter type, which is always 0 (none).
+        let mut scanlines = Vec::with_capacity(rows.len() * (1 + width as usize * 3));
+        for row in rows {
+            scanlines.push(0);
+            scanlines.extend(row.iter().flat_map(|pixel| pixel.to_rgb()));
+        }
+
+        let mut header = Vec::with_capacity(13);
+        header.extend(width.to_be_bytes());
+        header.extend(height.to_be_bytes());
+        // 8 bits per channel, RGB, with the standard compression, filtering and no interlacing.
+        header.extend([8, 2, 0, 0, 0]);
+
+        let mut png = PNG_SIGNATURE.to_vec();
+        write_chunk(&mut png, b"IHDR", &header);
+        write_chunk(&mut png, b"IDAT", &zlib_stored(&scanlines));
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+}
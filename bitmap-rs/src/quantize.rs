@@ -0,0 +1,315 @@
+use crate::Error::IllegalParameter;
+use crate::{Bitmap, Error, Pixel, Pixel24Bit, Pixel8Bit};
+use crate::math;
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+
+/// The maximum number of iterations of k-means clustering to perform when extracting a palette.
+const MAX_ITERATIONS: usize = 32;
+
+impl Bitmap<Pixel24Bit> {
+    /// Extract a palette of at most `max_colors` colors that best represents the colors in the
+    /// image, using k-means clustering.
+    ///
+    /// If the image contains no more than `max_colors` distinct colors, those colors are returned
+    /// exactly.
+    pub fn extract_palette(&self, max_colors: usize) -> Result<Vec<Pixel24Bit>, Error> {
+        self.cluster_colors(max_colors, Self::initial_centroids)
+    }
+
+    /// Extract a palette of at most `max_colors` colors like [Bitmap::extract_palette], but choose
+    /// the initial centroids with k-means++ (picking each color at random, weighted by how far it
+    /// is from the centroids chosen so far) instead of always picking the furthest color.
+    ///
+    /// The random choices are driven by `seed`, so the same image and seed always give the same
+    /// palette, and different seeds can be tried to find a better one.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap, Pixel};
+    ///
+    /// let pixels = (0..256).map(|i| rgb!(i as u8, (i * 7 % 256) as u8, (255 - i) as u8)).collect();
+    /// let bitmap = Bitmap::new_from_pixels(16, 16, pixels).unwrap();
+    ///
+    /// let palette = bitmap.extract_palette_seeded(8, 42).unwrap();
+    /// assert_eq!(palette.len(), 8);
+    ///
+    /// let bytes = |palette: Vec<_>| palette.iter().flat_map(Pixel::to_bytes).collect::<Vec<u8>>();
+    /// assert_eq!(bytes(bitmap.extract_palette_seeded(8, 42).unwrap()), bytes(palette));
+    /// ```
+    pub fn extract_palette_seeded(&self, max_colors: usize, seed: u64) -> Result<Vec<Pixel24Bit>, Error> {
+        self.cluster_colors(max_colors, |colors, count| Self::initial_centroids_seeded(colors, count, seed))
+    }
+
+    /// Cluster the colors of the image into at most `max_colors` colors with k-means clustering,
+    /// starting from the centroids chosen by `initialize`.
+    fn cluster_colors(
+        &self,
+        max_colors: usize,
+        initialize: impl FnOnce(&[(Pixel24Bit, usize)], usize) -> Vec<Pixel24Bit>,
+    ) -> Result<Vec<Pixel24Bit>, Error> {
+        if max_colors == 0 {
+            return Err(IllegalParameter("palette must contain at least one color"));
+        }
+
+        // Cluster the distinct colors (weighted by their frequency) rather than every pixel.
+        let mut color_counts: BTreeMap<(u8, u8, u8), usize> = BTreeMap::new();
+        for pixel in &self.pixels {
+            *color_counts.entry((pixel.red, pixel.green, pixel.blue)).or_default() += 1;
+        }
+
+        let mut colors: Vec<(Pixel24Bit, usize)> = color_counts.into_iter()
+            .map(|((red, green, blue), count)| (Pixel24Bit { red, green, blue }, count))
+            .collect();
+
+        // Sort the colors so that the result doesn't depend on the iteration order of the map.
+        colors.sort_by_key(|(pixel, count)| (Reverse(*count), pixel.red, pixel.green, pixel.blue));
+
+        if colors.len() <= max_colors {
+            return Ok(colors.into_iter().map(|(pixel, _)| pixel).collect());
+        }
+
+        let mut centroids = initialize(&colors, max_colors);
+        let mut assignments = vec![usize::MAX; colors.len()];
+
+        for _ in 0..MAX_ITERATIONS {
+            // Assign each color to its nearest centroid.
+            let mut has_changed = false;
+            for (assignment, (pixel, _)) in assignments.iter_mut().zip(colors.iter()) {
+                let nearest = nearest_color_index(&centroids, pixel);
+                if *assignment != nearest {
+                    *assignment = nearest;
+                    has_changed = true;
+                }
+            }
+
+            if !has_changed {
+                break;
+            }
+
+            // Move each centroid to the (weighted) mean of the colors assigned to it.
+            let mut sums = vec![[0usize; 4]; centroids.len()];
+            for (&assignment, (pixel, count)) in assignments.iter().zip(colors.iter()) {
+                let sum = &mut sums[assignment];
+                sum[0] += pixel.red as usize * count;
+                sum[1] += pixel.green as usize * count;
+                sum[2] += pixel.blue as usize * count;
+                sum[3] += count;
+            }
+
+            for (centroid, [red, green, blue, count]) in centroids.iter_mut().zip(sums) {
+                // Centroids with no colors assigned to them are left where they are.
+                let mean = |sum: usize| (sum + count / 2).checked_div(count).map(|value| value as u8);
+                if let (Some(red), Some(green), Some(blue)) = (mean(red), mean(green), mean(blue)) {
+                    *centroid = Pixel24Bit { red, green, blue };
+                }
+            }
+        }
+
+        Ok(centroids)
+    }
+
+    /// Choose the initial centroids for k-means clustering, starting with the most common color
+    /// and then repeatedly choosing the color that is furthest from the centroids chosen so far.
+    fn initial_centroids(colors: &[(Pixel24Bit, usize)], count: usize) -> Vec<Pixel24Bit> {
+        let mut centroids = vec![colors[0].0];
+        let mut distances: Vec<f64> = colors.iter().map(|(pixel, _)| pixel.difference(&centroids[0])).collect();
+
+        while centroids.len() < count {
+            let (furthest, _) = distances.iter()
+                .enumerate()
+                .fold((0, f64::NEG_INFINITY), |best, (i, &distance)| if distance > best.1 { (i, distance) } else { best });
+
+            let centroid = colors[furthest].0;
+            for (distance, (pixel, _)) in distances.iter_mut().zip(colors.iter()) {
+                *distance = distance.min(pixel.difference(&centroid));
+            }
+
+            centroids.push(centroid);
+        }
+
+        centroids
+    }
+
+    /// Choose the initial centroids for k-means clustering with k-means++, picking each color at
+    /// random with a probability proportional to its frequency and its squared distance from the
+    /// nearest centroid chosen so far.
+    fn initial_centroids_seeded(colors: &[(Pixel24Bit, usize)], count: usize, seed: u64) -> Vec<Pixel24Bit> {
+        let mut random = SplitMix64(seed);
+
+        // Pick the index of a color at random, weighted by the given weights (or the first color if
+        // they are all zero, which can only happen once every color is a centroid).
+        let mut pick = |weights: &[f64]| {
+            let mut target = random.next_f64() * weights.iter().sum::<f64>();
+            weights.iter().position(|weight| {
+                target -= weight;
+                target < 0.0
+            }).unwrap_or(0)
+        };
+
+        let counts: Vec<f64> = colors.iter().map(|(_, count)| *count as f64).collect();
+        let mut centroids = vec![colors[pick(&counts)].0];
+        let mut distances: Vec<f64> = colors.iter().map(|(pixel, _)| pixel.difference(&centroids[0])).collect();
+
+        while centroids.len() < count {
+            let weights: Vec<f64> = distances.iter().zip(&counts).map(|(distance, count)| distance * distance * count).collect();
+            let centroid = colors[pick(&weights)].0;
+
+            for (distance, (pixel, _)) in distances.iter_mut().zip(colors.iter()) {
+                *distance = distance.min(pixel.difference(&centroid));
+            }
+
+            centroids.push(centroid);
+        }
+
+        centroids
+    }
+
+    /// Convert the image to an 8bpp indexed bitmap, with a color table of at most `max_colors`
+    /// colors extracted with [Bitmap::extract_palette].
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{hex, Bitmap, Pixel, Pixel24Bit};
+    ///
+    /// // Create an image of four colors, each with a little noise.
+    /// let colors = [hex!("#4CAF50").unwrap(), hex!("#F44336").unwrap(), hex!("#2196F3").unwrap(), hex!("#FFEB3B").unwrap()];
+    /// let pixels: Vec<Pixel24Bit> = (0..64)
+    ///     .map(|i| {
+    ///         let color = colors[i / 16];
+    ///         let noise = (i % 3) as u8;
+    ///         Pixel24Bit { red: color.red.saturating_add(noise), green: color.green.saturating_sub(noise), blue: color.blue }
+    ///     })
+    ///     .collect();
+    /// let bitmap = Bitmap::new_from_pixels(8, 8, pixels).unwrap();
+    ///
+    /// let indexed = bitmap.to_indexed(4).unwrap();
+    /// assert_eq!(indexed.color_table.len(), 4);
+    ///
+    /// let round_tripped = indexed.to_rgb24().unwrap();
+    /// for (pixel, original) in round_tripped.pixels.iter().zip(bitmap.pixels.iter()) {
+    ///     assert!(pixel.difference(original) < 4.0);
+    /// }
+    /// ```
+    pub fn to_indexed(&self, max_colors: usize) -> Result<Bitmap<Pixel8Bit>, Error> {
+        if max_colors > 256 {
+            return Err(IllegalParameter("an 8bpp color table cannot contain more than 256 colors"));
+        }
+
+        let color_table = self.extract_palette(max_colors)?;
+        let pixels = self.pixels.iter()
+            .map(|pixel| Pixel8Bit { index: nearest_color_index(&color_table, pixel) as u8 })
+            .collect();
+
+        Bitmap::new_from_indexed_pixels(self.get_raw_width(), self.get_raw_height(), color_table, pixels)
+    }
+
+    /// Reduce the image to the colors of the given palette with Floyd-Steinberg dithering, which
+    /// diffuses the error of each pixel to its neighbors to approximate the colors between those
+    /// in the palette.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap};
+    ///
+    /// // A mid-gray image dithered with black and white is (roughly) half black and half white.
+    /// let mut bitmap = Bitmap::new_from_pixels(8, 8, vec![rgb!(128, 128, 128); 64]).unwrap();
+    /// bitmap.dither_floyd_steinberg(&[rgb!(0, 0, 0), rgb!(255, 255, 255)]).unwrap();
+    ///
+    /// let white_count = bitmap.pixels.iter().filter(|pixel| pixel.red == 255).count();
+    /// assert!((30..=34).contains(&white_count));
+    /// assert!(bitmap.pixels.iter().all(|pixel| pixel.red == 0 || pixel.red == 255));
+    /// ```
+    pub fn dither_floyd_steinberg(&mut self, palette: &[Pixel24Bit]) -> Result<(), Error> {
+        if palette.is_empty() {
+            return Err(IllegalParameter("palette must contain at least one color"));
+        }
+
+        let width = self.get_width() as usize;
+        if width == 0 {
+            return Ok(());
+        }
+
+        // The color of each pixel, including the error diffused to it so far.
+        let mut colors: Vec<[f64; 3]> = self.pixels.iter()
+            .map(|pixel| [f64::from(pixel.red), f64::from(pixel.green), f64::from(pixel.blue)])
+            .collect();
+
+        for i in 0..colors.len() {
+            let [red, green, blue] = colors[i].map(|channel| math::round(channel).clamp(0.0, 255.0) as u8);
+            let color = palette[nearest_color_index(palette, &Pixel24Bit { red, green, blue })];
+            self.pixels[i] = color;
+
+            let error = [
+                colors[i][0] - f64::from(color.red),
+                colors[i][1] - f64::from(color.green),
+                colors[i][2] - f64::from(color.blue),
+            ];
+
+            // Diffuse the error to the pixels to the right and on the next row.
+            let (x, y) = (i % width, i / width);
+            for (dx, dy, weight) in [(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)] {
+                let Some(neighbor_x) = x.checked_add_signed(dx).filter(|&neighbor_x| neighbor_x < width) else {
+                    continue;
+                };
+
+                if let Some(neighbor) = colors.get_mut((y + dy) * width + neighbor_x) {
+                    for channel in 0..3 {
+                        neighbor[channel] += error[channel] * weight / 16.0;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Bitmap<Pixel8Bit> {
+    /// Convert the image to a 24bpp bitmap, by expanding each pixel through the color table.
+    pub fn to_rgb24(&self) -> Result<Bitmap<Pixel24Bit>, Error> {
+        let pixels = self.pixels.iter()
+            .map(|pixel| {
+                self.color_table.get(pixel.index as usize).copied()
+                    .ok_or(IllegalParameter("pixel refers to a color that is not in the color table"))
+            })
+            .collect::<Result<Vec<Pixel24Bit>, Error>>()?;
+
+        Bitmap::new_from_pixels(self.get_raw_width(), self.get_raw_height(), pixels)
+    }
+}
+
+/// A small, fast pseudo-random number generator (SplitMix64), used to seed k-means++ so that the
+/// palette only depends on the seed.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Get a random number in `[0.0, 1.0)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Find the index of the color in `colors` that is nearest to `pixel`.
+fn nearest_color_index(colors: &[Pixel24Bit], pixel: &Pixel24Bit) -> usize {
+    colors.iter()
+        .enumerate()
+        .fold((0, f64::INFINITY), |best, (i, color)| {
+            let difference = color.difference(pixel);
+            if difference < best.1 { (i, difference) } else { best }
+        })
+        .0
+}
@@ -0,0 +1,142 @@
+use crate::Error;
+use crate::Error::IllegalParameter;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Decode the RLE8-compressed pixel data of a bitmap into one color table index per pixel.
+///
+/// The indices are returned in the same row order as the compressed data. Any pixels that are
+/// skipped by the compressed data (e.g., with a delta escape) are left as index 0.
+///
+/// See: https://learn.microsoft.com/en-us/windows/win32/gdi/bitmap-compression
+pub(crate) fn decode_rle8(data: &[u8], width: u32, height: u32) -> Result<Vec<u8>, Error> {
+    let (width, height) = (width as usize, height as usize);
+    let mut indices = vec![0u8; width * height];
+
+    let (mut x, mut y) = (0usize, 0usize);
+    let mut position = 0;
+
+    let mut set_index = |x: usize, y: usize, index: u8| {
+        if x >= width || y >= height {
+            return Err(IllegalParameter("RLE8 data exceeds the bounds of the image"));
+        }
+
+        indices[y * width + x] = index;
+        Ok(())
+    };
+
+    loop {
+        let Some(&[count, value]) = data.get(position..position + 2) else {
+            return Err(IllegalParameter("RLE8 data ended without an end-of-bitmap marker"));
+        };
+        position += 2;
+
+        match (count, value) {
+            // Encoded mode: repeat the index `count` times.
+            (1.., index) => {
+                for _ in 0..count {
+                    set_index(x, y, index)?;
+                    x += 1;
+                }
+            },
+
+            // End-of-line.
+            (0, 0) => {
+                x = 0;
+                y += 1;
+            },
+
+            // End-of-bitmap.
+            (0, 1) => break,
+
+            // Delta: move the current position right and down.
+            (0, 2) => {
+                let Some(&[dx, dy]) = data.get(position..position + 2) else {
+                    return Err(IllegalParameter("RLE8 data ended in the middle of a delta escape"));
+                };
+                position += 2;
+
+                x += dx as usize;
+                y += dy as usize;
+            },
+
+            // Absolute mode: copy the following `length` indices as-is (padded to a 16-bit
+            // boundary).
+            (0, length) => {
+                let Some(absolute_indices) = data.get(position..position + length as usize) else {
+                    return Err(IllegalParameter("RLE8 data ended in the middle of an absolute run"));
+                };
+                position += (length as usize).next_multiple_of(2);
+
+                for &index in absolute_indices {
+                    set_index(x, y, index)?;
+                    x += 1;
+                }
+            },
+        }
+    }
+
+    Ok(indices)
+}
+
+/// Encode the rows of color table indices of a bitmap as RLE8-compressed pixel data.
+///
+/// Repeated indices are encoded as runs, while sequences of differing indices are encoded in
+/// absolute mode. Each row is terminated with an end-of-line marker, except for the last row which
+/// is terminated with an end-of-bitmap marker.
+pub(crate) fn encode_rle8<'a>(rows: impl ExactSizeIterator<Item = &'a [u8]>) -> Vec<u8> {
+    let row_count = rows.len();
+    let mut data = vec![];
+
+    for (y, row) in rows.enumerate() {
+        let mut x = 0;
+
+        while x < row.len() {
+            let run_length = run_length_at(row, x);
+            if run_length >= 2 {
+                data.extend_from_slice(&[run_length as u8, row[x]]);
+                x += run_length;
+                continue;
+            }
+
+            // Collect the indices up to the next run that is worth encoding as a run.
+            let mut end = x + 1;
+            while end < row.len() && end - x < 255 && run_length_at(row, end) < 3 {
+                end += 1;
+            }
+
+            let absolute_indices = &row[x..end];
+            if absolute_indices.len() >= 3 {
+                // Absolute runs must be padded to a 16-bit boundary.
+                data.extend_from_slice(&[0, absolute_indices.len() as u8]);
+                data.extend_from_slice(absolute_indices);
+                if absolute_indices.len() % 2 != 0 {
+                    data.push(0);
+                }
+            } else {
+                // Absolute mode can't represent fewer than 3 indices (as they would be confused
+                // with the escapes), so these are encoded as runs of one.
+                absolute_indices.iter().for_each(|&index| data.extend_from_slice(&[1, index]));
+            }
+
+            x = end;
+        }
+
+        if y == row_count - 1 {
+            data.extend_from_slice(&[0, 1]);
+        } else {
+            data.extend_from_slice(&[0, 0]);
+        }
+    }
+
+    data
+}
+
+/// Get the number of times the index at `start` is repeated (up to 255, the longest run that can
+/// be encoded).
+fn run_length_at(row: &[u8], start: usize) -> usize {
+    row[start..].iter()
+        .take(255)
+        .take_while(|&&index| index == row[start])
+        .count()
+}
@@ -0,0 +1,70 @@
+use crate::{math, Bitmap, EdgeMode, Pixel24Bit};
+
+/// Linearly interpolate from `a` to `b` by `t`.
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+impl Bitmap<Pixel24Bit> {
+    /// Sample the image at the normalized `(x, y)` coordinate (see
+    /// [Bitmap::normalized_to_coords]), by bilinearly interpolating between the four pixels
+    /// surrounding the coordinate, with each channel rounded to the nearest value.
+    ///
+    /// Sampling the center of a pixel gives exactly that pixel, and pixels beyond the edges of the
+    /// image are found according to `edge`. Returns `None` if either coordinate isn't finite, or
+    /// the image has no pixels.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use bitmap_rs::{rgb, Bitmap, EdgeMode};
+    ///
+    /// let bitmap = Bitmap::new_from_pixels(2, 1, vec![rgb!(0, 0, 0), rgb!(255, 255, 255)]).unwrap();
+    ///
+    /// // The midpoint is halfway between the two colors.
+    /// let pixel = bitmap.sample_normalized_bilinear(0.5, 0.5, EdgeMode::Clamp).unwrap();
+    /// assert_eq!((pixel.red, pixel.green, pixel.blue), (128, 128, 128));
+    ///
+    /// // The center of each pixel is exactly that pixel.
+    /// assert_eq!(bitmap.sample_normalized_bilinear(0.25, 0.5, EdgeMode::Clamp).unwrap().red, 0);
+    /// assert_eq!(bitmap.sample_normalized_bilinear(0.75, 0.5, EdgeMode::Clamp).unwrap().red, 255);
+    ///
+    /// // Beyond the center of the last pixel, the edge is either held or blended with the
+    /// // opposite edge.
+    /// assert_eq!(bitmap.sample_normalized_bilinear(1.0, 0.5, EdgeMode::Clamp).unwrap().red, 255);
+    /// assert_eq!(bitmap.sample_normalized_bilinear(1.0, 0.5, EdgeMode::Wrap).unwrap().red, 128);
+    ///
+    /// assert!(bitmap.sample_normalized_bilinear(0.5, f64::INFINITY, EdgeMode::Clamp).is_none());
+    /// ```
+    pub fn sample_normalized_bilinear(&self, x: f64, y: f64, edge: EdgeMode) -> Option<Pixel24Bit> {
+        let (width, height) = (self.get_width(), self.get_height());
+        if !x.is_finite() || !y.is_finite() || width == 0 || height == 0 {
+            return None;
+        }
+
+        // Measure from the centers of the pixels, rather than their corners.
+        let x = x * f64::from(width) - 0.5;
+        let y = y * f64::from(height) - 0.5;
+        let (x0, y0) = (math::floor(x), math::floor(y));
+        let (x_weight, y_weight) = (x - x0, y - y0);
+
+        // The casts saturate, which is fine as any value that large is clamped or wrapped anyway.
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let corner = |dx: i64, dy: i64| {
+            self.get_pixel_at(edge.resolve(x0.saturating_add(dx), width), edge.resolve(y0.saturating_add(dy), height))
+        };
+        let corners = [corner(0, 0)?, corner(1, 0)?, corner(0, 1)?, corner(1, 1)?];
+
+        let channel = |get: fn(&Pixel24Bit) -> u8| {
+            let [first, first_next, second, second_next] = corners.map(|pixel| f64::from(get(pixel)));
+            let value = lerp(lerp(first, first_next, x_weight), lerp(second, second_next, x_weight), y_weight);
+            math::round(value).clamp(0.0, 255.0) as u8
+        };
+
+        Some(Pixel24Bit {
+            red: channel(|pixel| pixel.red),
+            green: channel(|pixel| pixel.green),
+            blue: channel(|pixel| pixel.blue),
+        })
+    }
+}
@@ -0,0 +1,32 @@
+//! Ensure the crate still builds without `std` (i.e., with `--no-default-features --features libm`), without
+//! relying on CI to run a separate build.
+//!
+//! The check runs a nested cargo build, which is slow and needs the dependencies to be available
+//! offline or over the network, so it is ignored by default. Run it with:
+//!
+//! ```sh
+//! cargo test -p bitmap-rs --test no_std -- --ignored
+//! ```
+
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+#[ignore = "runs a nested cargo build; run with `cargo test -p bitmap-rs --test no_std -- --ignored`"]
+fn builds_without_std() {
+    let manifest_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+
+    // Use a separate target directory, so that the build doesn't contend with the one running
+    // this test for the lock on the target directory.
+    let target_directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join("no-std");
+
+    let output = Command::new(env!("CARGO"))
+        .args(["build", "--lib", "--no-default-features", "--features", "libm", "--manifest-path"])
+        .arg(&manifest_path)
+        .arg("--target-dir")
+        .arg(&target_directory)
+        .output()
+        .expect("failed to run cargo");
+
+    assert!(output.status.success(), "no_std build failed:\n{}", String::from_utf8_lossy(&output.stderr));
+}
@@ -0,0 +1,37 @@
+//! Benchmark converting a full-size flag image to flag data, with the palette shipped with the
+//! editor.
+//!
+//! Run with `cargo bench -p mage_arena_flag_editor --bench encode_flag` (adding
+//! `--features parallel` to benchmark the parallel palette mapping). The throughput is reported
+//! in flag pixels encoded per second.
+
+use bitmap_rs::{Bitmap, Pixel24Bit};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use mage_arena_flag::flag::{encode_flag, EncodeOptions, FlagDimensions};
+use std::hint::black_box;
+
+const PALETTE: &[u8] = include_bytes!("../../palette.bmp");
+const FLAG: &[u8] = include_bytes!("../../custom_flag.bmp");
+
+fn encode(c: &mut Criterion) {
+    let palette: Bitmap<Pixel24Bit> = Bitmap::new_from_bytes(PALETTE.to_vec()).unwrap();
+    let dimensions = FlagDimensions::default();
+    let options = EncodeOptions::default();
+
+    let mut group = c.benchmark_group("encode_flag");
+    group.throughput(Throughput::Elements(dimensions.pixel_count() as u64));
+
+    group.bench_function(format!("{}x{}", dimensions.width, dimensions.height), |b| {
+        // encode_flag takes ownership of the image, so decode a fresh copy outside the timing.
+        b.iter_batched(
+            || Bitmap::<Pixel24Bit>::new_from_bytes(FLAG.to_vec()).unwrap(),
+            |flag| encode_flag(flag, black_box(&palette), &dimensions, &options).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, encode);
+criterion_main!(benches);
@@ -0,0 +1,75 @@
+use crate::error::Error;
+use crate::error::Error::{AccessFailure, UnexpectedValue};
+use crate::flag::{map_flag_to_palette, ColorMetric, FlagDimensions};
+use crate::mage_arena::read_bitmap_file;
+use bitmap_rs::{Bitmap, Pixel24Bit};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Map a single flag image to the palette, and write the raw flag data to the output file.
+fn convert_flag(palette: &Bitmap<Pixel24Bit>, input_file: &PathBuf, output_file: &Path, dimensions: &FlagDimensions) -> Result<(), Error> {
+    let flag = read_bitmap_file(input_file)?;
+    let raw_data = map_flag_to_palette(palette, &flag, dimensions, ColorMetric::default())?;
+
+    fs::write(output_file, raw_data)
+        .map_err(|err| AccessFailure(format!("failed to write flag data to {}: {err}", output_file.display())))
+}
+
+/// Whether the file has the given extension (ignoring case).
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension().is_some_and(|file_extension| file_extension.eq_ignore_ascii_case(extension))
+}
+
+/// Map every bitmap image in the input directory to the palette, writing the raw flag data of
+/// each to a correspondingly named `.txt` file in the output directory (in the same format as flag
+/// backups), without touching the registry.
+///
+/// Only `.bmp` images are converted, as PNG images can't be read; any PNG images in the directory
+/// are skipped with a warning, so that they aren't silently left out.
+///
+/// Every image is converted even if some of them fail, and the failures are summarized at the end.
+pub fn batch_convert(palette_file: PathBuf, input_directory: &Path, output_directory: &Path, dimensions: &FlagDimensions) -> Result<(), Error> {
+    let palette = read_bitmap_file(&palette_file)?;
+
+    let mut input_files: Vec<PathBuf> = fs::read_dir(input_directory)
+        .map_err(|err| AccessFailure(format!("could not access the input directory ({}): {err}", input_directory.display())))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+    input_files.sort();
+
+    let (png_files, other_files): (Vec<PathBuf>, Vec<PathBuf>) = input_files.into_iter().partition(|path| has_extension(path, "png"));
+    for png_file in &png_files {
+        warn!("Skipping {}: PNG images aren't supported, so convert it to a 24-bit bitmap first", png_file.display());
+    }
+    let input_files: Vec<PathBuf> = other_files.into_iter().filter(|path| has_extension(path, "bmp")).collect();
+
+    fs::create_dir_all(output_directory)
+        .map_err(|err| AccessFailure(format!("could not create the output directory: {err}")))?;
+
+    let mut failures: Vec<String> = vec![];
+    for input_file in &input_files {
+        let Some(file_stem) = input_file.file_stem() else {
+            continue;
+        };
+
+        let output_file = output_directory.join(file_stem).with_extension("txt");
+        match convert_flag(&palette, input_file, &output_file, dimensions) {
+            Ok(()) => info!("Converted {} to {}", input_file.display(), output_file.display()),
+            Err(err) => failures.push(format!("{}: {err}", input_file.display())),
+        }
+    }
+
+    println!("Converted {} of {} images.", input_files.len() - failures.len(), input_files.len());
+
+    if !failures.is_empty() {
+        return Err(UnexpectedValue(format!(
+            "failed to convert {} images\n\n{}",
+            failures.len(),
+            failures.join("\n")
+        )));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,439 @@
+use crate::error::Error;
+use crate::error::Error::{AccessFailure, UnexpectedValue};
+use crate::flag::{ColorMetric, CoordinateRounding, PaletteSampling, RecordSeparators};
+use crate::{Commands, FlagDimensionArgs};
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// The file name of the config file.
+pub const CONFIG_FILE_NAME: &str = "mageflag.toml";
+
+/// The defaults for the dimensions of the flag grid, which apply to every command.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct FlagConfig {
+    pub flag_width: Option<usize>,
+    pub flag_height: Option<usize>,
+    pub pixel_size: Option<usize>,
+    pub separators: Option<RecordSeparators>,
+    pub strict_trailing: Option<bool>,
+}
+
+/// The defaults for the `read` command.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ReadConfig {
+    pub palette_file: Option<PathBuf>,
+    pub output_file: Option<PathBuf>,
+    pub sample: Option<PaletteSampling>,
+    pub coord_rounding: Option<CoordinateRounding>,
+    pub supersample: Option<u32>,
+    pub flip_y: Option<bool>,
+    pub stats: Option<bool>,
+}
+
+/// The defaults for the `write` command.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct WriteConfig {
+    pub palette_file: Option<PathBuf>,
+    pub input_file: Option<PathBuf>,
+    pub preview: Option<bool>,
+    pub dry_run: Option<bool>,
+    pub no_backup: Option<bool>,
+    pub backup_dir: Option<PathBuf>,
+    pub preprocess: Option<String>,
+    pub metric: Option<ColorMetric>,
+    pub flip_y: Option<bool>,
+    pub tie_break: Option<(f64, f64)>,
+    pub auto_trim: Option<bool>,
+}
+
+/// The defaults for the `write-csv` command.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct WriteCsvConfig {
+    pub dry_run: Option<bool>,
+    pub no_backup: Option<bool>,
+    pub backup_dir: Option<PathBuf>,
+}
+
+/// The defaults for the `watch` command.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct WatchConfig {
+    pub palette_file: Option<PathBuf>,
+    pub input_file: Option<PathBuf>,
+    pub no_backup: Option<bool>,
+    pub backup_dir: Option<PathBuf>,
+    pub preprocess: Option<String>,
+    pub metric: Option<ColorMetric>,
+    pub flip_y: Option<bool>,
+    pub tie_break: Option<(f64, f64)>,
+    pub auto_trim: Option<bool>,
+}
+
+/// The defaults for the `undo` command.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct UndoConfig {
+    pub backup_dir: Option<PathBuf>,
+}
+
+/// The defaults for the `generate-palette` command.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct GeneratePaletteConfig {
+    pub output: Option<PathBuf>,
+    pub colors: Option<usize>,
+    pub seed: Option<u64>,
+}
+
+/// The defaults for the `generate-gradient` command.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct GenerateGradientConfig {
+    pub output: Option<PathBuf>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+/// The defaults for the `generate-hsv-palette` command.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct GenerateHsvPaletteConfig {
+    pub output: Option<PathBuf>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+/// The defaults for the `batch` command.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct BatchConfig {
+    pub palette_file: Option<PathBuf>,
+}
+
+/// The defaults for the `export-reg` command.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ExportRegConfig {
+    pub output: Option<PathBuf>,
+}
+
+/// The defaults for the `palette-map` command.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct PaletteMapConfig {
+    pub output: Option<PathBuf>,
+}
+
+/// The defaults for the `quality` command.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct QualityConfig {
+    pub palette_file: Option<PathBuf>,
+    pub input_file: Option<PathBuf>,
+    pub threshold: Option<f64>,
+    pub metric: Option<ColorMetric>,
+}
+
+/// The defaults for the `swatches` command.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct SwatchesConfig {
+    pub palette_file: Option<PathBuf>,
+}
+
+/// The config file, which supplies the defaults for the command line options.
+///
+/// Each table mirrors the options of a command (e.g., `[write]` for `write`), except for `[flag]`,
+/// which supplies the flag dimensions for every command. Options given explicitly on the command
+/// line take precedence over the config file (and switches can be turned off with, e.g.,
+/// `--dry-run=false`).
+///
+/// The `force` switch can only be given on the command line, so that the config file can't
+/// silently skip the check that Mage Arena isn't running before every write.
+///
+/// ```toml
+/// [flag]
+/// flag-width = 100
+///
+/// [write]
+/// palette-file = "palettes/default.bmp"
+/// preview = true
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub flag: FlagConfig,
+    pub read: ReadConfig,
+    pub write: WriteConfig,
+    pub write_csv: WriteCsvConfig,
+    pub watch: WatchConfig,
+    pub undo: UndoConfig,
+    pub generate_palette: GeneratePaletteConfig,
+    pub generate_gradient: GenerateGradientConfig,
+    pub generate_hsv_palette: GenerateHsvPaletteConfig,
+    pub batch: BatchConfig,
+    pub export_reg: ExportRegConfig,
+    pub palette_map: PaletteMapConfig,
+    pub quality: QualityConfig,
+    pub swatches: SwatchesConfig,
+}
+
+/// Get the paths that the config file is searched for in, in order of precedence: the current
+/// directory, then the user config directory.
+fn config_file_candidates() -> Vec<PathBuf> {
+    let user_config_directory = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+
+    let mut candidates = vec![PathBuf::from(CONFIG_FILE_NAME)];
+    if let Some(user_config_directory) = user_config_directory {
+        candidates.push(user_config_directory.join("mageflag").join(CONFIG_FILE_NAME));
+    }
+
+    candidates
+}
+
+impl Config {
+    /// Load the first config file that exists, or the default (empty) config if there is none.
+    pub fn load() -> Result<Config, Error> {
+        let Some(config_file) = config_file_candidates().into_iter().find(|path| path.is_file()) else {
+            return Ok(Config::default());
+        };
+
+        let contents = fs::read_to_string(&config_file)
+            .map_err(|err| AccessFailure(format!("failed to read config file ({}): {err}", config_file.display())))?;
+
+        let config = toml::from_str(&contents)
+            .map_err(|err| UnexpectedValue(format!("invalid config file ({}): {err}", config_file.display())))?;
+
+        info!("Loaded config from {}", config_file.display());
+        Ok(config)
+    }
+
+    /// Apply the config to any options of the command that weren't given on the command line.
+    pub fn apply(&self, command: &mut Commands, matches: &ArgMatches) {
+        let Some((_, matches)) = matches.subcommand() else {
+            return;
+        };
+
+        match command {
+            Commands::Read { palette_file, output_file, reg_file: _, sample, coord_rounding, supersample, flip_y, stats, csv: _, dimensions } => {
+                merge(matches, "palette_file", palette_file, &self.read.palette_file);
+                merge(matches, "output_file", output_file, &self.read.output_file);
+                merge(matches, "sample", sample, &self.read.sample);
+                merge(matches, "coord_rounding", coord_rounding, &self.read.coord_rounding);
+                merge(matches, "supersample", supersample, &self.read.supersample);
+                merge(matches, "flip_y", flip_y, &self.read.flip_y);
+                merge(matches, "stats", stats, &self.read.stats);
+                self.flag.apply(dimensions, matches);
+            },
+
+            Commands::Write { palette_file, input_file, preview, dry_run, no_backup, backup_dir, force: _, encode, dimensions } => {
+                merge(matches, "palette_file", palette_file, &self.write.palette_file);
+                merge(matches, "input_file", input_file, &self.write.input_file);
+                merge(matches, "preview", preview, &self.write.preview);
+                merge(matches, "dry_run", dry_run, &self.write.dry_run);
+                merge(matches, "no_backup", no_backup, &self.write.no_backup);
+                merge(matches, "backup_dir", backup_dir, &self.write.backup_dir);
+                merge(matches, "preprocess", &mut encode.preprocess, &self.write.preprocess.clone().map(Some));
+                merge(matches, "metric", &mut encode.metric, &self.write.metric);
+                merge(matches, "flip_y", &mut encode.flip_y, &self.write.flip_y);
+                merge(matches, "tie_break", &mut encode.tie_break, &self.write.tie_break.map(Some));
+                merge(matches, "auto_trim", &mut encode.auto_trim, &self.write.auto_trim);
+                self.flag.apply(dimensions, matches);
+            },
+
+            Commands::WriteCsv { input: _, dry_run, no_backup, backup_dir, force: _, dimensions } => {
+                merge(matches, "dry_run", dry_run, &self.write_csv.dry_run);
+                merge(matches, "no_backup", no_backup, &self.write_csv.no_backup);
+                merge(matches, "backup_dir", backup_dir, &self.write_csv.backup_dir);
+                self.flag.apply(dimensions, matches);
+            },
+
+            Commands::Watch { palette_file, input_file, no_backup, backup_dir, force: _, encode, dimensions } => {
+                merge(matches, "palette_file", palette_file, &self.watch.palette_file);
+                merge(matches, "input_file", input_file, &self.watch.input_file);
+                merge(matches, "no_backup", no_backup, &self.watch.no_backup);
+                merge(matches, "backup_dir", backup_dir, &self.watch.backup_dir);
+                merge(matches, "preprocess", &mut encode.preprocess, &self.watch.preprocess.clone().map(Some));
+                merge(matches, "metric", &mut encode.metric, &self.watch.metric);
+                merge(matches, "flip_y", &mut encode.flip_y, &self.watch.flip_y);
+                merge(matches, "tie_break", &mut encode.tie_break, &self.watch.tie_break.map(Some));
+                merge(matches, "auto_trim", &mut encode.auto_trim, &self.watch.auto_trim);
+                self.flag.apply(dimensions, matches);
+            },
+
+            Commands::Undo { backup_dir, dimensions } => {
+                merge(matches, "backup_dir", backup_dir, &self.undo.backup_dir);
+                self.flag.apply(dimensions, matches);
+            },
+
+            Commands::GeneratePalette { input: _, output, colors, seed } => {
+                merge(matches, "output", output, &self.generate_palette.output);
+                merge(matches, "colors", colors, &self.generate_palette.colors);
+                merge(matches, "seed", seed, &self.generate_palette.seed.map(Some));
+            },
+
+            Commands::GenerateGradient { output, start: _, end: _, direction: _, width, height } => {
+                merge(matches, "output", output, &self.generate_gradient.output);
+                merge(matches, "width", width, &self.generate_gradient.width);
+                merge(matches, "height", height, &self.generate_gradient.height);
+            },
+
+            Commands::GenerateHsvPalette { output, width, height } => {
+                merge(matches, "output", output, &self.generate_hsv_palette.output);
+                merge(matches, "width", width, &self.generate_hsv_palette.width);
+                merge(matches, "height", height, &self.generate_hsv_palette.height);
+            },
+
+            Commands::Batch { palette_file, input_dir: _, output_dir: _, dimensions } => {
+                merge(matches, "palette_file", palette_file, &self.batch.palette_file);
+                self.flag.apply(dimensions, matches);
+            },
+
+            Commands::ExportReg { output } => {
+                merge(matches, "output", output, &self.export_reg.output);
+            },
+
+            Commands::PaletteMap { output, reg_file: _, dimensions } => {
+                merge(matches, "output", output, &self.palette_map.output);
+                self.flag.apply(dimensions, matches);
+            },
+
+            Commands::Quality { palette_file, input_file, threshold, metric, dimensions } => {
+                merge(matches, "palette_file", palette_file, &self.quality.palette_file);
+                merge(matches, "input_file", input_file, &self.quality.input_file);
+                merge(matches, "threshold", threshold, &self.quality.threshold);
+                merge(matches, "metric", metric, &self.quality.metric);
+                self.flag.apply(dimensions, matches);
+            },
+
+            Commands::Swatches { palette_file } => {
+                merge(matches, "palette_file", palette_file, &self.swatches.palette_file);
+            },
+
+            Commands::Diff { first_file: _, second_file: _ } => {},
+
+            Commands::Inspect => {},
+        }
+    }
+}
+
+impl FlagConfig {
+    fn apply(&self, dimensions: &mut FlagDimensionArgs, matches: &ArgMatches) {
+        merge(matches, "flag_width", &mut dimensions.flag_width, &self.flag_width);
+        merge(matches, "flag_height", &mut dimensions.flag_height, &self.flag_height);
+        merge(matches, "pixel_size", &mut dimensions.pixel_size, &self.pixel_size);
+        merge(matches, "separators", &mut dimensions.separators, &self.separators);
+        merge(matches, "strict_trailing", &mut dimensions.strict_trailing, &self.strict_trailing);
+    }
+}
+
+/// Replace the value of an option with the value from the config, unless the option was given on
+/// the command line.
+fn merge<T: Clone>(matches: &ArgMatches, id: &str, value: &mut T, config_value: &Option<T>) {
+    if matches.value_source(id) != Some(ValueSource::CommandLine)
+        && let Some(config_value) = config_value {
+        *value = config_value.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cli;
+    use clap::{CommandFactory, FromArgMatches};
+
+    /// Parse the command line, and apply the config file to the command.
+    fn parse_with_config(args: &[&str], config: &str) -> Commands {
+        let matches = Cli::command().try_get_matches_from(["mageflag"].iter().chain(args)).unwrap();
+        let mut command = Cli::from_arg_matches(&matches).unwrap().command.unwrap();
+
+        toml::from_str::<Config>(config).unwrap().apply(&mut command, &matches);
+        command
+    }
+
+    #[test]
+    fn command_line_takes_precedence_over_config_over_defaults() {
+        let config = r#"
+            [flag]
+            flag-width = 50
+
+            [write]
+            palette-file = "palettes/default.bmp"
+            metric = "redmean"
+            dry-run = true
+            preview = true
+        "#;
+
+        let Commands::Write { palette_file, encode, dry_run, preview, no_backup, dimensions, .. } = parse_with_config(&["write"], "") else { panic!() };
+        assert_eq!((palette_file, encode.metric, dry_run, preview, no_backup), (PathBuf::from("palette.bmp"), ColorMetric::Euclidean, false, false, false));
+        assert_eq!(dimensions.flag_width, 100);
+
+        let Commands::Write { palette_file, encode, dry_run, preview, no_backup, dimensions, .. } = parse_with_config(&["write"], config) else { panic!() };
+        assert_eq!((palette_file, encode.metric, dry_run, preview, no_backup), (PathBuf::from("palettes/default.bmp"), ColorMetric::Redmean, true, true, false));
+        assert_eq!(dimensions.flag_width, 50);
+
+        let args = ["write", "-p", "other.bmp", "--metric", "euclidean", "--dry-run=false", "--preview", "--no-backup", "--flag-width", "20"];
+        let Commands::Write { palette_file, encode, dry_run, preview, no_backup, dimensions, .. } = parse_with_config(&args, config) else { panic!() };
+        assert_eq!((palette_file, encode.metric, dry_run, preview, no_backup), (PathBuf::from("other.bmp"), ColorMetric::Euclidean, false, true, true));
+        assert_eq!(dimensions.flag_width, 20);
+    }
+
+    #[test]
+    fn config_only_applies_to_its_own_command() {
+        let config = r#"
+            [read]
+            flip-y = true
+
+            [write]
+            flip-y = false
+        "#;
+
+        let Commands::Read { flip_y, .. } = parse_with_config(&["read"], config) else { panic!() };
+        assert!(flip_y);
+
+        let Commands::Write { encode, .. } = parse_with_config(&["write", "--flip-y"], config) else { panic!() };
+        assert!(encode.flip_y);
+    }
+
+    #[test]
+    fn watch_reads_the_same_encode_options_as_write() {
+        let config = r#"
+            [watch]
+            preprocess = "gamma=2.2"
+            metric = "redmean"
+            auto-trim = true
+        "#;
+
+        let args = ["watch", "--flip-y", "--tie-break", "0.5,0.5", "--auto-trim=false"];
+        let Commands::Watch { encode, .. } = parse_with_config(&args, config) else { panic!() };
+        assert_eq!(encode.preprocess.as_deref(), Some("gamma=2.2"));
+        assert_eq!(encode.metric, ColorMetric::Redmean);
+        assert!(encode.flip_y);
+        assert_eq!(encode.tie_break, Some((0.5, 0.5)));
+        assert!(!encode.auto_trim);
+    }
+
+    #[test]
+    fn force_is_refused_in_config() {
+        for table in ["write", "write-csv", "watch"] {
+            let err = toml::from_str::<Config>(&format!("[{table}]\nforce = true\n")).unwrap_err();
+            assert!(err.to_string().contains("unknown field `force`"), "{err}");
+        }
+
+        let Commands::Write { force, .. } = parse_with_config(&["write", "--force"], "") else { panic!() };
+        assert!(force);
+    }
+}
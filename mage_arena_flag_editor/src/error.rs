@@ -7,6 +7,10 @@ pub enum Error {
     /// An attempt to access a necessary resource failed.
     AccessFailure(String),
 
+    /// A necessary resource was temporarily unavailable (e.g., locked by another process), so the
+    /// attempt to access it may succeed if it is retried.
+    Busy(String),
+
     /// An unexpected value was encountered.
     UnexpectedValue(String),
 
@@ -18,6 +22,7 @@ impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Error::AccessFailure(msg) => write!(f, "access failure: {msg}"),
+            Error::Busy(msg) => write!(f, "resource busy: {msg}"),
             Error::UnexpectedValue(msg) => write!(f, "unexpected value: {msg}"),
             Error::External(err) => write!(f, "external error: {err}"),
         }
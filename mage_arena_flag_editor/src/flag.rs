@@ -0,0 +1,1592 @@
+use crate::error::Error;
+use crate::error::Error::{External, UnexpectedValue};
+use crate::preprocess::{apply_preprocess_steps, PreprocessStep};
+use bitmap_rs::{Bitmap, EdgeMode, Pixel, Pixel24Bit};
+use std::collections::{HashMap, HashSet};
+
+/// The width of the flag in pixels.
+pub const MAGE_ARENA_FLAG_WIDTH: i32 = 100;
+
+/// The height of the flag in pixels.
+pub const MAGE_ARENA_FLAG_HEIGHT: i32 = 66;
+
+/// The number of bytes used to represent a pixel.
+pub const MAGE_ARENA_FLAG_PIXEL_SIZE: usize = 10;
+
+/// The dimensions of the flag grid, and the size and separators of each of its records.
+///
+/// Defaults to the dimensions used by the current version of the game.
+#[derive(Debug, Clone, Copy)]
+pub struct FlagDimensions {
+    /// The width of the flag in pixels.
+    pub width: usize,
+
+    /// The height of the flag in pixels.
+    pub height: usize,
+
+    /// The number of bytes used to represent a pixel.
+    pub pixel_size: usize,
+
+    /// The separators used in each record.
+    pub separators: RecordSeparators,
+
+    /// Whether the last record must end with a null, as written by the game. Otherwise, it may
+    /// also end with the record delimiter, as some flags do.
+    pub strict_trailing: bool,
+}
+
+impl FlagDimensions {
+    pub fn new(width: usize, height: usize, pixel_size: usize) -> Result<Self, Error> {
+        if width == 0 || height == 0 {
+            return Err(UnexpectedValue(format!("flag dimensions must be non-zero (got {width}x{height})")));
+        }
+
+        // Each record is formatted as `0.<digits>:0.<digits>` followed by a trailing character.
+        if pixel_size < 8 || !pixel_size.is_multiple_of(2) {
+            return Err(UnexpectedValue(format!("pixel size must be an even number of at least 8 bytes (got {pixel_size})")));
+        }
+
+        Ok(FlagDimensions { width, height, pixel_size, separators: RecordSeparators::Auto, strict_trailing: true })
+    }
+
+    /// Get the number of pixels in the flag.
+    pub fn pixel_count(&self) -> usize {
+        self.width * self.height
+    }
+
+    /// Get the number of decimal places used for each coordinate in a record.
+    fn precision(&self) -> usize {
+        (self.pixel_size - 6) / 2
+    }
+}
+
+impl Default for FlagDimensions {
+    fn default() -> Self {
+        FlagDimensions {
+            width: MAGE_ARENA_FLAG_WIDTH as usize,
+            height: MAGE_ARENA_FLAG_HEIGHT as usize,
+            pixel_size: MAGE_ARENA_FLAG_PIXEL_SIZE,
+            separators: RecordSeparators::Auto,
+            strict_trailing: true,
+        }
+    }
+}
+
+/// The separators used in the records of the raw flag data, which depend on the locale of the
+/// game build.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RecordSeparators {
+    /// Detect the separators from the flag data (falling back to periods if there is none).
+    #[default]
+    Auto,
+
+    /// Period decimal separators, with records delimited by commas (e.g., `0.50:0.25,`).
+    Period,
+
+    /// Comma decimal separators, with records delimited by semicolons (e.g., `0,50:0,25;`).
+    Comma,
+}
+
+impl RecordSeparators {
+    /// Resolve [RecordSeparators::Auto] to the separators used by the raw flag data, by checking
+    /// the decimal separator of the first coordinate. Other separators are returned as they are.
+    pub fn resolve(self, raw_data: &[u8]) -> RecordSeparators {
+        if self != RecordSeparators::Auto {
+            return self;
+        }
+
+        let first_coordinate = raw_data.split(|&b| b == b':').next().unwrap_or_default();
+        if raw_data.contains(&b':') && first_coordinate.contains(&b',') {
+            RecordSeparators::Comma
+        } else {
+            RecordSeparators::Period
+        }
+    }
+
+    /// Get the character that ends each record (other than the last, which ends with a null).
+    fn delimiter(self) -> char {
+        match self {
+            RecordSeparators::Comma => ';',
+            RecordSeparators::Auto | RecordSeparators::Period => ',',
+        }
+    }
+
+    /// Get the decimal separator of each coordinate.
+    fn decimal_separator(self) -> char {
+        match self {
+            RecordSeparators::Comma => ',',
+            RecordSeparators::Auto | RecordSeparators::Period => '.',
+        }
+    }
+}
+
+/// Parse the raw flag data into the normalized `(x, y)` palette coordinate of each flag pixel.
+///
+/// The registry values are column-ordered, so the coordinates are transposed into row-ordered
+/// form (i.e., the same order as the pixels of a bitmap image).
+pub fn parse_flag_records(raw_data: &[u8], dimensions: &FlagDimensions) -> Result<Vec<(f64, f64)>, Error> {
+    if raw_data.is_empty() {
+        return Err(UnexpectedValue("flag data is missing".to_string()));
+    }
+
+    let FlagDimensions { width, height, pixel_size, separators, strict_trailing } = *dimensions;
+    let separators = separators.resolve(raw_data);
+    let expected_length = dimensions.pixel_count() * pixel_size;
+    if raw_data.len() != expected_length {
+        return Err(UnexpectedValue(format!(
+            "raw flag data is {} bytes, but a {width}x{height} flag with {pixel_size}-byte pixels should be {expected_length} bytes",
+            raw_data.len()
+        )));
+    }
+
+    // Split the raw data into chunks.
+    let raw_pixels: Vec<&[u8]> = raw_data.chunks_exact(pixel_size).collect();
+
+    // Perform a matrix transposition on the pixels - as the registry values are column-ordered
+    // while bitmap images are row-ordered.
+    let pixels: Vec<&[u8]> = (0..height)
+        .flat_map(|i| {
+            let raw_pixels = &raw_pixels;
+            (0..width).map(move |j| {
+                raw_pixels[j * height + i]
+            })
+        }).collect();
+
+    // Ensure that all chunks have the delimiter as the last byte (except the last chunk, which must
+    // have null, or may also have the delimiter if the trailing null isn't strictly required).
+    let mut bad_pixels: Vec<Error> = vec![];
+    let coordinates: Vec<(f64, f64)> = pixels.iter()
+        .enumerate()
+        .map(|(i, pixel)| {
+            // Ensure pixel data ends with the delimiter (or a null if it's the last pixel).
+            let is_last_pixel = i == pixels.len() - 1;
+
+            let expected_last_char = if is_last_pixel { 0 } else { separators.delimiter() as u8 };
+            let actual_last_char = pixel[pixel_size - 1];
+            let is_lenient_last_char = is_last_pixel && !strict_trailing && actual_last_char == separators.delimiter() as u8;
+
+            if actual_last_char != expected_last_char && !is_lenient_last_char {
+                return Err(UnexpectedValue(format!("pixel {i} contains an invalid last character (expected: {expected_last_char}, got: {actual_last_char})")))
+            }
+
+            let Some(divider) = pixel.iter().position(|&b| b == 0x3A) else {
+                return Err(UnexpectedValue(format!("pixel {i} is missing the expected divider character (:)")))
+            };
+
+            // Normalize the decimal separator, as the float parser only accepts periods.
+            let decimal_separator = separators.decimal_separator();
+            let x_str = String::from_utf8(pixel[0..divider].to_vec())
+                .map_err(|err| UnexpectedValue(format!("pixel {i}'s x-coordinate was not valid UTF-8: {err}")))?;
+            let x = x_str.replace(decimal_separator, ".").parse::<f64>()
+                .map_err(|err| UnexpectedValue(format!("pixel {i}'s x-coordinate ({x_str}) was not a valid float: {err}")))?;
+
+            let y_str = String::from_utf8(pixel[divider+1..pixel_size - 1].to_vec())
+                .map_err(|err| UnexpectedValue(format!("pixel {i}'s y-coordinate was not valid UTF-8: {err}")))?;
+            let y = y_str.replace(decimal_separator, ".").parse::<f64>()
+                .map_err(|err| UnexpectedValue(format!("pixel {i}'s y-coordinate ({y_str}) was not a valid float: {err}")))?;
+
+            Ok((x, y))
+        })
+        .filter_map(|pixel| pixel.map_err(|e| bad_pixels.push(e)).ok())
+        .collect();
+
+    info!("Parsed {} flag pixels ({} bad pixels)", coordinates.len(), bad_pixels.len());
+
+    if !bad_pixels.is_empty() {
+        return Err(UnexpectedValue(format!(
+            "bad pixels\n\n{}",
+            bad_pixels.iter().map(|err| err.to_string()).collect::<Vec<String>>().join("\n")
+        )));
+    }
+
+    // Interpret every coordinate in the flag consistently, based on the format of the whole flag.
+    match detect_coordinate_format(&coordinates)? {
+        CoordinateFormat::Normalized => Ok(coordinates),
+        CoordinateFormat::Percentage => Ok(coordinates.into_iter().map(|(x, y)| (x / 100.0, y / 100.0)).collect()),
+    }
+}
+
+/// The format of the coordinates in the raw flag data.
+#[derive(Debug, PartialEq)]
+enum CoordinateFormat {
+    /// The coordinates are fractions of the palette dimensions, in the range [0.0, 1.0].
+    Normalized,
+
+    /// The coordinates are percentages of the palette dimensions, in the range [0.0, 100.0].
+    Percentage,
+}
+
+/// Detect the format of the coordinates of a flag.
+///
+/// A flag with any value above 1.0 is in percentage format. Percentages below 1% can't be
+/// distinguished from normalized coordinates, so a flag that also contains non-zero values below
+/// 1.0 is assumed to have mixed formats and is rejected.
+fn detect_coordinate_format(coordinates: &[(f64, f64)]) -> Result<CoordinateFormat, Error> {
+    let values = || coordinates.iter().flat_map(|&(x, y)| [x, y]);
+
+    let percentage_count = values().filter(|&value| value > 1.0).count();
+    if percentage_count == 0 {
+        return Ok(CoordinateFormat::Normalized);
+    }
+
+    let normalized_count = values().filter(|&value| value > 0.0 && value < 1.0).count();
+    if normalized_count > 0 {
+        return Err(UnexpectedValue(format!(
+            "flag contains a mix of percentage and normalized coordinates ({percentage_count} values above 1.0, {normalized_count} non-zero values below 1.0)"
+        )));
+    }
+
+    Ok(CoordinateFormat::Percentage)
+}
+
+/// Mirror the palette vertically, for a palette that was authored the other way up.
+///
+/// Flag coordinates have their origin at the first pixel stored in the palette file, which is the
+/// bottom-left corner of a (standard) bottom-up bitmap, with y increasing through the rows in the
+/// order they are stored. Sampling the mirrored palette is equivalent to sampling the original at
+/// `(x, 1.0 - y)`, but maps each row exactly onto its mirror image.
+pub fn flip_palette_rows(palette: &Bitmap<Pixel24Bit>) -> Bitmap<Pixel24Bit> {
+    let last_row = palette.get_height().saturating_sub(1);
+    palette.map_pixels(|x, y, pixel| *palette.get_pixel_at(x, last_row - y).unwrap_or(pixel))
+}
+
+/// Supersample the palette, by replacing each cell with the mean color of the `n`x`n` cells around
+/// it (clamped to the edges of the palette), so that sampling a cell of the result averages its
+/// neighborhood in the original.
+///
+/// An `n` of 1 leaves the palette unchanged.
+pub fn supersample_palette(palette: &Bitmap<Pixel24Bit>, n: u32) -> Result<Bitmap<Pixel24Bit>, Error> {
+    if n == 0 {
+        return Err(UnexpectedValue("the supersampling neighborhood must be at least 1 cell wide".to_string()));
+    }
+
+    // The neighborhood of a cell along one axis, clamped to the palette: its first cell and size.
+    let neighborhood = |cell: u32, size: u32| {
+        let first = cell.saturating_sub((n - 1) / 2);
+        let last = cell.saturating_add(n / 2).min(size - 1);
+        (first, last - first + 1)
+    };
+
+    let (width, height) = (palette.get_width(), palette.get_height());
+    let pixels = (0..palette.pixels.len())
+        .map(|i| {
+            let (x, y) = palette.coords_of(i);
+            let ((region_x, region_width), (region_y, region_height)) = (neighborhood(x, width), neighborhood(y, height));
+            palette.average_color_region(region_x, region_y, region_width, region_height)
+        })
+        .collect::<Result<Vec<Pixel24Bit>, _>>()
+        .map_err(|err| External(format!("failed to supersample the palette: {err}")))?;
+
+    Bitmap::new_from_pixels(palette.get_raw_width(), palette.get_raw_height(), pixels)
+        .map_err(|err| External(format!("failed to create bitmap image: {err}")))
+}
+
+/// How [concat_palettes] arranges the palettes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConcatLayout {
+    /// Side by side, from left to right. The palettes must all be the same height.
+    Horizontal,
+
+    /// One after another, in the order the rows are stored (so the first palette is at the bottom
+    /// of a bottom-up bitmap). The palettes must all be the same width.
+    Vertical,
+}
+
+/// Combine the palettes into a single palette, arranged by `layout`.
+///
+/// The combined palette has the orientation of the first palette, and the rows of any palette
+/// stored the other way up are reordered to match.
+pub fn concat_palettes(palettes: &[Bitmap<Pixel24Bit>], layout: ConcatLayout) -> Result<Bitmap<Pixel24Bit>, Error> {
+    let Some(first) = palettes.first() else {
+        return Err(UnexpectedValue("at least one palette is required".to_string()));
+    };
+
+    // The rows of a palette, in the order of the first palette.
+    fn rows(palette: &Bitmap<Pixel24Bit>, is_top_down: bool) -> Vec<&[Pixel24Bit]> {
+        let mut rows: Vec<&[Pixel24Bit]> = palette.pixels.chunks_exact(palette.get_width().max(1) as usize).collect();
+        if palette.is_top_down() != is_top_down {
+            rows.reverse();
+        }
+        rows
+    }
+
+    let (width, height, pixels) = match layout {
+        ConcatLayout::Horizontal => {
+            let height = first.get_height();
+            if let Some((i, palette)) = palettes.iter().enumerate().find(|(_, palette)| palette.get_height() != height) {
+                return Err(UnexpectedValue(format!(
+                    "palette {i} is {}x{} pixels, but palettes placed side by side must all be {height} pixels high",
+                    palette.get_width(), palette.get_height()
+                )));
+            }
+
+            let palette_rows: Vec<Vec<&[Pixel24Bit]>> = palettes.iter().map(|palette| rows(palette, first.is_top_down())).collect();
+            let pixels = (0..height as usize)
+                .flat_map(|y| palette_rows.iter().flat_map(move |rows| rows[y].iter().copied()))
+                .collect();
+
+            (palettes.iter().map(|palette| u64::from(palette.get_width())).sum(), u64::from(height), pixels)
+        },
+
+        ConcatLayout::Vertical => {
+            let width = first.get_width();
+            if let Some((i, palette)) = palettes.iter().enumerate().find(|(_, palette)| palette.get_width() != width) {
+                return Err(UnexpectedValue(format!(
+                    "palette {i} is {}x{} pixels, but palettes placed one after another must all be {width} pixels wide",
+                    palette.get_width(), palette.get_height()
+                )));
+            }
+
+            let pixels = palettes.iter()
+                .flat_map(|palette| rows(palette, first.is_top_down()).into_iter().flatten().copied())
+                .collect();
+
+            (u64::from(width), palettes.iter().map(|palette| u64::from(palette.get_height())).sum(), pixels)
+        },
+    };
+
+    let (Ok(width), Ok(height)) = (i32::try_from(width), i32::try_from(height)) else {
+        return Err(UnexpectedValue(format!("the combined palette ({width}x{height} pixels) is too large for a bitmap image")));
+    };
+
+    let height = if first.is_top_down() { -height } else { height };
+    Bitmap::new_from_pixels(width, height, pixels)
+        .map_err(|err| External(format!("failed to create bitmap image: {err}")))
+}
+
+/// Get the palette cell that the normalized `(x, y)` palette coordinate refers to.
+///
+/// A coordinate of 1.0 (or anything beyond the palette) is clamped to the last cell.
+fn palette_cell(palette: &Bitmap<Pixel24Bit>, i: usize, (x, y): (f64, f64)) -> Result<(u32, u32), Error> {
+    palette.normalized_to_coords(x, y, EdgeMode::Clamp)
+        .ok_or_else(|| unresolved_palette_pixel(palette, i, (x, y)))
+}
+
+/// The error for a flag pixel whose palette coordinate can't be resolved (e.g., because it isn't
+/// a finite number).
+fn unresolved_palette_pixel(palette: &Bitmap<Pixel24Bit>, i: usize, (x, y): (f64, f64)) -> Error {
+    UnexpectedValue(format!("failed to resolve palette pixel for pixel {i} ({x}, {y}) in a {}x{} palette", palette.get_width(), palette.get_height()))
+}
+
+/// How the palette color at a flag pixel's palette coordinate is sampled.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PaletteSampling {
+    /// The color of the palette cell that contains the coordinate, as the game does.
+    #[default]
+    Nearest,
+
+    /// A blend of the four palette cells surrounding the coordinate, which smooths gradients in
+    /// small palettes.
+    Bilinear,
+}
+
+/// How a normalized palette coordinate is rounded to a palette cell (see
+/// [palette_coordinate_to_cell]), in case a palette's width or height makes the game land on a
+/// different cell than expected.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CoordinateRounding {
+    /// The cell that contains the coordinate, `floor(coordinate * size)`, so 0.5 is the second
+    /// cell of both a 2 and a 3 cell wide palette.
+    #[default]
+    Floor,
+
+    /// The cell whose leading edge is nearest the coordinate, `round(coordinate * size)`, so 0.5
+    /// is the second cell of a 2 cell wide palette, but the third cell of a 3 cell wide palette.
+    Round,
+
+    /// The cell whose center is nearest the coordinate, where 0.0 and 1.0 are the centers of the
+    /// first and last cells, `round(coordinate * (size - 1))`, so 0.5 is the second cell of both a
+    /// 2 and a 3 cell wide palette (rounding halfway away from zero).
+    NearestCenter,
+}
+
+/// Map a normalized palette coordinate onto one of `size` palette cells with the given rounding,
+/// clamping it to the first or last cell, or `None` if the coordinate isn't finite or there are
+/// no cells.
+///
+/// [CoordinateRounding::Floor] gives the same cell as [Bitmap::normalized_to_coords] (with
+/// [EdgeMode::Clamp]).
+pub fn palette_coordinate_to_cell(coordinate: f64, size: u32, rounding: CoordinateRounding) -> Option<u32> {
+    if !coordinate.is_finite() || size == 0 {
+        return None;
+    }
+
+    let last_cell = f64::from(size - 1);
+    let cell = match rounding {
+        CoordinateRounding::Floor => (coordinate * f64::from(size)).floor(),
+        CoordinateRounding::Round => (coordinate * f64::from(size)).round(),
+        CoordinateRounding::NearestCenter => (coordinate * last_cell).round(),
+    };
+
+    Some(cell.clamp(0.0, last_cell) as u32)
+}
+
+/// Get each distinct color of the palette with the normalized `(x, y)` coordinate of its first
+/// cell (as `write` would encode it), sorted from darkest to brightest.
+pub fn palette_swatches(palette: &Bitmap<Pixel24Bit>) -> Vec<(Pixel24Bit, (f64, f64))> {
+    let (width, height) = (f64::from(palette.get_width()), f64::from(palette.get_height()));
+
+    let mut swatches: Vec<(Pixel24Bit, (f64, f64))> = palette.unique_palette().into_iter()
+        .map(|(color, (x, y))| (color, (f64::from(x) / width, f64::from(y) / height)))
+        .collect();
+    swatches.sort_by(|(a, _), (b, _)| a.luminance().total_cmp(&b.luminance()));
+
+    swatches
+}
+
+/// Resolve the normalized `(x, y)` palette coordinate of each flag pixel to the palette color at
+/// that coordinate, rounding it to a palette cell with `rounding` for [PaletteSampling::Nearest].
+fn resolve_flag_pixels(palette: &Bitmap<Pixel24Bit>, coordinates: &[(f64, f64)], sampling: PaletteSampling, rounding: CoordinateRounding) -> Result<Vec<Pixel24Bit>, Error> {
+    let mut bad_pixels: Vec<Error> = vec![];
+    let pixels: Vec<Pixel24Bit> = coordinates.iter()
+        .enumerate()
+        .map(|(i, &(x, y))| {
+            let palette_pixel = match sampling {
+                PaletteSampling::Nearest => palette_coordinate_to_cell(x, palette.get_width(), rounding)
+                    .zip(palette_coordinate_to_cell(y, palette.get_height(), rounding))
+                    .and_then(|(x, y)| palette.get_pixel_at(x, y))
+                    .copied(),
+                PaletteSampling::Bilinear => palette.sample_normalized_bilinear(x, y, EdgeMode::Clamp),
+            };
+
+            palette_pixel.ok_or_else(|| unresolved_palette_pixel(palette, i, (x, y)))
+        })
+        .filter_map(|pixel| pixel.map_err(|e| bad_pixels.push(e)).ok())
+        .collect();
+
+    info!("Resolved {} flag pixels against the palette ({} bad pixels)", pixels.len(), bad_pixels.len());
+
+    if !bad_pixels.is_empty() {
+        return Err(UnexpectedValue(format!(
+            "bad pixels\n\n{}",
+            bad_pixels.iter().map(|err| err.to_string()).collect::<Vec<String>>().join("\n")
+        )));
+    }
+
+    Ok(pixels)
+}
+
+/// Build the flag image from the normalized `(x, y)` palette coordinate of each flag pixel (in row
+/// order), by resolving each coordinate to the palette color at that coordinate (sampled with
+/// `sampling`).
+pub fn resolve_flag_image(palette: &Bitmap<Pixel24Bit>, coordinates: &[(f64, f64)], dimensions: &FlagDimensions, sampling: PaletteSampling) -> Result<Bitmap<Pixel24Bit>, Error> {
+    resolve_flag_image_rounded(palette, coordinates, dimensions, sampling, CoordinateRounding::Floor)
+}
+
+/// Build the flag image as [resolve_flag_image] does, but rounding each coordinate to a palette
+/// cell with `rounding` (see [palette_coordinate_to_cell]) when sampling the nearest cell.
+pub fn resolve_flag_image_rounded(palette: &Bitmap<Pixel24Bit>, coordinates: &[(f64, f64)], dimensions: &FlagDimensions, sampling: PaletteSampling, rounding: CoordinateRounding) -> Result<Bitmap<Pixel24Bit>, Error> {
+    let pixels = resolve_flag_pixels(palette, coordinates, sampling, rounding)?;
+
+    let width = i32::try_from(dimensions.width)
+        .map_err(|_| UnexpectedValue(format!("flag width ({}) is too large for a bitmap image", dimensions.width)))?;
+    let height = i32::try_from(dimensions.height)
+        .map_err(|_| UnexpectedValue(format!("flag height ({}) is too large for a bitmap image", dimensions.height)))?;
+
+    Bitmap::new_from_pixels(width, height, pixels)
+        .map_err(|err| External(format!("failed to create bitmap image: {err}")))
+}
+
+/// Statistics about how a flag samples its palette.
+#[derive(Debug, Clone, Copy)]
+pub struct FlagStats {
+    /// The number of distinct palette colors used by the flag.
+    pub distinct_colors: usize,
+
+    /// The most frequently sampled palette color, and the number of flag pixels that use it.
+    pub most_common_color: (Pixel24Bit, usize),
+
+    /// The smallest and largest `(x, y)` palette cells sampled by the flag.
+    pub sampled_bounds: ((u32, u32), (u32, u32)),
+}
+
+/// Compute statistics about how the flag, given the normalized `(x, y)` palette coordinate of
+/// each of its pixels, samples the palette.
+///
+/// Returns `None` if there are no coordinates.
+pub fn flag_stats(palette: &Bitmap<Pixel24Bit>, coordinates: &[(f64, f64)]) -> Result<Option<FlagStats>, Error> {
+    let mut color_counts: HashMap<u32, (Pixel24Bit, usize)> = HashMap::new();
+    let mut sampled_bounds: Option<((u32, u32), (u32, u32))> = None;
+
+    for (i, &coordinate) in coordinates.iter().enumerate() {
+        let (x, y) = palette_cell(palette, i, coordinate)?;
+        let pixel = palette.try_get_pixel_at(x, y)
+            .map_err(|err| UnexpectedValue(format!("failed to resolve palette pixel for pixel {i}: {err}")))?;
+
+        color_counts.entry(pack_rgb(pixel)).or_insert((*pixel, 0)).1 += 1;
+        sampled_bounds = Some(match sampled_bounds {
+            Some(((min_x, min_y), (max_x, max_y))) => ((min_x.min(x), min_y.min(y)), (max_x.max(x), max_y.max(y))),
+            None => ((x, y), (x, y)),
+        });
+    }
+
+    // Break ties by color, so that the result doesn't depend on the iteration order of the map.
+    let most_common_color = color_counts.iter()
+        .max_by_key(|&(&key, &(_, count))| (count, std::cmp::Reverse(key)))
+        .map(|(_, &most_common)| most_common);
+
+    Ok(most_common_color.zip(sampled_bounds).map(|(most_common_color, sampled_bounds)| FlagStats {
+        distinct_colors: color_counts.len(),
+        most_common_color,
+        sampled_bounds,
+    }))
+}
+
+/// The largest number of palette cells along each side of a palette usage map.
+const MAX_PALETTE_MAP_SIZE: usize = 1000;
+
+/// Build a map of the palette cells that the flag samples, given the normalized `(x, y)` palette
+/// coordinate of each of its pixels.
+///
+/// The palette colors aren't stored with the flag, so the map only shows the palette layout that
+/// the flag expects: it has one pixel for each palette cell that a coordinate can address (e.g.,
+/// 100x100 for coordinates with two decimal places), in the same orientation as the palette, and
+/// each cell that the flag samples is white while the rest are black.
+pub fn palette_usage_map(coordinates: &[(f64, f64)], dimensions: &FlagDimensions) -> Result<Bitmap<Pixel24Bit>, Error> {
+    let size = u32::try_from(dimensions.precision()).ok()
+        .and_then(|precision| 10usize.checked_pow(precision))
+        .filter(|&size| size <= MAX_PALETTE_MAP_SIZE)
+        .ok_or_else(|| UnexpectedValue(format!(
+            "coordinates with {} decimal places address too many palette cells to map (at most {MAX_PALETTE_MAP_SIZE} per side)",
+            dimensions.precision()
+        )))?;
+
+    let unused = Pixel24Bit { red: 0, green: 0, blue: 0 };
+    let used = Pixel24Bit { red: 255, green: 255, blue: 255 };
+
+    let mut pixels = vec![unused; size * size];
+    for &(x, y) in coordinates {
+        // A coordinate of 1.0 addresses the last cell, rather than one past it.
+        let cell = |value: f64| ((value * size as f64).round() as usize).min(size - 1);
+        pixels[cell(y) * size + cell(x)] = used;
+    }
+
+    Bitmap::new_from_pixels(size as i32, size as i32, pixels)
+        .map_err(|err| External(format!("failed to create bitmap image: {err}")))
+}
+
+/// Decode the raw flag data into the flag image, by resolving each pixel against the palette.
+///
+/// This is the transformation performed by `read`, without any IO.
+pub fn decode_flag(raw_data: &[u8], palette: &Bitmap<Pixel24Bit>, dimensions: &FlagDimensions) -> Result<Bitmap<Pixel24Bit>, Error> {
+    resolve_flag_image(palette, &parse_flag_records(raw_data, dimensions)?, dimensions, PaletteSampling::Nearest)
+}
+
+/// Encode the normalized `(x, y)` palette coordinate of each flag pixel (in row order) as raw
+/// flag data.
+///
+/// This is the inverse of [parse_flag_records]; the coordinates are transposed into the
+/// column-ordered form used by the registry values. [RecordSeparators::Auto] is encoded with
+/// period separators.
+pub fn encode_flag_records(coordinates: &[(f64, f64)], dimensions: &FlagDimensions) -> Result<String, Error> {
+    let FlagDimensions { width, height, separators, .. } = *dimensions;
+    let precision = dimensions.precision();
+
+    let pixel_count = dimensions.pixel_count();
+    if coordinates.len() != pixel_count {
+        return Err(UnexpectedValue(format!("expected {pixel_count} flag pixels, got {}", coordinates.len())));
+    }
+
+    // Perform a matrix transposition on the pixels - as the registry values are column-ordered
+    // while bitmap images are row-ordered.
+    Ok((0..width)
+        .flat_map(|i| {
+            (0..height).map(move |j| {
+                j * width + i
+            })
+        })
+        .enumerate()
+        .map(|(i, index)| {
+            let (x, y) = coordinates[index];
+            let trailing_character = if i == pixel_count - 1 {
+                '\0'
+            } else {
+                separators.delimiter()
+            };
+
+            let record = format!("{x:.precision$}:{y:.precision$}{trailing_character}");
+            match separators.decimal_separator() {
+                '.' => record,
+                decimal_separator => record.replace('.', &decimal_separator.to_string()),
+            }
+        })
+        .collect())
+}
+
+/// Format the normalized `(x, y)` palette coordinate of each flag pixel (in row order, as returned
+/// by [parse_flag_records]) as CSV, with an `index,x,y` header row followed by one row per pixel.
+pub fn coordinates_to_csv(coordinates: &[(f64, f64)]) -> String {
+    let mut csv = String::from("index,x,y\n");
+    for (i, (x, y)) in coordinates.iter().enumerate() {
+        csv.push_str(&format!("{i},{x},{y}\n"));
+    }
+
+    csv
+}
+
+/// Parse CSV of `index,x,y` rows (as written by [coordinates_to_csv]) into the normalized
+/// `(x, y)` palette coordinate of each flag pixel, in row order.
+///
+/// The header row and blank lines are skipped. The rows may be in any order, but every pixel of
+/// the flag must have exactly one row, and every coordinate must be within `[0.0, 1.0]`.
+pub fn coordinates_from_csv(csv: &str, dimensions: &FlagDimensions) -> Result<Vec<(f64, f64)>, Error> {
+    let pixel_count = dimensions.pixel_count();
+    let mut coordinates: Vec<Option<(f64, f64)>> = vec![None; pixel_count];
+
+    for (line_index, line) in csv.lines().enumerate() {
+        let row = line_index + 1;
+        let line = line.trim();
+        if line.is_empty() || (line_index == 0 && line.starts_with("index")) {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let [index, x, y] = fields[..] else {
+            return Err(UnexpectedValue(format!("row {row} should have 3 fields (index,x,y), but has {}", fields.len())));
+        };
+
+        let index = index.parse::<usize>()
+            .map_err(|err| UnexpectedValue(format!("row {row}'s index ({index}) was not a valid index: {err}")))?;
+        if index >= pixel_count {
+            return Err(UnexpectedValue(format!("row {row}'s index ({index}) is out of range for a flag of {pixel_count} pixels")));
+        }
+
+        let parse_coordinate = |name: &str, value: &str| {
+            let coordinate = value.parse::<f64>()
+                .map_err(|err| UnexpectedValue(format!("row {row}'s {name}-coordinate ({value}) was not a valid float: {err}")))?;
+            if !(0.0..=1.0).contains(&coordinate) {
+                return Err(UnexpectedValue(format!("row {row}'s {name}-coordinate ({value}) is outside of the range [0, 1]")));
+            }
+
+            Ok(coordinate)
+        };
+        let coordinate = (parse_coordinate("x", x)?, parse_coordinate("y", y)?);
+
+        if coordinates[index].replace(coordinate).is_some() {
+            return Err(UnexpectedValue(format!("row {row} repeats index {index}")));
+        }
+    }
+
+    let missing = coordinates.iter().filter(|coordinate| coordinate.is_none()).count();
+    if missing > 0 {
+        return Err(UnexpectedValue(format!("expected a row for each of the {pixel_count} flag pixels, but {missing} are missing")));
+    }
+
+    Ok(coordinates.into_iter().flatten().collect())
+}
+
+/// The metric used to measure the difference between a flag pixel and a palette color.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorMetric {
+    /// The Euclidean distance between the colors.
+    #[default]
+    Euclidean,
+
+    /// The "redmean" weighted distance, which approximates the sensitivity of human vision.
+    Redmean,
+}
+
+impl ColorMetric {
+    /// Get the difference between the two colors with this metric.
+    pub fn difference(self, a: &Pixel24Bit, b: &Pixel24Bit) -> f64 {
+        match self {
+            ColorMetric::Euclidean => a.difference(b),
+            ColorMetric::Redmean => a.difference_redmean(b),
+        }
+    }
+}
+
+/// Find the location of the closest match (by the given metric) to the color among the distinct
+/// colors of the palette, from [Bitmap::unique_palette].
+fn closest_unique_color(unique_colors: &[(Pixel24Bit, (u32, u32))], color: &Pixel24Bit, metric: ColorMetric) -> Option<(u32, u32)> {
+    unique_colors.iter()
+        .fold((None, f64::INFINITY), |best, (palette_color, location)| {
+            let difference = metric.difference(palette_color, color);
+            if difference < best.1 { (Some(*location), difference) } else { best }
+        })
+        .0
+}
+
+/// Get a function that finds the location of the closest match in the palette for a color.
+///
+/// Without a preferred location, only the distinct colors of the palette are searched, which
+/// finds the same locations as searching every palette pixel. With one, every palette pixel is
+/// searched, so that ties between cells of the same color are broken by their distance to it.
+fn palette_matcher(palette: &Bitmap<Pixel24Bit>, metric: ColorMetric, prefer: Option<(u32, u32)>) -> impl Fn(&Pixel24Bit) -> Option<(u32, u32)> + Send + Sync {
+    let unique_colors = if prefer.is_none() { palette.unique_palette() } else { vec![] };
+
+    move |color| match prefer {
+        Some(prefer) => palette.find_pixel_by_closest_match_biased_with(color, prefer, |a, b| metric.difference(a, b)),
+        None => closest_unique_color(&unique_colors, color, metric),
+    }
+}
+
+/// Find the location of the closest match in the palette for each of the given colors, in order.
+#[cfg(not(feature = "parallel"))]
+fn match_colors(palette: &Bitmap<Pixel24Bit>, colors: &[Pixel24Bit], metric: ColorMetric, prefer: Option<(u32, u32)>) -> Vec<Option<(u32, u32)>> {
+    colors.iter().map(palette_matcher(palette, metric, prefer)).collect()
+}
+
+/// Find the location of the closest match in the palette for each of the given colors, in order.
+///
+/// The colors are matched in parallel, as each match requires a full scan of the palette.
+#[cfg(feature = "parallel")]
+fn match_colors(palette: &Bitmap<Pixel24Bit>, colors: &[Pixel24Bit], metric: ColorMetric, prefer: Option<(u32, u32)>) -> Vec<Option<(u32, u32)>> {
+    use rayon::prelude::*;
+    colors.par_iter().map(palette_matcher(palette, metric, prefer)).collect()
+}
+
+/// Pack the color of a pixel into a single value, for use as a key.
+fn pack_rgb(pixel: &Pixel24Bit) -> u32 {
+    u32::from_be_bytes([0, pixel.red, pixel.green, pixel.blue])
+}
+
+/// Find the location of the closest match in the palette for each of the given pixels, in order,
+/// using `match_colors` to match each distinct color only once.
+fn find_palette_matches_with(
+    pixels: &[Pixel24Bit],
+    match_colors: impl FnOnce(&[Pixel24Bit]) -> Vec<Option<(u32, u32)>>,
+) -> Vec<Result<(u32, u32), Error>> {
+    let mut distinct_colors: Vec<Pixel24Bit> = vec![];
+    let mut seen_colors: HashSet<u32> = HashSet::new();
+    for pixel in pixels {
+        if seen_colors.insert(pack_rgb(pixel)) {
+            distinct_colors.push(*pixel);
+        }
+    }
+
+    let cache: HashMap<u32, (u32, u32)> = distinct_colors.iter()
+        .zip(match_colors(&distinct_colors))
+        .filter_map(|(color, closest_pixel)| Some((pack_rgb(color), closest_pixel?)))
+        .collect();
+
+    debug!("Matched {} distinct colors for {} flag pixels", distinct_colors.len(), pixels.len());
+
+    pixels.iter()
+        .enumerate()
+        .map(|(i, pixel)| {
+            cache.get(&pack_rgb(pixel)).copied()
+                .ok_or_else(|| UnexpectedValue(format!("failed to find a match in the palette for pixel {i}")))
+        })
+        .collect()
+}
+
+/// Find the location of the closest match in the palette for each of the given pixels, in order.
+fn find_palette_matches(palette: &Bitmap<Pixel24Bit>, pixels: &[Pixel24Bit], metric: ColorMetric, prefer: Option<(u32, u32)>) -> Vec<Result<(u32, u32), Error>> {
+    find_palette_matches_with(pixels, |colors| match_colors(palette, colors, metric, prefer))
+}
+
+/// Map each pixel of the flag image to the normalized `(x, y)` coordinate of its closest match in
+/// the palette (by the given metric), and encode the coordinates as raw flag data.
+pub fn map_flag_to_palette(palette: &Bitmap<Pixel24Bit>, flag: &Bitmap<Pixel24Bit>, dimensions: &FlagDimensions, metric: ColorMetric) -> Result<String, Error> {
+    map_flag_to_palette_biased(palette, flag, dimensions, metric, None)
+}
+
+/// Map each pixel of the flag image to its closest match in the palette like
+/// [map_flag_to_palette], breaking ties between equally close palette cells by their distance to
+/// the `prefer` cell (if any).
+fn map_flag_to_palette_biased(palette: &Bitmap<Pixel24Bit>, flag: &Bitmap<Pixel24Bit>, dimensions: &FlagDimensions, metric: ColorMetric, prefer: Option<(u32, u32)>) -> Result<String, Error> {
+    // The pixels are transposed by their position in the flag, so an image of any other size
+    // (even with the same number of pixels) can't be mapped.
+    let (width, height) = (flag.get_width() as usize, flag.get_height() as usize);
+    if (width, height) != (dimensions.width, dimensions.height) {
+        return Err(UnexpectedValue(format!(
+            "the flag image is {width}x{height} pixels, but the flag is {}x{} pixels (resize the image to match)",
+            dimensions.width, dimensions.height
+        )));
+    }
+
+    encode_palette_matches(find_palette_matches(palette, &flag.pixels, metric, prefer), palette, dimensions)
+}
+
+/// Encode the location of the palette match of each flag pixel as raw flag data.
+///
+/// This fails if any pixel wasn't matched, rather than encoding the matched pixels alone, as the
+/// trailing null must land on the last pixel of the flag.
+fn encode_palette_matches(matches: Vec<Result<(u32, u32), Error>>, palette: &Bitmap<Pixel24Bit>, dimensions: &FlagDimensions) -> Result<String, Error> {
+    let palette_width = f64::from(palette.get_width());
+    let palette_height = f64::from(palette.get_height());
+
+    let mut bad_pixels: Vec<Error> = vec![];
+    let coordinates: Vec<(f64, f64)> = matches
+        .into_iter()
+        .filter_map(|pixel| pixel.map_err(|err| bad_pixels.push(err)).ok())
+        .map(|(x, y)| (f64::from(x) / palette_width, f64::from(y) / palette_height))
+        .collect();
+
+    info!("Mapped {} flag pixels to the palette ({} bad pixels)", coordinates.len(), bad_pixels.len());
+
+    if !bad_pixels.is_empty() {
+        return Err(UnexpectedValue(format!(
+            "error mapping pixels\n\n{}",
+            bad_pixels.iter().map(|err| err.to_string()).collect::<Vec<String>>().join("\n")
+        )));
+    }
+
+    encode_flag_records(&coordinates, dimensions)
+}
+
+/// How far a pixel may be from the background color to be trimmed as part of the border by
+/// [trim_to_flag], which allows for a little noise (e.g., from compression) in the border.
+pub const AUTO_TRIM_TOLERANCE: f64 = 16.0;
+
+/// Crop away the uniform border of the image, then scale what's left (keeping its aspect ratio)
+/// to fit the flag, centered and padded with the border color.
+///
+/// The border color is the color of the first pixel, and pixels within [AUTO_TRIM_TOLERANCE] of
+/// it count as border. The image is scaled by sampling the nearest pixel.
+pub fn trim_to_flag(image: &Bitmap<Pixel24Bit>, dimensions: &FlagDimensions) -> Result<Bitmap<Pixel24Bit>, Error> {
+    let Some(&background) = image.pixels.first() else {
+        return Err(UnexpectedValue("the flag image has no pixels to trim".to_string()));
+    };
+
+    let trimmed = image.auto_trim(background, AUTO_TRIM_TOLERANCE)
+        .map_err(|err| External(format!("failed to trim the flag image: {err}")))?;
+    let (width, height) = (trimmed.get_width() as usize, trimmed.get_height() as usize);
+    debug!("Trimmed the flag image from {}x{} to {width}x{height} pixels", image.get_width(), image.get_height());
+
+    // Scale the trimmed image to fill as much of the flag as possible, then center it.
+    let scale = (dimensions.width as f64 / width as f64).min(dimensions.height as f64 / height as f64);
+    let scaled_width = ((width as f64 * scale).round() as usize).clamp(1, dimensions.width);
+    let scaled_height = ((height as f64 * scale).round() as usize).clamp(1, dimensions.height);
+    let (left, top) = ((dimensions.width - scaled_width) / 2, (dimensions.height - scaled_height) / 2);
+
+    let pixels = (0..dimensions.height)
+        .flat_map(|y| (0..dimensions.width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            if !(left..left + scaled_width).contains(&x) || !(top..top + scaled_height).contains(&y) {
+                return background;
+            }
+
+            // Sample the center of the flag pixel.
+            let sample_x = ((x - left) as f64 + 0.5) / scaled_width as f64;
+            let sample_y = ((y - top) as f64 + 0.5) / scaled_height as f64;
+            trimmed.sample_normalized(sample_x, sample_y, EdgeMode::Clamp).copied().unwrap_or(background)
+        })
+        .collect();
+
+    let (Ok(flag_width), Ok(flag_height)) = (i32::try_from(dimensions.width), i32::try_from(dimensions.height)) else {
+        return Err(UnexpectedValue(format!("the flag ({}x{} pixels) is too large for a bitmap image", dimensions.width, dimensions.height)));
+    };
+
+    let flag_height = if image.is_top_down() { -flag_height } else { flag_height };
+    Bitmap::new_from_pixels(flag_width, flag_height, pixels)
+        .map_err(|err| External(format!("failed to create bitmap image: {err}")))
+}
+
+/// Options that control how [encode_flag] maps the flag image to the palette.
+#[derive(Debug, Default, Clone)]
+pub struct EncodeOptions {
+    /// The preprocessing steps to apply to the flag image, in order, before it is matched to the
+    /// palette.
+    pub preprocess: Vec<PreprocessStep>,
+
+    /// The metric used to find the closest palette color to each pixel.
+    pub metric: ColorMetric,
+
+    /// The normalized palette coordinate to prefer when several palette cells match a pixel
+    /// equally well (choosing the nearest cell to it), or [None] to choose the first cell.
+    pub tie_break: Option<(f64, f64)>,
+
+    /// Crop away the border of the flag image, and fit what's left to the flag (see
+    /// [trim_to_flag]), before it is preprocessed.
+    pub auto_trim: bool,
+}
+
+/// Encode the flag image as raw flag data, by applying the preprocessing steps and then mapping
+/// each pixel to its closest match in the palette.
+///
+/// This is the transformation performed by `write`, without any IO, so it can be reused wherever
+/// the registry isn't available (e.g., in a browser).
+pub fn encode_flag(mut image: Bitmap<Pixel24Bit>, palette: &Bitmap<Pixel24Bit>, dimensions: &FlagDimensions, options: &EncodeOptions) -> Result<String, Error> {
+    if options.auto_trim {
+        image = trim_to_flag(&image, dimensions)?;
+    }
+
+    apply_preprocess_steps(&options.preprocess, &mut image, palette)?;
+
+    let prefer = options.tie_break
+        .map(|(x, y)| palette.normalized_to_coords(x, y, EdgeMode::Clamp)
+            .ok_or_else(|| UnexpectedValue(format!("the tie-break coordinate ({x}, {y}) is not a valid palette coordinate"))))
+        .transpose()?;
+    map_flag_to_palette_biased(palette, &image, dimensions, options.metric, prefer)
+}
+
+/// How closely a flag image survives being encoded against a palette and decoded again.
+#[derive(Debug, Clone, Copy)]
+pub struct FlagQuality {
+    /// The number of pixels in the flag.
+    pub pixel_count: usize,
+
+    /// The number of pixels whose decoded color is within the threshold of the original color.
+    pub within_threshold: usize,
+
+    /// The mean difference between the original and decoded colors.
+    pub mean_difference: f64,
+
+    /// The largest difference between the original and decoded colors, and the `(x, y)`
+    /// coordinates of the first pixel with it.
+    pub worst_difference: (f64, (u32, u32)),
+
+    /// The peak signal-to-noise ratio of the decoded flag against the original, in decibels (see
+    /// [Bitmap::psnr]), which is infinite if every pixel round-trips exactly.
+    pub psnr: f64,
+
+    /// The structural similarity of the decoded flag to the original (see [Bitmap::ssim]), from
+    /// -1.0 to 1.0 for identical images, which reflects how close it looks even where dithering
+    /// makes individual pixels differ.
+    pub ssim: f64,
+}
+
+/// Measure how closely the flag image round-trips through [encode_flag] and [decode_flag] with
+/// the palette, by comparing each decoded pixel to the original with [Pixel::difference], and
+/// counting the pixels that differ by no more than `threshold`.
+pub fn flag_quality(image: Bitmap<Pixel24Bit>, palette: &Bitmap<Pixel24Bit>, dimensions: &FlagDimensions, options: &EncodeOptions, threshold: f64) -> Result<FlagQuality, Error> {
+    let original_pixels = image.pixels.clone();
+    let raw_data = encode_flag(image, palette, dimensions, options)?;
+    let decoded = decode_flag(raw_data.as_bytes(), palette, dimensions)?;
+
+    // The flag is encoded from the pixels in the order they're stored, and decoded bottom-up, so
+    // compare them in that order, rather than flipping the rows of a top-down image to match.
+    let original = Bitmap::new_from_pixels(decoded.get_width() as i32, decoded.get_height() as i32, original_pixels)
+        .map_err(|err| External(format!("failed to compare the decoded flag to the original: {err}")))?;
+
+    let (psnr, ssim) = decoded.psnr(&original)
+        .and_then(|psnr| Ok((psnr, decoded.ssim(&original)?)))
+        .map_err(|err| External(format!("failed to compare the decoded flag to the original: {err}")))?;
+
+    let differences: Vec<f64> = original.pixels.iter()
+        .zip(decoded.pixels.iter())
+        .map(|(original, decoded)| original.difference(decoded))
+        .collect();
+
+    let (worst_index, worst_difference) = differences.iter()
+        .enumerate()
+        .fold((0, 0.0), |worst, (i, &difference)| if difference > worst.1 { (i, difference) } else { worst });
+
+    Ok(FlagQuality {
+        pixel_count: differences.len(),
+        within_threshold: differences.iter().filter(|&&difference| difference <= threshold).count(),
+        mean_difference: differences.iter().sum::<f64>() / differences.len() as f64,
+        worst_difference: (worst_difference, decoded.coords_of(worst_index)),
+        psnr,
+        ssim,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    /// Get the channels of each pixel, for comparing pixels.
+    fn channels(pixels: &[Pixel24Bit]) -> Vec<(u8, u8, u8)> {
+        pixels.iter().map(|pixel| (pixel.red, pixel.green, pixel.blue)).collect()
+    }
+
+    /// A 2x2 palette of red, green (top row), blue and white (bottom row).
+    fn synthetic_palette() -> Bitmap<Pixel24Bit> {
+        Bitmap::new_from_pixels(2, 2, vec![
+            Pixel24Bit { red: 255, green: 0, blue: 0 },
+            Pixel24Bit { red: 0, green: 255, blue: 0 },
+            Pixel24Bit { red: 0, green: 0, blue: 255 },
+            Pixel24Bit { red: 255, green: 255, blue: 255 },
+        ]).unwrap()
+    }
+
+    /// The dimensions of a 2x2 flag.
+    fn synthetic_dimensions() -> FlagDimensions {
+        FlagDimensions::new(2, 2, MAGE_ARENA_FLAG_PIXEL_SIZE).unwrap()
+    }
+
+    /// The raw flag data (in column order) of a 2x2 flag of red, green (top row), white and blue
+    /// (bottom row) on the [synthetic_palette].
+    const SYNTHETIC_FLAG_DATA: &str = "0.00:0.00,0.50:0.50,0.50:0.00,0.00:0.50\0";
+
+    #[test]
+    fn flag_stats_reports_palette_usage() {
+        // Red, red, green and red, on the top row of the palette.
+        let coordinates = [(0.0, 0.0), (0.0, 0.0), (0.5, 0.0), (0.25, 0.0)];
+        let stats = flag_stats(&synthetic_palette(), &coordinates).unwrap().unwrap();
+
+        assert_eq!(stats.distinct_colors, 2);
+        assert_eq!(channels(&[stats.most_common_color.0]), vec![(255, 0, 0)]);
+        assert_eq!(stats.most_common_color.1, 3);
+        assert_eq!(stats.sampled_bounds, ((0, 0), (1, 0)));
+
+        assert!(flag_stats(&synthetic_palette(), &[]).unwrap().is_none());
+    }
+
+    #[test]
+    fn palette_usage_map_marks_sampled_cells() {
+        let coordinates = [(0.0, 0.0), (0.25, 0.5), (0.25, 0.5), (1.0, 0.99)];
+        let map = palette_usage_map(&coordinates, &FlagDimensions::default()).unwrap();
+
+        assert_eq!((map.get_width(), map.get_height()), (100, 100));
+
+        let used = Pixel24Bit { red: 255, green: 255, blue: 255 };
+        assert_eq!(map.positions_of(&used), vec![(0, 0), (25, 50), (99, 99)]);
+    }
+
+    #[test]
+    fn decode_flag_resolves_pixels_against_palette() {
+        let flag = decode_flag(SYNTHETIC_FLAG_DATA.as_bytes(), &synthetic_palette(), &synthetic_dimensions()).unwrap();
+
+        assert_eq!((flag.get_width(), flag.get_height()), (2, 2));
+        assert_eq!(channels(&flag.pixels), vec![(255, 0, 0), (0, 255, 0), (255, 255, 255), (0, 0, 255)]);
+    }
+
+    #[test]
+    fn decode_flag_resolves_coordinate_of_one_to_last_column() {
+        let raw_data = "1.00:0.00,0.00:0.00,1.00:1.00,0.00:1.00\0";
+        let flag = decode_flag(raw_data.as_bytes(), &synthetic_palette(), &synthetic_dimensions()).unwrap();
+
+        assert_eq!(channels(&flag.pixels), vec![(0, 255, 0), (255, 255, 255), (255, 0, 0), (0, 0, 255)]);
+    }
+
+    #[test]
+    fn resolve_flag_image_clamps_coordinates_to_palette_edge() {
+        let coordinates = [(1.0, 1.0), (0.0, 1.0), (1.0, 0.0), (0.5, 0.0)];
+        let flag = resolve_flag_image(&synthetic_palette(), &coordinates, &synthetic_dimensions(), PaletteSampling::Nearest).unwrap();
+        assert_eq!(channels(&flag.pixels), vec![(255, 255, 255), (0, 0, 255), (0, 255, 0), (0, 255, 0)]);
+
+        let coordinates = [(f64::NAN, 0.0), (0.0, 0.0), (0.0, 0.0), (0.0, 0.0)];
+        assert!(resolve_flag_image(&synthetic_palette(), &coordinates, &synthetic_dimensions(), PaletteSampling::Nearest).is_err());
+    }
+
+    #[test]
+    fn resolve_flag_image_interpolates_bilinear_samples() {
+        let palette = Bitmap::new_from_pixels(2, 1, vec![
+            Pixel24Bit { red: 0, green: 0, blue: 0 },
+            Pixel24Bit { red: 200, green: 100, blue: 50 },
+        ]).unwrap();
+
+        let coordinates = [(0.25, 0.5), (0.5, 0.5), (0.625, 0.5), (0.75, 0.5)];
+        let flag = resolve_flag_image(&palette, &coordinates, &synthetic_dimensions(), PaletteSampling::Bilinear).unwrap();
+        assert_eq!(channels(&flag.pixels), vec![(0, 0, 0), (100, 50, 25), (150, 75, 38), (200, 100, 50)]);
+
+        let flag = resolve_flag_image(&palette, &coordinates, &synthetic_dimensions(), PaletteSampling::Nearest).unwrap();
+        assert_eq!(channels(&flag.pixels), vec![(0, 0, 0), (200, 100, 50), (200, 100, 50), (200, 100, 50)]);
+    }
+
+    #[test]
+    fn concat_palettes_places_palettes_side_by_side() {
+        let solid = |red: u8, width: i32, height: i32| {
+            Bitmap::new_from_pixels(width, height, vec![Pixel24Bit { red, green: 0, blue: 0 }; (width * height.abs()) as usize]).unwrap()
+        };
+
+        let combined = concat_palettes(&[solid(1, 50, 66), solid(2, 50, 66)], ConcatLayout::Horizontal).unwrap();
+        assert_eq!((combined.get_width(), combined.get_height()), (100, 66));
+        for y in [0, 65] {
+            assert_eq!(combined.get_pixel_at(49, y).unwrap().red, 1);
+            assert_eq!(combined.get_pixel_at(50, y).unwrap().red, 2);
+        }
+
+        let combined = concat_palettes(&[solid(1, 50, 66), solid(2, 50, 10)], ConcatLayout::Vertical).unwrap();
+        assert_eq!((combined.get_width(), combined.get_height()), (50, 76));
+        assert_eq!(combined.get_pixel_at(0, 65).unwrap().red, 1);
+        assert_eq!(combined.get_pixel_at(0, 66).unwrap().red, 2);
+
+        let err = concat_palettes(&[solid(1, 50, 66), solid(2, 50, 10)], ConcatLayout::Horizontal).unwrap_err();
+        assert!(err.to_string().contains("palette 1 is 50x10 pixels"), "{err}");
+        assert!(concat_palettes(&[], ConcatLayout::Vertical).is_err());
+    }
+
+    #[test]
+    fn concat_palettes_matches_orientation_of_first_palette() {
+        // A bottom-up palette with a red first (bottom) row, and the same palette stored top-down.
+        let bottom_up = synthetic_palette();
+        let mut top_down = synthetic_palette();
+        top_down.set_top_down(true);
+
+        let combined = concat_palettes(&[bottom_up, top_down], ConcatLayout::Horizontal).unwrap();
+        assert!(!combined.is_top_down());
+        assert_eq!(channels(&combined.pixels[..4]), vec![(255, 0, 0), (0, 255, 0), (255, 0, 0), (0, 255, 0)]);
+    }
+
+    #[test]
+    fn flip_palette_rows_mirrors_sampled_row() {
+        let palette = flip_palette_rows(&synthetic_palette());
+
+        // Each coordinate samples the other row of the palette.
+        let coordinates = [(0.0, 0.0), (0.5, 0.0), (0.0, 0.5), (0.5, 0.5)];
+        let flag = resolve_flag_image(&palette, &coordinates, &synthetic_dimensions(), PaletteSampling::Nearest).unwrap();
+        assert_eq!(channels(&flag.pixels), vec![(0, 0, 255), (255, 255, 255), (255, 0, 0), (0, 255, 0)]);
+
+        // Encoding the palette colors against the mirrored palette gives the mirrored coordinates.
+        let raw_data = encode_flag(synthetic_palette(), &palette, &synthetic_dimensions(), &EncodeOptions::default()).unwrap();
+        assert_eq!(parse_flag_records(raw_data.as_bytes(), &synthetic_dimensions()).unwrap(), vec![(0.0, 0.5), (0.5, 0.5), (0.0, 0.0), (0.5, 0.0)]);
+    }
+
+    #[test]
+    fn encode_flag_records_transposes_to_column_order() {
+        let dimensions = FlagDimensions::new(3, 2, MAGE_ARENA_FLAG_PIXEL_SIZE).unwrap();
+
+        // The coordinate of each pixel (in row order) is its own (x / 10, y / 10).
+        let coordinates: Vec<(f64, f64)> = (0..6).map(|i| ((i % 3) as f64 / 10.0, (i / 3) as f64 / 10.0)).collect();
+
+        let raw_data = encode_flag_records(&coordinates, &dimensions).unwrap();
+        assert_eq!(raw_data, "0.00:0.00,0.00:0.10,0.10:0.00,0.10:0.10,0.20:0.00,0.20:0.10\0");
+        assert_eq!(parse_flag_records(raw_data.as_bytes(), &dimensions).unwrap(), coordinates);
+    }
+
+    #[test]
+    fn parse_flag_records_requires_null_after_last_pixel() {
+        let raw_data = SYNTHETIC_FLAG_DATA.replace('\0', ",");
+
+        let err = parse_flag_records(raw_data.as_bytes(), &synthetic_dimensions()).unwrap_err();
+        assert!(err.to_string().contains("pixel 3 contains an invalid last character (expected: 0, got: 44)"), "{err}");
+    }
+
+    #[test]
+    fn parse_flag_records_accepts_delimiter_after_last_pixel_unless_strict() {
+        let expected = vec![(0.0, 0.0), (0.5, 0.0), (0.5, 0.5), (0.0, 0.5)];
+        let lenient = FlagDimensions { strict_trailing: false, ..synthetic_dimensions() };
+
+        let raw_data = SYNTHETIC_FLAG_DATA.replace('\0', ",");
+        assert_eq!(parse_flag_records(raw_data.as_bytes(), &lenient).unwrap(), expected);
+        assert_eq!(parse_flag_records(SYNTHETIC_FLAG_DATA.as_bytes(), &lenient).unwrap(), expected);
+
+        // Other characters are still rejected, as is a null before the last pixel.
+        let raw_data = SYNTHETIC_FLAG_DATA.replace('\0', ";");
+        assert!(parse_flag_records(raw_data.as_bytes(), &lenient).is_err());
+        let raw_data = SYNTHETIC_FLAG_DATA.replacen(',', "\0", 1);
+        assert!(parse_flag_records(raw_data.as_bytes(), &lenient).is_err());
+    }
+
+    #[test]
+    fn parse_flag_records_rejects_null_before_last_pixel() {
+        let raw_data = SYNTHETIC_FLAG_DATA.replacen(',', "\0", 1);
+
+        let err = parse_flag_records(raw_data.as_bytes(), &synthetic_dimensions()).unwrap_err();
+        assert!(err.to_string().contains("pixel 0 contains an invalid last character (expected: 44, got: 0)"), "{err}");
+    }
+
+    #[test]
+    fn parse_flag_records_rejects_missing_divider() {
+        // The third record (in column order) is the second pixel (in row order).
+        let raw_data = SYNTHETIC_FLAG_DATA.replace("0.50:0.00", "0.50;0.00");
+
+        let err = parse_flag_records(raw_data.as_bytes(), &synthetic_dimensions()).unwrap_err();
+        assert!(err.to_string().contains("pixel 1 is missing the expected divider character (:)"), "{err}");
+    }
+
+    #[test]
+    fn encode_palette_matches_rejects_unmatched_pixels() {
+        let palette = synthetic_palette();
+        let pixels = vec![
+            Pixel24Bit { red: 255, green: 0, blue: 0 },
+            Pixel24Bit { red: 0, green: 255, blue: 0 },
+            Pixel24Bit { red: 255, green: 255, blue: 255 },
+            Pixel24Bit { red: 0, green: 0, blue: 255 },
+        ];
+
+        // Every pixel matches, so the trailing null is on the last pixel.
+        let matches = find_palette_matches_with(&pixels, |colors| match_colors(&palette, colors, ColorMetric::Euclidean, None));
+        let raw_data = encode_palette_matches(matches, &palette, &synthetic_dimensions()).unwrap();
+        assert_eq!(raw_data, SYNTHETIC_FLAG_DATA);
+
+        // The green pixel can't be matched, so no flag data is produced at all (rather than the
+        // other three pixels, with the trailing null on the wrong pixel).
+        let matches = find_palette_matches_with(&pixels, |colors| {
+            colors.iter().map(|color| if color.green == 255 && color.red == 0 { None } else { palette.find_pixel_by_closest_match(color) }).collect()
+        });
+        let err = encode_palette_matches(matches, &palette, &synthetic_dimensions()).unwrap_err();
+        assert!(err.to_string().contains("failed to find a match in the palette for pixel 1"), "{err}");
+    }
+
+    #[test]
+    fn encode_flag_rejects_wrong_sized_image() {
+        let image = Bitmap::new_from_pixels(50, 50, vec![Pixel24Bit { red: 255, green: 0, blue: 0 }; 2500]).unwrap();
+
+        let err = encode_flag(image, &synthetic_palette(), &FlagDimensions::default(), &EncodeOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("the flag image is 50x50 pixels, but the flag is 100x66 pixels"), "{err}");
+    }
+
+    #[test]
+    fn encode_flag_maps_pixels_to_palette() {
+        // The colors of the synthetic flag, with a little noise.
+        let image = Bitmap::new_from_pixels(2, 2, vec![
+            Pixel24Bit { red: 250, green: 10, blue: 0 },
+            Pixel24Bit { red: 0, green: 240, blue: 20 },
+            Pixel24Bit { red: 230, green: 250, blue: 255 },
+            Pixel24Bit { red: 5, green: 5, blue: 200 },
+        ]).unwrap();
+
+        let raw_data = encode_flag(image, &synthetic_palette(), &synthetic_dimensions(), &EncodeOptions::default()).unwrap();
+        assert_eq!(raw_data, SYNTHETIC_FLAG_DATA);
+    }
+
+    #[test]
+    fn flag_quality_measures_round_trip_differences() {
+        let image = Bitmap::new_from_pixels(2, 2, vec![
+            Pixel24Bit { red: 250, green: 10, blue: 0 },
+            Pixel24Bit { red: 0, green: 240, blue: 20 },
+            Pixel24Bit { red: 230, green: 250, blue: 255 },
+            Pixel24Bit { red: 5, green: 5, blue: 200 },
+        ]).unwrap();
+
+        let quality = flag_quality(image, &synthetic_palette(), &synthetic_dimensions(), &EncodeOptions::default(), 25.0).unwrap();
+        assert_eq!((quality.pixel_count, quality.within_threshold), (4, 2));
+        assert!((quality.mean_difference - 29.28).abs() < 0.01, "{}", quality.mean_difference);
+
+        let (worst_difference, worst_pixel) = quality.worst_difference;
+        assert!((worst_difference - 55.45).abs() < 0.01, "{worst_difference}");
+        assert_eq!(worst_pixel, (1, 1));
+        assert!((quality.psnr - 22.41).abs() < 0.01, "{}", quality.psnr);
+        assert!(quality.ssim > 0.0 && quality.ssim < 1.0, "{}", quality.ssim);
+
+        // The palette colors themselves round-trip losslessly.
+        let quality = flag_quality(synthetic_palette(), &synthetic_palette(), &synthetic_dimensions(), &EncodeOptions::default(), 0.0).unwrap();
+        assert_eq!(quality.within_threshold, 4);
+        assert_eq!(quality.worst_difference.0, 0.0);
+        assert_eq!(quality.psnr, f64::INFINITY);
+        assert!((quality.ssim - 1.0).abs() < 1e-9, "{}", quality.ssim);
+    }
+
+    #[test]
+    fn flag_quality_pairs_pixels_of_a_top_down_image_in_stored_order() {
+        let image = Bitmap::new_from_pixels(2, -2, synthetic_palette().pixels).unwrap();
+        assert!(image.is_top_down());
+
+        let quality = flag_quality(image, &synthetic_palette(), &synthetic_dimensions(), &EncodeOptions::default(), 0.0).unwrap();
+        assert_eq!(quality.within_threshold, 4);
+        assert_eq!(quality.psnr, f64::INFINITY);
+        assert!((quality.ssim - 1.0).abs() < 1e-9, "{}", quality.ssim);
+    }
+
+    /// Build raw flag data (in the registry's column order), formatting the record for the pixel
+    /// at (x, y) with the given function.
+    fn flag_data(format_record: impl Fn(usize, usize) -> String) -> Vec<u8> {
+        let FlagDimensions { width, height, .. } = FlagDimensions::default();
+
+        (0..width)
+            .flat_map(|x| (0..height).map(move |y| (x, y)))
+            .enumerate()
+            .map(|(i, (x, y))| {
+                let trailing_character = if i == width * height - 1 { '\0' } else { ',' };
+                format!("{}{}", format_record(x, y), trailing_character)
+            })
+            .collect::<String>()
+            .into_bytes()
+    }
+
+    /// Build raw flag data where the pixel at (x, y) refers to the palette coordinate
+    /// (x / 100, y / 100).
+    fn seeded_flag_data() -> Vec<u8> {
+        flag_data(|x, y| format!("{:.2}:{:.2}", x as f64 / 100.0, y as f64 / 100.0))
+    }
+
+    /// Assert that the coordinates match those of [seeded_flag_data].
+    fn assert_seeded_coordinates(coordinates: &[(f64, f64)]) {
+        assert_eq!(coordinates.len(), (MAGE_ARENA_FLAG_WIDTH * MAGE_ARENA_FLAG_HEIGHT) as usize);
+        for (i, &(x, y)) in coordinates.iter().enumerate() {
+            let expected_x = (i % MAGE_ARENA_FLAG_WIDTH as usize) as f64 / 100.0;
+            let expected_y = (i / MAGE_ARENA_FLAG_WIDTH as usize) as f64 / 100.0;
+            assert!((x - expected_x).abs() < 1e-9, "pixel {i}: expected x {expected_x}, got {x}");
+            assert!((y - expected_y).abs() < 1e-9, "pixel {i}: expected y {expected_y}, got {y}");
+        }
+    }
+
+    #[test]
+    fn parse_flag_records_reads_all_normalized_flag() {
+        let coordinates = parse_flag_records(&seeded_flag_data(), &FlagDimensions::default()).unwrap();
+        assert_seeded_coordinates(&coordinates);
+    }
+
+    #[test]
+    fn parse_flag_records_reads_all_percentage_flag() {
+        // The same flag as the seeded flag, but with the coordinates as percentages.
+        let raw_data = flag_data(|x, y| format!("{:04.1}:{:04.1}", x as f64, y as f64));
+
+        let coordinates = parse_flag_records(&raw_data, &FlagDimensions::default()).unwrap();
+        assert_seeded_coordinates(&coordinates);
+    }
+
+    #[test]
+    fn parse_flag_records_rejects_mixed_formats() {
+        // Write the first column as normalized coordinates, and the rest as percentages.
+        let raw_data = flag_data(|x, y| if x == 0 {
+            format!("{:.2}:{:.2}", 0.5, 0.5)
+        } else {
+            format!("{:04.1}:{:04.1}", x as f64, y as f64)
+        });
+
+        let err = parse_flag_records(&raw_data, &FlagDimensions::default()).unwrap_err();
+        assert!(err.to_string().contains("mix of percentage and normalized coordinates"), "{err}");
+    }
+
+    /// Generate `count` pseudo-random coordinates in the range [0.0, 1.0) from the given seed.
+    fn random_coordinates(seed: u64, count: usize) -> Vec<(f64, f64)> {
+        let mut state = seed;
+        let mut next = move || {
+            // A linear congruential generator is plenty for generating test data.
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 33) as f64 / (1u64 << 31) as f64
+        };
+
+        (0..count).map(|_| (next(), next())).collect()
+    }
+
+    /// Encode the coordinates as `write` would, then decode them as `read` would.
+    fn round_trip(coordinates: &[(f64, f64)]) -> Vec<(f64, f64)> {
+        let raw_data = encode_flag_records(coordinates, &FlagDimensions::default()).unwrap();
+        parse_flag_records(raw_data.as_bytes(), &FlagDimensions::default()).unwrap()
+    }
+
+    #[test]
+    fn parse_flag_records_detects_separators() {
+        let expected = vec![(0.0, 0.0), (0.5, 0.0), (0.5, 0.5), (0.0, 0.5)];
+        let comma_data = "0,00:0,00;0,50:0,50;0,50:0,00;0,00:0,50\0";
+
+        assert_eq!(parse_flag_records(SYNTHETIC_FLAG_DATA.as_bytes(), &synthetic_dimensions()).unwrap(), expected);
+        assert_eq!(parse_flag_records(comma_data.as_bytes(), &synthetic_dimensions()).unwrap(), expected);
+
+        // Explicit separators must match the data.
+        let dimensions = |separators| FlagDimensions { separators, ..synthetic_dimensions() };
+        assert_eq!(parse_flag_records(comma_data.as_bytes(), &dimensions(RecordSeparators::Comma)).unwrap(), expected);
+        assert!(parse_flag_records(comma_data.as_bytes(), &dimensions(RecordSeparators::Period)).is_err());
+        assert!(parse_flag_records(SYNTHETIC_FLAG_DATA.as_bytes(), &dimensions(RecordSeparators::Comma)).is_err());
+    }
+
+    #[test]
+    fn encode_flag_records_uses_separators() {
+        let coordinates = [(0.0, 0.0), (0.5, 0.0), (0.5, 0.5), (0.0, 0.5)];
+        let dimensions = |separators| FlagDimensions { separators, ..synthetic_dimensions() };
+
+        assert_eq!(encode_flag_records(&coordinates, &dimensions(RecordSeparators::Auto)).unwrap(), SYNTHETIC_FLAG_DATA);
+        assert_eq!(encode_flag_records(&coordinates, &dimensions(RecordSeparators::Period)).unwrap(), SYNTHETIC_FLAG_DATA);
+        assert_eq!(encode_flag_records(&coordinates, &dimensions(RecordSeparators::Comma)).unwrap(), "0,00:0,00;0,50:0,50;0,50:0,00;0,00:0,50\0");
+    }
+
+    #[test]
+    fn coordinates_to_csv_lists_pixels_in_row_order() {
+        let coordinates = parse_flag_records(SYNTHETIC_FLAG_DATA.as_bytes(), &synthetic_dimensions()).unwrap();
+        assert_eq!(coordinates_to_csv(&coordinates), "index,x,y\n0,0,0\n1,0.5,0\n2,0.5,0.5\n3,0,0.5\n");
+    }
+
+    #[test]
+    fn coordinates_from_csv_reads_rows_in_any_order() {
+        let expected = parse_flag_records(SYNTHETIC_FLAG_DATA.as_bytes(), &synthetic_dimensions()).unwrap();
+        assert_eq!(coordinates_from_csv(&coordinates_to_csv(&expected), &synthetic_dimensions()).unwrap(), expected);
+
+        let csv = "3,0,0.5\n1, 0.5, 0\n\n0,0,0\n2,0.5,0.5\n";
+        assert_eq!(coordinates_from_csv(csv, &synthetic_dimensions()).unwrap(), expected);
+        assert_eq!(encode_flag_records(&expected, &synthetic_dimensions()).unwrap(), SYNTHETIC_FLAG_DATA);
+    }
+
+    #[test]
+    fn coordinates_from_csv_rejects_invalid_rows() {
+        let error = |csv: &str| coordinates_from_csv(csv, &synthetic_dimensions()).unwrap_err().to_string();
+
+        assert!(error("index,x,y\n0,0,0\n1,0,1.5\n2,0,0\n3,0,0\n").contains("row 3's y-coordinate (1.5) is outside of the range [0, 1]"));
+        assert!(error("0,0,0\n1,0,0\n1,0,0\n3,0,0\n").contains("row 3 repeats index 1"));
+        assert!(error("0,0,0\n1,0,0\n2,0,0\n").contains("but 1 are missing"));
+        assert!(error("0,0,0\n4,0,0\n").contains("row 2's index (4) is out of range"));
+        assert!(error("0,0\n").contains("row 1 should have 3 fields"));
+    }
+
+    #[test]
+    fn flag_records_round_trip() {
+        let pixel_count = (MAGE_ARENA_FLAG_WIDTH * MAGE_ARENA_FLAG_HEIGHT) as usize;
+
+        for seed in 0..16 {
+            let coordinates = random_coordinates(seed, pixel_count);
+            let decoded = round_trip(&coordinates);
+
+            assert_eq!(decoded.len(), coordinates.len());
+            for (i, (expected, actual)) in coordinates.iter().zip(decoded.iter()).enumerate() {
+                // The coordinates are encoded with two decimal places.
+                assert!((expected.0 - actual.0).abs() <= 0.005 + 1e-9, "seed {seed}, pixel {i}: {expected:?} != {actual:?}");
+                assert!((expected.1 - actual.1).abs() <= 0.005 + 1e-9, "seed {seed}, pixel {i}: {expected:?} != {actual:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn find_palette_matches_scans_each_distinct_color_once() {
+        let palette = Bitmap::new_from_pixels(4, 1, vec![
+            Pixel24Bit { red: 0, green: 0, blue: 0 },
+            Pixel24Bit { red: 255, green: 0, blue: 0 },
+            Pixel24Bit { red: 0, green: 255, blue: 0 },
+            Pixel24Bit { red: 0, green: 0, blue: 255 },
+        ]).unwrap();
+
+        // An image of large flat regions, with only three distinct colors.
+        let pixels: Vec<Pixel24Bit> = (0..1000)
+            .map(|i| Pixel24Bit { red: [250, 10, 5][i % 3], green: [5, 240, 0][i % 3], blue: [0, 20, 200][i % 3] })
+            .collect();
+
+        let scan_count = Cell::new(0);
+        let scan = |pixel: &Pixel24Bit| {
+            scan_count.set(scan_count.get() + 1);
+            palette.find_pixel_by_closest_match(pixel)
+        };
+
+        let uncached: Vec<Option<(u32, u32)>> = pixels.iter().map(scan).collect();
+        assert_eq!(scan_count.replace(0), pixels.len());
+
+        let cached: Vec<Option<(u32, u32)>> = find_palette_matches_with(&pixels, |colors| colors.iter().map(scan).collect())
+            .into_iter()
+            .map(Result::ok)
+            .collect();
+        assert_eq!(scan_count.get(), 3);
+
+        assert_eq!(cached, uncached);
+    }
+
+    /// Generate a bitmap of pseudo-random pixels from the given seed.
+    fn random_bitmap(seed: u64, width: i32, height: i32) -> Bitmap<Pixel24Bit> {
+        let pixels = random_coordinates(seed, (width * height) as usize)
+            .into_iter()
+            .map(|(a, b)| Pixel24Bit { red: (a * 256.0) as u8, green: (b * 256.0) as u8, blue: ((a + b) * 128.0) as u8 })
+            .collect();
+
+        Bitmap::new_from_pixels(width, height, pixels).unwrap()
+    }
+
+    #[test]
+    fn encode_flag_breaks_ties_toward_preferred_coordinate() {
+        // A palette with the same red at its first and last cells.
+        let red = Pixel24Bit { red: 255, green: 0, blue: 0 };
+        let black = Pixel24Bit { red: 0, green: 0, blue: 0 };
+        let palette = Bitmap::new_from_pixels(4, 4, (0..16).map(|i| if i == 0 || i == 15 { red } else { black }).collect()).unwrap();
+        let image = || Bitmap::new_from_pixels(2, 2, vec![red; 4]).unwrap();
+
+        let raw_data = encode_flag(image(), &palette, &synthetic_dimensions(), &EncodeOptions::default()).unwrap();
+        assert_eq!(raw_data, "0.00:0.00,0.00:0.00,0.00:0.00,0.00:0.00\0");
+
+        let options = EncodeOptions { tie_break: Some((1.0, 1.0)), ..EncodeOptions::default() };
+        let raw_data = encode_flag(image(), &palette, &synthetic_dimensions(), &options).unwrap();
+        assert_eq!(raw_data, "0.75:0.75,0.75:0.75,0.75:0.75,0.75:0.75\0");
+    }
+
+    #[test]
+    fn encode_flag_auto_trims_padded_image() {
+        // The synthetic flag, in the middle of a black border.
+        let logo = [(255, 0, 0), (0, 255, 0), (255, 255, 255), (0, 0, 255)];
+        let pixels = (0..36)
+            .map(|i| {
+                let (x, y) = (i % 6, i / 6);
+                let (red, green, blue) = if (2..4).contains(&x) && (3..5).contains(&y) { logo[(y - 3) * 2 + x - 2] } else { (0, 0, 0) };
+                Pixel24Bit { red, green, blue }
+            })
+            .collect::<Vec<Pixel24Bit>>();
+        let image = || Bitmap::new_from_pixels(6, 6, pixels.clone()).unwrap();
+
+        let options = EncodeOptions { auto_trim: true, ..EncodeOptions::default() };
+        assert_eq!(encode_flag(image(), &synthetic_palette(), &synthetic_dimensions(), &options).unwrap(), SYNTHETIC_FLAG_DATA);
+        assert!(encode_flag(image(), &synthetic_palette(), &synthetic_dimensions(), &EncodeOptions::default()).is_err());
+
+        // A wider flag is filled from the middle, and padded with the border color.
+        let dimensions = FlagDimensions::new(4, 2, MAGE_ARENA_FLAG_PIXEL_SIZE).unwrap();
+        let flag = trim_to_flag(&image(), &dimensions).unwrap();
+        assert_eq!(
+            channels(&flag.pixels),
+            vec![(0, 0, 0), (255, 0, 0), (0, 255, 0), (0, 0, 0), (0, 0, 0), (255, 255, 255), (0, 0, 255), (0, 0, 0)]
+        );
+    }
+
+    #[test]
+    fn palette_swatches_lists_first_cell_of_each_color_by_luminance() {
+        let palette = Bitmap::new_from_pixels(2, 2, vec![
+            Pixel24Bit { red: 255, green: 255, blue: 255 },
+            Pixel24Bit { red: 0, green: 0, blue: 0 },
+            Pixel24Bit { red: 255, green: 0, blue: 0 },
+            Pixel24Bit { red: 0, green: 0, blue: 0 },
+        ]).unwrap();
+
+        let swatches = palette_swatches(&palette);
+        let colors: Vec<Pixel24Bit> = swatches.iter().map(|(color, _)| *color).collect();
+        let coordinates: Vec<(f64, f64)> = swatches.iter().map(|(_, coordinate)| *coordinate).collect();
+
+        assert_eq!(channels(&colors), vec![(0, 0, 0), (255, 0, 0), (255, 255, 255)]);
+        assert_eq!(coordinates, vec![(0.5, 0.0), (0.0, 0.5), (0.0, 0.0)]);
+    }
+
+    #[test]
+    fn palette_swatches_sorts_and_prints_colors_from_the_file_as_rgb() {
+        // A 2x1 palette whose row is stored as blue, green, red: a red pixel, then a blue pixel.
+        let mut bytes = Bitmap::new_from_pixels(2, 1, vec![Pixel24Bit { red: 0, green: 0, blue: 0 }; 2]).unwrap().to_bytes();
+        bytes[54..60].copy_from_slice(&[0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00]);
+        let palette = Bitmap::<Pixel24Bit>::new_from_bytes(bytes).unwrap();
+
+        // Blue is darker than red, so it's listed first.
+        let swatches: Vec<String> = palette_swatches(&palette).iter()
+            .map(|(color, coordinate)| format!("{color} {coordinate:?}"))
+            .collect();
+        assert_eq!(swatches, vec!["#0000FF (0.5, 0.0)", "#FF0000 (0.0, 0.0)"]);
+    }
+
+    #[test]
+    fn supersample_palette_averages_clamped_neighborhoods() {
+        let palette = Bitmap::new_from_pixels(3, 1, vec![
+            Pixel24Bit { red: 0, green: 0, blue: 0 },
+            Pixel24Bit { red: 90, green: 30, blue: 60 },
+            Pixel24Bit { red: 210, green: 90, blue: 0 },
+        ]).unwrap();
+        let coordinates = [(0.0, 0.0), (0.4, 0.0), (0.7, 0.0), (1.0, 0.0)];
+        let dimensions = FlagDimensions::new(2, 2, MAGE_ARENA_FLAG_PIXEL_SIZE).unwrap();
+
+        // A single cell is the same as sampling the palette directly.
+        let single = resolve_flag_image(&palette, &coordinates, &dimensions, PaletteSampling::Nearest).unwrap();
+        let supersampled = resolve_flag_image(&supersample_palette(&palette, 1).unwrap(), &coordinates, &dimensions, PaletteSampling::Nearest).unwrap();
+        assert_eq!(channels(&supersampled.pixels), channels(&single.pixels));
+
+        // Neighborhoods at the edges only include the cells within the palette.
+        let supersampled = supersample_palette(&palette, 3).unwrap();
+        assert_eq!(channels(&supersampled.pixels), vec![(45, 15, 30), (100, 40, 20), (150, 60, 30)]);
+
+        assert!(supersample_palette(&palette, 0).is_err());
+    }
+
+    #[test]
+    fn palette_coordinate_to_cell_rounds_halfway_coordinate() {
+        let cells = |rounding| (palette_coordinate_to_cell(0.5, 2, rounding), palette_coordinate_to_cell(0.5, 3, rounding));
+
+        assert_eq!(cells(CoordinateRounding::Floor), (Some(1), Some(1)));
+        assert_eq!(cells(CoordinateRounding::Round), (Some(1), Some(2)));
+        assert_eq!(cells(CoordinateRounding::NearestCenter), (Some(1), Some(1)));
+
+        // Every mode clamps to the palette, and rejects coordinates that aren't numbers.
+        for rounding in [CoordinateRounding::Floor, CoordinateRounding::Round, CoordinateRounding::NearestCenter] {
+            assert_eq!(palette_coordinate_to_cell(1.0, 3, rounding), Some(2));
+            assert_eq!(palette_coordinate_to_cell(-0.5, 3, rounding), Some(0));
+            assert_eq!(palette_coordinate_to_cell(f64::NAN, 3, rounding), None);
+        }
+
+        // Flooring matches how the palette is sampled without a rounding mode.
+        let palette = Bitmap::new_from_pixels(3, 1, vec![Pixel24Bit { red: 0, green: 0, blue: 0 }; 3]).unwrap();
+        for coordinate in [0.0, 0.33, 1.0 / 3.0, 0.5, 0.66, 2.0 / 3.0, 0.99, 1.0] {
+            let (x, _) = palette.normalized_to_coords(coordinate, 0.0, EdgeMode::Clamp).unwrap();
+            assert_eq!(palette_coordinate_to_cell(coordinate, 3, CoordinateRounding::Floor), Some(x), "{coordinate}");
+        }
+    }
+
+    #[test]
+    fn match_colors_finds_same_location_as_full_search() {
+        // A palette of large flat regions, with a few distinct colors repeated throughout.
+        let colors = [(10, 20, 30), (200, 40, 90), (0, 255, 128), (250, 250, 250), (90, 90, 10)];
+        let pixels = (0..64 * 64)
+            .map(|i: usize| {
+                let (red, green, blue) = colors[(i / 512 + i % 7 / 5) % colors.len()];
+                Pixel24Bit { red, green, blue }
+            })
+            .collect();
+        let palette = Bitmap::new_from_pixels(64, 64, pixels).unwrap();
+        assert_eq!(palette.unique_palette().len(), colors.len());
+
+        let flag = random_bitmap(3, 32, 32);
+        for metric in [ColorMetric::Euclidean, ColorMetric::Redmean] {
+            let full: Vec<Option<(u32, u32)>> = flag.pixels.iter()
+                .map(|pixel| palette.find_pixel_by_closest_match_with(pixel, |a, b| metric.difference(a, b)))
+                .collect();
+
+            assert_eq!(match_colors(&palette, &flag.pixels, metric, None), full);
+        }
+    }
+}
@@ -0,0 +1,10 @@
+//! The parts of the Mage Arena flag editor that don't depend on the registry or the file system:
+//! the flag data format, and the conversion between flag images and flag data.
+
+#[macro_use]
+pub mod logging;
+pub mod error;
+pub mod flag;
+pub mod preprocess;
+#[cfg(feature = "wasm")]
+pub mod wasm;
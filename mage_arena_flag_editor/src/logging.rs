@@ -0,0 +1,45 @@
+use std::sync::atomic::{AtomicI8, Ordering};
+
+/// The verbosity of the progress output; negative is quiet, zero is the default, and positive is
+/// increasingly verbose.
+static VERBOSITY: AtomicI8 = AtomicI8::new(0);
+
+/// Set the verbosity of the progress output.
+pub fn set_verbosity(verbosity: i8) {
+    VERBOSITY.store(verbosity, Ordering::Relaxed);
+}
+
+/// Get the verbosity of the progress output.
+pub fn verbosity() -> i8 {
+    VERBOSITY.load(Ordering::Relaxed)
+}
+
+/// Print a warning to standard error, unless the output is quiet.
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        if $crate::logging::verbosity() >= 0 {
+            eprintln!("warning: {}", format_args!($($arg)*));
+        }
+    };
+}
+
+/// Print progress information to standard error, if the output is verbose.
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if $crate::logging::verbosity() >= 1 {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// Print detailed progress information to standard error, if the output is very verbose.
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::logging::verbosity() >= 2 {
+            eprintln!($($arg)*);
+        }
+    };
+}
@@ -1,10 +1,19 @@
 use crate::error::Error;
-use crate::error::Error::{AccessFailure, External, UnexpectedValue};
+use crate::error::Error::{AccessFailure, Busy, External, UnexpectedValue};
+use crate::flag::{coordinates_from_csv, coordinates_to_csv, decode_flag, encode_flag, encode_flag_records, flag_quality, flag_stats, flip_palette_rows, palette_swatches, palette_usage_map, parse_flag_records, resolve_flag_image_rounded, supersample_palette, CoordinateRounding, EncodeOptions, FlagDimensions, PaletteSampling, RecordSeparators};
+use crate::process;
 use bitmap_rs::{Bitmap, Pixel24Bit};
+use std::fs;
 use std::fs::File;
+use std::io;
 use std::io::{BufReader, BufWriter, Read, Write};
-use std::path::PathBuf;
-use windows_registry::{Key, Value, CURRENT_USER};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use notify_debouncer_mini::notify::RecursiveMode;
+use notify_debouncer_mini::{new_debouncer, DebouncedEvent, DebouncedEventKind};
+use windows_registry::{Key, Type, Value, CURRENT_USER};
 
 /// The key for Mage Arena's registry data in the [Hive::CurrentUser] registry.
 pub const MAGE_ARENA_KEY: &str = r"Software\jrsjams\MageArena";
@@ -12,193 +21,1320 @@ pub const MAGE_ARENA_KEY: &str = r"Software\jrsjams\MageArena";
 /// The key for the flag, relative to [MAGE_ARENA_KEY] in the [Hive::CurrentUser] registry.
 pub const MAGE_ARENA_FLAG_KEY_PREFIX: &str = r"flagGrid_";
 
-/// The width of the flag in pixels.
-pub const MAGE_ARENA_FLAG_WIDTH: i32 = 100;
+/// The prefix of the keys for software settings in the [Hive::CurrentUser] registry.
+const SOFTWARE_KEY_PREFIX: &str = r"Software\";
 
-/// The height of the flag in pixels.
-pub const MAGE_ARENA_FLAG_HEIGHT: i32 = 66;
+/// The key that 32-bit software settings are redirected to, relative to [SOFTWARE_KEY_PREFIX].
+const WOW6432_NODE_KEY: &str = "WOW6432Node";
 
-/// The number of bytes used to represent a pixel.
-const MAGE_ARENA_FLAG_PIXEL_SIZE: usize = 10;
+/// Get the path that a 32-bit install would store the settings key at (under the `WOW6432Node`
+/// key), or `None` if the key isn't under [SOFTWARE_KEY_PREFIX] or is already redirected.
+fn wow6432_node_key_path(key_path: &str) -> Option<String> {
+    let prefix = key_path.get(..SOFTWARE_KEY_PREFIX.len())?;
+    let relative_path = &key_path[SOFTWARE_KEY_PREFIX.len()..];
+
+    if !prefix.eq_ignore_ascii_case(SOFTWARE_KEY_PREFIX) || relative_path.split('\\').next()?.eq_ignore_ascii_case(WOW6432_NODE_KEY) {
+        return None;
+    }
+
+    Some(format!(r"{prefix}{WOW6432_NODE_KEY}\{relative_path}"))
+}
+
+/// Select the flag grid key from the names of the values under the Mage Arena settings key at
+/// `key_path`, i.e., the first that starts with [MAGE_ARENA_FLAG_KEY_PREFIX].
+///
+/// If there is no such value, the error lists the values that are present, in case the prefix has
+/// changed in a new version of the game.
+fn select_flag_grid_key(value_names: Vec<String>, key_path: &str) -> Result<String, Error> {
+    let (flag_grid_keys, other_keys): (Vec<String>, Vec<String>) = value_names.into_iter()
+        .partition(|name| name.starts_with(MAGE_ARENA_FLAG_KEY_PREFIX));
+
+    let Some(flag_grid_key) = flag_grid_keys.first() else {
+        let present_values = if other_keys.is_empty() { "none".to_string() } else { other_keys.join(", ") };
+        return Err(AccessFailure(format!(
+            r"failed to find flag grid key under COMPUTER\HKEY_CURRENT_USER\{key_path} (expected registry key with prefix {MAGE_ARENA_FLAG_KEY_PREFIX}; values present: {present_values})"
+        )));
+    };
+
+    if flag_grid_keys.len() > 1 {
+        warn!("Found {} flag grid keys ({}); using {flag_grid_key}", flag_grid_keys.len(), flag_grid_keys.join(", "));
+    }
+
+    Ok(flag_grid_key.clone())
+}
 
 /// Locate the user-specific flag grid key under the Mage Arena settings key.
 ///
-/// This function indexes [`COMPUTER\HKEY_CURRENT_USER\{MAGE_ARENA_KEY}`](MAGE_ARENA_KEY) for keys
-/// that start with [MAGE_ARENA_FLAG_KEY_PREFIX], returning the full name of the flag key if it is
+/// This function indexes [`COMPUTER\HKEY_CURRENT_USER\{key_path}`](MAGE_ARENA_KEY) for keys that
+/// start with [MAGE_ARENA_FLAG_KEY_PREFIX], returning the full name of the flag key if it is
 /// found, or an error if it is not.
-fn locate_flag_grid_key(mage_arena_key: &Key) -> Result<String, Error> {
-    mage_arena_key.values()
-        .map_err(|err| AccessFailure(format!(r"failed to index the subkeys of COMPUTER\HKEY_CURRENT_USER\{MAGE_ARENA_KEY} in the registry: {err}")))?
-        .find_map(|(key, _)| if key.starts_with(MAGE_ARENA_FLAG_KEY_PREFIX) { Some(key) } else { None })
-        .ok_or_else(|| AccessFailure(format!("failed to find flag grid key (expected registry key with prefix {MAGE_ARENA_FLAG_KEY_PREFIX})")))
+fn locate_flag_grid_key(mage_arena_key: &impl RegistryKey, key_path: &str) -> Result<String, Error> {
+    let value_names = mage_arena_key.value_names()
+        .map_err(|err| err.into_error(format!(r"failed to index the subkeys of COMPUTER\HKEY_CURRENT_USER\{key_path} in the registry")))?;
+
+    let flag_grid_key = select_flag_grid_key(value_names, key_path)?;
+
+    info!(r"Located flag grid key: COMPUTER\HKEY_CURRENT_USER\{key_path}\{flag_grid_key}");
+    Ok(flag_grid_key)
+}
+
+/// The Win32 error codes of registry operations that fail because the key is temporarily in use
+/// (e.g., by the game), rather than because it doesn't exist.
+const TRANSIENT_REGISTRY_ERRORS: [u32; 4] = [
+    5,   // ERROR_ACCESS_DENIED
+    32,  // ERROR_SHARING_VIOLATION
+    33,  // ERROR_LOCK_VIOLATION
+    170, // ERROR_BUSY
+];
+
+/// A failed registry operation.
+#[derive(Debug)]
+pub struct RegistryError {
+    /// The description of the failure.
+    pub message: String,
+
+    /// Whether the failure may be temporary (e.g., the key is locked by another process), so that
+    /// the operation may succeed if it is retried.
+    pub transient: bool,
+}
+
+impl RegistryError {
+    /// Convert the failure into an [Error], describing the operation that failed: [Busy] if it is
+    /// transient, or [AccessFailure] if it isn't.
+    fn into_error(self, operation: String) -> Error {
+        let message = format!("{operation}: {}", self.message);
+        if self.transient { Busy(message) } else { AccessFailure(message) }
+    }
+}
+
+/// Convert the error of a Windows registry operation into a [RegistryError], classifying it as
+/// transient by its Win32 error code (see [TRANSIENT_REGISTRY_ERRORS]).
+fn registry_result<T>(result: windows_registry::Result<T>) -> Result<T, RegistryError> {
+    result.map_err(|err| RegistryError {
+        transient: TRANSIENT_REGISTRY_ERRORS.iter().any(|&code| err.code().0 == (0x8007_0000 | code) as i32),
+        message: err.to_string(),
+    })
+}
+
+/// The access that a registry key is opened with.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum KeyAccess {
+    /// Open the key for reading only, so that commands that don't change the flag work even
+    /// without permission to write to the key.
+    Read,
+
+    /// Open the key for reading and writing.
+    ReadWrite,
+}
+
+/// A registry hive that the Mage Arena settings key can be opened under.
+pub trait RegistryHive {
+    /// The type of the keys opened under the hive.
+    type Key: RegistryKey;
+
+    /// Open the existing key at `path` under the hive, with the given access.
+    fn open_key(&self, path: &str, access: KeyAccess) -> Result<Self::Key, RegistryError>;
+}
+
+/// A value under a registry key, with its raw data.
+pub struct RegistryValue {
+    /// The name of the value.
+    pub name: String,
+    /// The registry type of the value.
+    pub ty: Type,
+    /// The raw bytes of the value.
+    pub data: Vec<u8>,
+}
+
+impl RegistryValue {
+    /// The name of the value's registry type, as shown by regedit (e.g., `REG_BINARY`).
+    pub fn type_name(&self) -> String {
+        match self.ty {
+            Type::U32 => "REG_DWORD".to_string(),
+            Type::U64 => "REG_QWORD".to_string(),
+            Type::String => "REG_SZ".to_string(),
+            Type::ExpandString => "REG_EXPAND_SZ".to_string(),
+            Type::MultiString => "REG_MULTI_SZ".to_string(),
+            Type::Bytes => "REG_BINARY".to_string(),
+            Type::Other(ty) => format!("type {ty}"),
+        }
+    }
+
+    /// Decode the value as text, if it holds any.
+    ///
+    /// String values are stored as UTF-16, but Unity stores the strings in its player preferences
+    /// (like the flag grid) as null-terminated UTF-8 in binary values, so those are decoded too.
+    /// Multiple strings are separated by newlines.
+    pub fn text(&self) -> Option<String> {
+        match self.ty {
+            Type::String | Type::ExpandString | Type::MultiString => {
+                let units: Vec<u16> = self.data.chunks_exact(2).map(|unit| u16::from_le_bytes([unit[0], unit[1]])).collect();
+                let text = String::from_utf16(&units).ok()?;
+                Some(text.trim_end_matches('\0').replace('\0', "\n"))
+            },
+            Type::Bytes => std::str::from_utf8(self.data.strip_suffix(&[0])?).ok().map(str::to_string),
+            _ => None,
+        }
+    }
+}
+
+/// An open registry key, holding the values that the flag grid is stored in.
+pub trait RegistryKey {
+    /// Get every value under the key, with its data.
+    fn values(&self) -> Result<Vec<RegistryValue>, RegistryError>;
+
+    /// Get the names of the values under the key.
+    fn value_names(&self) -> Result<Vec<String>, RegistryError> {
+        Ok(self.values()?.into_iter().map(|value| value.name).collect())
+    }
+
+    /// Read the named value as raw bytes.
+    fn read_value(&self, name: &str) -> Result<Vec<u8>, RegistryError>;
+
+    /// Write raw bytes to the named value.
+    fn write_value(&self, name: &str, data: &[u8]) -> Result<(), RegistryError>;
+}
+
+impl RegistryHive for Key {
+    type Key = Key;
+
+    fn open_key(&self, path: &str, access: KeyAccess) -> Result<Key, RegistryError> {
+        let mut options = self.options();
+        options.read();
+        if access == KeyAccess::ReadWrite {
+            options.write();
+        }
+
+        registry_result(options.open(path))
+    }
+}
+
+impl RegistryKey for Key {
+    fn values(&self) -> Result<Vec<RegistryValue>, RegistryError> {
+        Ok(registry_result(Key::values(self))?
+            .map(|(name, value)| RegistryValue { name, ty: value.ty(), data: value.to_vec() })
+            .collect())
+    }
+
+    fn read_value(&self, name: &str) -> Result<Vec<u8>, RegistryError> {
+        registry_result(self.get_value(name)).map(|value| value.to_vec())
+    }
+
+    fn write_value(&self, name: &str, data: &[u8]) -> Result<(), RegistryError> {
+        registry_result(self.set_value(name, &Value::from(data)))
+    }
+}
+
+/// The delay before the first retry of a transiently failed operation (see [retry]), which
+/// doubles with each subsequent retry.
+const RETRY_INITIAL_DELAY: Duration = Duration::from_millis(100);
+
+/// Run the operation, retrying it up to `retries` times (with exponential backoff) while it fails
+/// with [Busy], and returning the final error once the retries are exhausted. Any other error is
+/// returned immediately, as retrying won't help.
+pub fn retry<T>(retries: u32, mut operation: impl FnMut() -> Result<T, Error>) -> Result<T, Error> {
+    let mut delay = RETRY_INITIAL_DELAY;
+    for _ in 0..retries {
+        match operation() {
+            Err(Busy(message)) => {
+                info!("{message}; retrying in {} ms", delay.as_millis());
+                thread::sleep(delay);
+                delay *= 2;
+            },
+            result => return result,
+        }
+    }
+
+    operation()
+}
+
+/// Open the settings key at `key_path` under the hive (e.g., [MAGE_ARENA_KEY] under
+/// [CURRENT_USER]) with the given access, falling back to the `WOW6432Node` key used by 32-bit installs if the key
+/// doesn't exist, returning the path of the key that was opened along with it.
+fn open_settings_key<H: RegistryHive + ?Sized>(hive: &H, key_path: &str, access: KeyAccess) -> Result<(String, H::Key), Error> {
+    let fallback_key_path = wow6432_node_key_path(key_path);
+
+    // If either key is in use, rather than missing, the caller may want to retry.
+    let mut transient = false;
+    let opened_key = [Some(key_path), fallback_key_path.as_deref()].into_iter()
+        .flatten()
+        .find_map(|key_path| match hive.open_key(key_path, access) {
+            Ok(key) => Some((key_path.to_string(), key)),
+            Err(err) => {
+                transient |= err.transient;
+                None
+            },
+        });
+
+    let Some((key_path, key)) = opened_key else {
+        let message = match &fallback_key_path {
+            Some(fallback_key_path) => format!(r"could not access the COMPUTER\HKEY_CURRENT_USER\{key_path} or COMPUTER\HKEY_CURRENT_USER\{fallback_key_path} registry keys"),
+            None => format!(r"could not access the COMPUTER\HKEY_CURRENT_USER\{key_path} registry key"),
+        };
+        return Err(if transient { Busy(message) } else { AccessFailure(message) });
+    };
+
+    info!(r"Using registry key: COMPUTER\HKEY_CURRENT_USER\{key_path}");
+    Ok((key_path, key))
+}
+
+/// The flag grid value of an open Mage Arena settings key.
+///
+/// The key is opened, and the flag grid value located, once when the flag is opened, and every
+/// [read](MageArenaFlag::read) and [write](MageArenaFlag::write) reuses them, so that a command
+/// that accesses the flag several times doesn't reopen the registry key each time. The key is
+/// closed when the flag is dropped.
+pub struct MageArenaFlag<K: RegistryKey = Key> {
+    /// The open settings key.
+    key: K,
+    /// The path of the settings key, relative to the hive.
+    key_path: String,
+    /// The name of the flag grid value under the settings key.
+    flag_grid_key: String,
+    /// The number of times that a read or write is retried if the key is in use.
+    retries: u32,
+}
+
+impl<K: RegistryKey> MageArenaFlag<K> {
+    /// Open the settings key at `key_path` under the hive with the given access (see
+    /// [open_settings_key]), and locate the flag grid value under it.
+    pub fn open<H: RegistryHive<Key = K> + ?Sized>(hive: &H, key_path: &str, access: KeyAccess) -> Result<Self, Error> {
+        let (key_path, key) = open_settings_key(hive, key_path, access)?;
+        let flag_grid_key = locate_flag_grid_key(&key, &key_path)?;
+        Ok(MageArenaFlag { key, key_path, flag_grid_key, retries: 0 })
+    }
+
+    /// The path of the settings key that was opened, relative to the hive.
+    pub fn key_path(&self) -> &str {
+        &self.key_path
+    }
+
+    /// The name of the flag grid value under the settings key.
+    pub fn flag_grid_key(&self) -> &str {
+        &self.flag_grid_key
+    }
+
+    /// Set the number of times that a read or write of the flag is retried if the key is in use
+    /// (see [retry]).
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Read the raw flag data from the flag grid value.
+    pub fn read(&self) -> Result<Vec<u8>, Error> {
+        retry(self.retries, || self.key.read_value(&self.flag_grid_key)
+            .map_err(|err| err.into_error(format!(r"could not read the COMPUTER\HKEY_CURRENT_USER\{}\{} registry key", self.key_path, self.flag_grid_key))))
+    }
+
+    /// Write the raw flag data to the flag grid value.
+    pub fn write(&self, data: &[u8]) -> Result<(), Error> {
+        retry(self.retries, || self.key.write_value(&self.flag_grid_key, data)
+            .map_err(|err| err.into_error(format!(r"could not write the COMPUTER\HKEY_CURRENT_USER\{}\{} registry key", self.key_path, self.flag_grid_key))))
+    }
+}
+
+/// A store that the raw flag data can be read from and written to.
+pub trait FlagStore {
+    /// Read the raw flag data from the store.
+    fn read_raw(&self) -> Result<Vec<u8>, Error>;
+
+    /// Write the raw flag data to the store.
+    fn write_raw(&self, data: &[u8]) -> Result<(), Error>;
+}
+
+impl<K: RegistryKey> FlagStore for MageArenaFlag<K> {
+    fn read_raw(&self) -> Result<Vec<u8>, Error> {
+        self.read()
+    }
+
+    fn write_raw(&self, data: &[u8]) -> Result<(), Error> {
+        self.write(data)
+    }
 }
 
-/// Read the flag from the registry.
-fn read_raw_flag_data() -> Result<Vec<u8>, Error> {
-    let mage_arena_key = CURRENT_USER.open(MAGE_ARENA_KEY)
-        .map_err(|_| AccessFailure(format!(r"could not access the COMPUTER\HKEY_CURRENT_USER\{MAGE_ARENA_KEY} registry key")))?;
+/// Stores the flag in the Mage Arena settings key of the Windows registry.
+///
+/// Each read or write opens the key afresh (for reading only, or for reading and writing,
+/// respectively), so the key isn't held open in between; use [RegistryFlagStore::open] to keep
+/// the key open across several of them.
+pub struct RegistryFlagStore {
+    /// The path of the settings key, relative to `HKEY_CURRENT_USER`.
+    key_path: String,
+    /// The number of times that accessing the key is retried if it is in use.
+    retries: u32,
+}
 
-    let flag_key = mage_arena_key.get_value(locate_flag_grid_key(&mage_arena_key)?)
-        .map_err(|_| AccessFailure("could not access MageArena flag registry key".to_string()))?;
+impl RegistryFlagStore {
+    /// Create a store for the flag under the given settings key (e.g., [MAGE_ARENA_KEY]), relative
+    /// to `HKEY_CURRENT_USER`, retrying each access to the key up to `retries` times while it is in
+    /// use (see [retry]).
+    pub fn new(key_path: String, retries: u32) -> Self {
+        RegistryFlagStore { key_path, retries }
+    }
 
-    Ok(flag_key.to_vec())
+    /// Open the settings key with the given access, and locate the flag grid value under it.
+    pub fn open(&self, access: KeyAccess) -> Result<MageArenaFlag, Error> {
+        retry(self.retries, || MageArenaFlag::open(CURRENT_USER, &self.key_path, access))
+            .map(|flag| flag.with_retries(self.retries))
+    }
+
+    /// Open the settings key, and get every value under it (whether or not there is a flag grid
+    /// value), along with the path of the key that was opened.
+    pub fn values(&self) -> Result<(String, Vec<RegistryValue>), Error> {
+        retry(self.retries, || {
+            let (key_path, key) = open_settings_key(CURRENT_USER, &self.key_path, KeyAccess::Read)?;
+            let values = RegistryKey::values(&key)
+                .map_err(|err| err.into_error(format!(r"failed to index the values of COMPUTER\HKEY_CURRENT_USER\{key_path} in the registry")))?;
+            Ok((key_path, values))
+        })
+    }
 }
 
-/// Write the flag to the registry.
-fn write_raw_flag_data(data: &[u8]) -> Result<(), Error> {
-    let mage_arena_key = CURRENT_USER.create(MAGE_ARENA_KEY)
-        .map_err(|_| AccessFailure(format!(r"could not access the COMPUTER\HKEY_CURRENT_USER\{MAGE_ARENA_KEY} registry key")))?;
+impl FlagStore for RegistryFlagStore {
+    fn read_raw(&self) -> Result<Vec<u8>, Error> {
+        self.open(KeyAccess::Read)?.read()
+    }
 
-    mage_arena_key.set_value(locate_flag_grid_key(&mage_arena_key)?, &Value::from(data))
-        .map_err(|_| AccessFailure("could not access MageArena flag registry key".to_string()))
+    fn write_raw(&self, data: &[u8]) -> Result<(), Error> {
+        self.open(KeyAccess::ReadWrite)?.write(data)
+    }
 }
 
-fn read_bitmap_file(bitmap_file: &PathBuf) -> Result<Bitmap<Pixel24Bit>, Error> {
-    let mut reader = BufReader::new(File::open(bitmap_file)
-        .map_err(|err| AccessFailure(format!("failed to access bitmap file: {err}")))?);
+/// The path that refers to standard input or output, instead of a file.
+pub const STDIO_PATH: &str = "-";
 
+/// Read the whole of the reader into memory, and parse it as a bitmap, naming the source of the
+/// bitmap in any error.
+fn read_bitmap(mut reader: impl Read, source: &str) -> Result<Bitmap<Pixel24Bit>, Error> {
     let mut file_data: Vec<u8> = vec![];
     reader.read_to_end(&mut file_data)
-        .map_err(|err| AccessFailure(format!("failed to read bitmap file: {err}")))?;
+        .map_err(|err| AccessFailure(format!("failed to read bitmap from {source}: {err}")))?;
 
     Bitmap::new_from_bytes(file_data)
-        .map_err(|err| External(format!("failed to parse bitmap data in palette file: {err}")))
+        .map_err(|err| External(format!("failed to parse bitmap data in {source}: {err}")))
 }
 
-pub fn read_flag(palette_file: PathBuf, output_file: PathBuf) -> Result<(), Error> {
-    let palette = read_bitmap_file(&palette_file)?;
+/// Read the bitmap from `bitmap_file`, or from standard input if it is [STDIO_PATH].
+pub(crate) fn read_bitmap_file(bitmap_file: &PathBuf) -> Result<Bitmap<Pixel24Bit>, Error> {
+    let bitmap = if bitmap_file == Path::new(STDIO_PATH) {
+        read_bitmap(io::stdin().lock(), "standard input")?
+    } else {
+        let reader = BufReader::new(File::open(bitmap_file)
+            .map_err(|err| AccessFailure(format!("failed to access bitmap file: {err}")))?);
 
-    let raw_data = read_raw_flag_data()?;
-    if raw_data.is_empty() {
-        return Err(UnexpectedValue("flag data is missing".to_string()));
-    }
+        read_bitmap(reader, &bitmap_file.display().to_string())?
+    };
+
+    debug!("Read {}x{} bitmap from {}", bitmap.get_width(), bitmap.get_height(), bitmap_file.display());
+    Ok(bitmap)
+}
 
-    // Split the raw data into chunks.
-    let (raw_pixels, []) = raw_data.as_chunks::<MAGE_ARENA_FLAG_PIXEL_SIZE>() else {
-        return Err(UnexpectedValue(format!("raw flag data length is not divisible by the pixel size ({MAGE_ARENA_FLAG_PIXEL_SIZE})")));
+/// Write the bitmap to `output_file`, or to standard output if it is [STDIO_PATH].
+pub(crate) fn write_bitmap_file(bitmap: &Bitmap<Pixel24Bit>, output_file: &Path) -> Result<(), Error> {
+    let written = if output_file == Path::new(STDIO_PATH) {
+        bitmap.write_to(BufWriter::new(io::stdout().lock()))
+            .map_err(|err| AccessFailure(format!("failed to write the bitmap to standard output: {err}")))?
+    } else {
+        bitmap.write_to_file_buffered(output_file)
+            .map_err(|err| AccessFailure(format!("failed to write the bitmap to {}: {err}", output_file.display())))?
     };
 
-    // Perform a matrix transposition on the pixels - as the registry values are column-ordered
-    // while bitmap images are row-ordered.
-    let pixels: Vec<[u8; 10]> = (0..MAGE_ARENA_FLAG_HEIGHT as usize)
-        .flat_map(|i| {
-            (0..MAGE_ARENA_FLAG_WIDTH as usize).map(move |j| {
-                raw_pixels[j * MAGE_ARENA_FLAG_HEIGHT as usize + i]
-            })
-        }).collect();
+    debug!("Wrote {written} bytes of bitmap data");
+    Ok(())
+}
 
-    // Ensure that all chunks have a comma as the last byte (except the last chunk, which must have
-    // null).
-    let mut bad_pixels: Vec<Error> = vec![];
-    let pixels: Vec<Pixel24Bit> = pixels.iter()
-        .enumerate()
-        .map(|(i, pixel)| {
-            // Ensure pixel data ends with an ASCII comma (or a null if it's the last pixel).
-            let is_last_pixel = i == pixels.len() - 1;
+/// Read the palette from the bitmap file, mirroring it vertically if `flip_y` is set.
+fn read_palette_file(palette_file: &PathBuf, flip_y: bool) -> Result<Bitmap<Pixel24Bit>, Error> {
+    let palette = read_bitmap_file(palette_file)?;
+    Ok(if flip_y { flip_palette_rows(&palette) } else { palette })
+}
 
-            let expected_last_char = if is_last_pixel { 0 } else { 0x2C };
-            let actual_last_char = pixel[9];
+/// Read the flag from the given storage as the normalized `(x, y)` palette coordinate of each
+/// flag pixel, in row order, without resolving the coordinates against a palette.
+pub fn read_flag_coordinates(storage: &dyn FlagStore, dimensions: &FlagDimensions) -> Result<Vec<(f64, f64)>, Error> {
+    parse_flag_records(&storage.read_raw()?, dimensions)
+}
+
+/// Options for [read_flag].
+#[derive(Debug, Default)]
+pub struct ReadOptions {
+    /// How the palette color of each flag pixel is sampled.
+    pub sampling: PaletteSampling,
+
+    /// How each palette coordinate is rounded to a palette cell, when sampling the nearest cell
+    /// (see [CoordinateRounding]).
+    pub coord_rounding: CoordinateRounding,
+
+    /// Mirror the palette vertically before sampling it (see [flip_palette_rows]).
+    pub flip_y: bool,
+
+    /// Average each palette sample over the `n`x`n` palette cells around it (see
+    /// [supersample_palette]), or take a single cell if this is 0 or 1.
+    pub supersample: u32,
+
+    /// Print statistics about how the flag samples the palette.
+    pub stats: bool,
+
+    /// Also write the palette coordinate of each flag pixel to this file as CSV (see
+    /// [coordinates_to_csv]).
+    pub csv_file: Option<PathBuf>,
+}
+
+/// Whether the file should be written as a PNG rather than a bitmap, i.e., it has a `.png`
+/// extension.
+fn is_png_file(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+}
+
+/// Read the flag from storage into `output_file` (or standard output, if it is [STDIO_PATH]), as
+/// a PNG if the file has a `.png` extension (see [Bitmap::to_png]), or a bitmap otherwise.
+pub fn read_flag(storage: &dyn FlagStore, palette_file: PathBuf, output_file: PathBuf, dimensions: &FlagDimensions, options: &ReadOptions) -> Result<(), Error> {
+    let palette = read_palette_file(&palette_file, options.flip_y)?;
+    let coordinates = read_flag_coordinates(storage, dimensions)?;
 
-            if actual_last_char != expected_last_char {
-                return Err(UnexpectedValue(format!("pixel {i} contains an invalid last character (expected: {expected_last_char}, got: {actual_last_char})")))
+    let bitmap = if options.supersample > 1 {
+        resolve_flag_image_rounded(&supersample_palette(&palette, options.supersample)?, &coordinates, dimensions, options.sampling, options.coord_rounding)?
+    } else {
+        resolve_flag_image_rounded(&palette, &coordinates, dimensions, options.sampling, options.coord_rounding)?
+    };
+
+    if is_png_file(&output_file) {
+        fs::write(&output_file, bitmap.to_png())
+            .map_err(|err| AccessFailure(format!("failed to write the PNG to {}: {err}", output_file.display())))?;
+    } else {
+        write_bitmap_file(&bitmap, &output_file)?;
+    }
+
+    let is_stdout = output_file == Path::new(STDIO_PATH);
+    if is_stdout {
+        info!("Wrote flag to standard output");
+    } else {
+        info!("Wrote flag to {}", output_file.display());
+    }
+
+    if let Some(csv_file) = &options.csv_file {
+        fs::write(csv_file, coordinates_to_csv(&coordinates))
+            .map_err(|err| AccessFailure(format!("failed to write flag coordinates to {}: {err}", csv_file.display())))?;
+        info!("Wrote flag coordinates to {}", csv_file.display());
+    }
+
+    if options.stats && let Some(stats) = flag_stats(&palette, &coordinates)? {
+        let (color, count) = stats.most_common_color;
+        let ((min_x, min_y), (max_x, max_y)) = stats.sampled_bounds;
+
+        let report = [
+            format!("Distinct palette colors: {}", stats.distinct_colors),
+            format!("Most common color: {color} ({count} of {} pixels)", coordinates.len()),
+            format!(
+                "Sampled palette cells: ({min_x}, {min_y}) to ({max_x}, {max_y}) of a {}x{} palette",
+                palette.get_width(), palette.get_height()
+            ),
+        ];
+
+        // Keep standard output clean for the bitmap if it was written there.
+        for line in report {
+            if is_stdout {
+                eprintln!("{line}");
+            } else {
+                println!("{line}");
             }
+        }
+    }
 
-            let Some(divider) = pixel.iter().position(|&b| b == 0x3A) else {
-                return Err(UnexpectedValue(format!("pixel {i} is missing the expected divider character (:)")))
-            };
-
-            let x_str = String::from_utf8(pixel[0..divider].to_vec())
-                .map_err(|err| UnexpectedValue(format!("pixel {i}'s x-coordinate was not valid UTF-8: {err}")))?;
-            let x = x_str.parse::<f64>()
-                .map_err(|err| UnexpectedValue(format!("pixel {i}'s x-coordinate ({x_str}) was not a valid float: {err}")))?;
-            let x = if x > 1.0 { x / 100.0 } else { x };
-            let x_coordinate = (x * f64::from(palette.get_width())) as u32;
-
-            let y_str = String::from_utf8(pixel[divider+1..9].to_vec())
-                .map_err(|err| UnexpectedValue(format!("pixel {i}'s y-coordinate was not valid UTF-8: {err}")))?;
-            let y = y_str.parse::<f64>()
-                .map_err(|err| UnexpectedValue(format!("pixel {i}'s y-coordinate ({y_str}) was not a valid float: {err}")))?;
-            let y = if y > 1.0 { y / 100.0 } else { y };
-            let y_coordinate = (y * f64::from(palette.get_height())) as u32;
-
-            let Some(palette_pixel) = palette.get_pixel_at(x_coordinate, y_coordinate) else {
-                return Err(UnexpectedValue(format!("failed to resolve palette pixel ({x_coordinate}, {y_coordinate}) for pixel {i}")));
-            };
-
-            Ok(*palette_pixel)
-        })
-        .filter_map(|pixel| pixel.map_err(|e| bad_pixels.push(e)).ok())
-        .collect();
+    Ok(())
+}
+
+/// Read the flag from storage, and write a map of the palette cells that it samples to
+/// `output_file` (or standard output, if it is [STDIO_PATH]).
+pub fn export_palette_map(storage: &dyn FlagStore, output_file: &Path, dimensions: &FlagDimensions) -> Result<(), Error> {
+    let map = palette_usage_map(&read_flag_coordinates(storage, dimensions)?, dimensions)?;
+    write_bitmap_file(&map, output_file)?;
+
+    let used = Pixel24Bit { red: 255, green: 255, blue: 255 };
+    info!(
+        "Wrote a {}x{} palette map to {} (the flag samples {} of {} palette cells)",
+        map.get_width(), map.get_height(), output_file.display(), map.positions_of(&used).len(), map.pixels.len()
+    );
+    Ok(())
+}
+
+/// Print each distinct color of the palette with the normalized palette coordinate of its first
+/// cell, from darkest to brightest, as a reference for authoring flag coordinates by hand.
+pub fn print_palette_swatches(palette_file: PathBuf) -> Result<(), Error> {
+    let palette = read_bitmap_file(&palette_file)?;
+
+    let swatches = palette_swatches(&palette);
+    for (color, (x, y)) in &swatches {
+        println!("{color}  ({x}, {y})");
+    }
+
+    info!("Listed {} distinct colors in {}", swatches.len(), palette_file.display());
+    Ok(())
+}
+
+/// Print the name, type, and size of every value under the settings key (and the text of those
+/// that hold text), to diagnose a flag grid value that can't be found.
+pub fn print_registry_values(registry: &RegistryFlagStore) -> Result<(), Error> {
+    let (key_path, values) = registry.values()?;
 
-    if !bad_pixels.is_empty() {
+    for value in &values {
+        match value.text() {
+            Some(text) => println!("{} ({}, {} bytes): {text:?}", value.name, value.type_name(), value.data.len()),
+            None => println!("{} ({}, {} bytes)", value.name, value.type_name(), value.data.len()),
+        }
+    }
+
+    info!(r"Listed {} values under COMPUTER\HKEY_CURRENT_USER\{key_path}", values.len());
+    Ok(())
+}
+
+/// Report how closely the flag image in `input_file` round-trips through the palette, counting
+/// the pixels whose decoded color is within `threshold` of the original.
+pub fn report_flag_quality(palette_file: PathBuf, input_file: PathBuf, dimensions: &FlagDimensions, options: &EncodeOptions, threshold: f64) -> Result<(), Error> {
+    let palette = read_bitmap_file(&palette_file)?;
+    let flag = read_bitmap_file(&input_file)?;
+
+    let quality = flag_quality(flag, &palette, dimensions, options, threshold)?;
+    let (worst_difference, (worst_x, worst_y)) = quality.worst_difference;
+
+    println!(
+        "{} of {} pixels ({:.1}%) are within {threshold} of their original color",
+        quality.within_threshold, quality.pixel_count, 100.0 * quality.within_threshold as f64 / quality.pixel_count as f64
+    );
+    println!("Mean difference: {:.2}", quality.mean_difference);
+    println!("Worst difference: {worst_difference:.2} (at pixel ({worst_x}, {worst_y}))");
+    if quality.psnr.is_infinite() {
+        println!("PSNR: infinite (the flag round-trips exactly)");
+    } else {
+        println!("PSNR: {:.2} dB", quality.psnr);
+    }
+    println!("SSIM: {:.4}", quality.ssim);
+    Ok(())
+}
+
+/// Get the signed difference of each channel (see [Pixel24Bit::channel_differences]) of every
+/// pixel that differs between the two images, with the index of the pixel in the first image.
+///
+/// The pixels are paired by their position in the image, even if only one of the images is
+/// stored top-down.
+fn differing_pixels(first: &Bitmap<Pixel24Bit>, mut second: Bitmap<Pixel24Bit>) -> Result<Vec<(usize, [i16; 3])>, Error> {
+    if (first.get_width(), first.get_height()) != (second.get_width(), second.get_height()) {
         return Err(UnexpectedValue(format!(
-            "bad pixels\n\n{}",
-            bad_pixels.iter().map(|err| err.to_string()).collect::<Vec<String>>().join("\n")
+            "the images must have the same dimensions (got {}x{} and {}x{})",
+            first.get_width(), first.get_height(), second.get_width(), second.get_height()
         )));
     }
 
-    let width = MAGE_ARENA_FLAG_WIDTH;
-    let height = MAGE_ARENA_FLAG_HEIGHT;
-    let bitmap = Bitmap::new_from_pixels(width, height, pixels)
-        .map_err(|err| External(format!("failed to create bitmap image: {err}")))?;
+    second.set_top_down(first.is_top_down());
+    Ok(first.pixels.iter().zip(&second.pixels)
+        .map(|(pixel, other)| pixel.channel_differences(other))
+        .enumerate()
+        .filter(|(_, differences)| *differences != [0; 3])
+        .collect())
+}
 
-    let mut output_file_writer = BufWriter::new(File::create(&output_file)
-        .map_err(|err| AccessFailure(format!("could not create or access the requested output file: {err}")))?);
+/// Compare two images pixel by pixel, printing how many pixels differ and the mean signed
+/// difference of each channel from the first image to the second (e.g., to see that a palette
+/// is consistently too blue). When verbose, each differing pixel is listed with the difference
+/// of each channel.
+pub fn diff_bitmaps(first_file: PathBuf, second_file: PathBuf) -> Result<(), Error> {
+    let first = read_bitmap_file(&first_file)?;
+    let second = read_bitmap_file(&second_file)?;
 
-    output_file_writer.write_all(&bitmap.to_bytes())
-        .map_err(|err| AccessFailure(format!("failed to write bytes to file: {err}")))?;
+    let differences = differing_pixels(&first, second)?;
+    let mut sums = [0i64; 3];
+    for (index, channel_differences) in &differences {
+        let (x, y) = first.coords_of(*index);
+        let [red, green, blue] = *channel_differences;
+        info!("Pixel ({x}, {y}) differs by red {red:+}, green {green:+}, blue {blue:+}");
 
-    output_file_writer.flush()
-        .map_err(|err| AccessFailure(format!("failed to flush output file: {err}")))?;
+        for (sum, difference) in sums.iter_mut().zip(channel_differences) {
+            *sum += i64::from(*difference);
+        }
+    }
 
+    let pixel_count = first.pixels.len();
+    println!("{} of {pixel_count} pixels differ", differences.len());
+
+    let [red, green, blue] = sums.map(|sum| sum as f64 / pixel_count.max(1) as f64);
+    println!("Mean channel difference: red {red:+.2}, green {green:+.2}, blue {blue:+.2}");
     Ok(())
 }
 
-pub fn write_flag(palette_file: PathBuf, input_file: PathBuf) -> Result<(), Error> {
-    let palette = read_bitmap_file(&palette_file)?;
-    let flag = read_bitmap_file(&input_file)?;
+/// Render the flag pixels (in row order) as a truecolor terminal preview.
+///
+/// Each character cell shows two rows of pixels, using the upper half block character with the
+/// upper pixel as the foreground color and the lower pixel as the background color.
+fn render_flag_preview(pixels: &[Pixel24Bit], dimensions: &FlagDimensions) -> String {
+    let rows: Vec<&[Pixel24Bit]> = pixels.chunks(dimensions.width).collect();
+
+    rows.chunks(2)
+        .map(|row_pair| {
+            let mut line: String = (0..row_pair[0].len())
+                .map(|x| {
+                    // The escape codes take the true red, green and blue channels.
+                    let [upper_red, upper_green, upper_blue] = row_pair[0][x].to_rgb();
+                    match row_pair.get(1) {
+                        Some(lower_row) => {
+                            let [lower_red, lower_green, lower_blue] = lower_row[x].to_rgb();
+                            format!(
+                                "\x1b[38;2;{upper_red};{upper_green};{upper_blue}m\x1b[48;2;{lower_red};{lower_green};{lower_blue}m\u{2580}"
+                            )
+                        },
+                        None => format!("\x1b[38;2;{upper_red};{upper_green};{upper_blue}m\x1b[49m\u{2580}"),
+                    }
+                })
+                .collect();
+
+            line.push_str("\x1b[0m\n");
+            line
+        })
+        .collect()
+}
+
+/// Ask the user to confirm an action on the terminal, returning true only if they answer yes.
+fn confirm(prompt: &str) -> Result<bool, Error> {
+    print!("{prompt} [y/N] ");
+    std::io::stdout().flush()
+        .map_err(|err| AccessFailure(format!("failed to flush standard output: {err}")))?;
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)
+        .map_err(|err| AccessFailure(format!("failed to read confirmation from standard input: {err}")))?;
 
-    let palette_width = f64::from(palette.get_width());
-    let palette_height = f64::from(palette.get_height());
+    Ok(matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes"))
+}
+
+/// The prefix of the file name of flag backups, which is followed by the time of the backup (in
+/// milliseconds since the Unix epoch).
+pub const FLAG_BACKUP_FILE_PREFIX: &str = "flag_backup_";
+
+/// Save the current raw flag data in the given storage to a timestamped backup file in the given
+/// directory, returning the path of the backup file.
+///
+/// If the current flag can't be read (e.g., because no flag has been saved yet), a warning is
+/// printed and no backup is made.
+fn backup_flag(storage: &dyn FlagStore, backup_directory: &Path) -> Result<Option<PathBuf>, Error> {
+    let raw_data = match storage.read_raw() {
+        Ok(raw_data) => raw_data,
+        Err(err) => {
+            warn!("skipping backup, as the current flag could not be read: {err}");
+            return Ok(None);
+        }
+    };
+
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)
+        .map_err(|err| UnexpectedValue(format!("system clock is set before the Unix epoch: {err}")))?
+        .as_millis();
+
+    fs::create_dir_all(backup_directory)
+        .map_err(|err| AccessFailure(format!("could not create the backup directory: {err}")))?;
+
+    let backup_file = backup_directory.join(format!("{FLAG_BACKUP_FILE_PREFIX}{timestamp}.txt"));
+    fs::write(&backup_file, raw_data)
+        .map_err(|err| AccessFailure(format!("failed to write backup file: {err}")))?;
+
+    Ok(Some(backup_file))
+}
+
+/// Find the most recent flag backup file in the given directory.
+fn find_latest_flag_backup(backup_directory: &Path) -> Result<PathBuf, Error> {
+    let entries = fs::read_dir(backup_directory)
+        .map_err(|err| AccessFailure(format!("could not access the backup directory ({}): {err}", backup_directory.display())))?;
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name();
+            let timestamp = file_name.to_str()?
+                .strip_prefix(FLAG_BACKUP_FILE_PREFIX)?
+                .strip_suffix(".txt")?
+                .parse::<u128>().ok()?;
+
+            Some((timestamp, entry.path()))
+        })
+        .max_by_key(|&(timestamp, _)| timestamp)
+        .map(|(_, path)| path)
+        .ok_or_else(|| AccessFailure(format!("no flag backups were found in {}", backup_directory.display())))
+}
+
+/// Restore the most recent flag backup in the given directory to the given storage, then delete
+/// that backup (so that repeated calls walk back through the backup history), returning the path
+/// of the restored backup.
+pub fn undo_flag(storage: &dyn FlagStore, backup_directory: &Path, dimensions: &FlagDimensions) -> Result<PathBuf, Error> {
+    let backup_file = find_latest_flag_backup(backup_directory)?;
+
+    let raw_data = fs::read(&backup_file)
+        .map_err(|err| AccessFailure(format!("failed to read backup file ({}): {err}", backup_file.display())))?;
+
+    // Ensure the backup is a valid flag before writing it.
+    parse_flag_records(&raw_data, dimensions)?;
+    storage.write_raw(&raw_data)?;
+
+    fs::remove_file(&backup_file)
+        .map_err(|err| AccessFailure(format!("restored the flag, but failed to delete backup file ({}): {err}", backup_file.display())))?;
+
+    Ok(backup_file)
+}
+
+/// Options that control how [write_flag] and [write_flag_csv] write the flag.
+#[derive(Debug, Default)]
+pub struct WriteOptions {
+    /// Preview the flag as it will appear in-game, and ask for confirmation before writing it.
+    pub preview: bool,
+
+    /// Perform the pixel mapping and report the result, without writing the flag.
+    pub dry_run: bool,
+
+    /// The directory to back up the current flag to before it is overwritten, or [None] to skip
+    /// the backup.
+    pub backup_directory: Option<PathBuf>,
+
+    /// Write the flag even if Mage Arena is running.
+    pub force: bool,
+
+    /// Mirror the palette vertically before mapping the flag image to it (see
+    /// [flip_palette_rows]).
+    pub flip_y: bool,
+
+    /// How the flag image is mapped to the palette.
+    pub encode: EncodeOptions,
+}
+
+pub fn write_flag(storage: &dyn FlagStore, palette_file: PathBuf, input_file: PathBuf, dimensions: &FlagDimensions, options: &WriteOptions) -> Result<(), Error> {
+    // The confirmation is read from standard input, so it can't also be the source of the image.
+    if options.preview && input_file == Path::new(STDIO_PATH) {
+        return Err(UnexpectedValue("--preview can't be used when the image is read from standard input".to_string()));
+    }
+
+    let palette = read_palette_file(&palette_file, options.flip_y)?;
+    let flag = read_bitmap_file(&input_file)?;
     let pixel_count = flag.pixels.len();
 
-    // Perform a matrix transposition on the pixels - as the registry values are column-ordered
-    // while bitmap images are row-ordered.
-    let pixels: Vec<Pixel24Bit> = (0..MAGE_ARENA_FLAG_WIDTH as usize)
-        .flat_map(|i| {
-            (0..MAGE_ARENA_FLAG_HEIGHT as usize).map(move |j| {
-                j * MAGE_ARENA_FLAG_WIDTH as usize + i
+    let dimensions = &with_stored_separators(storage, dimensions);
+    let raw_data = encode_flag(flag, &palette, dimensions, &options.encode)?;
+
+    if options.preview {
+        // Reconstruct the flag from the encoded data, so that the preview shows exactly what will
+        // be read back in-game.
+        let preview = decode_flag(raw_data.as_bytes(), &palette, dimensions)?;
+        print!("{}", render_flag_preview(&preview.pixels, dimensions));
+
+        if !confirm("Write this flag?")? {
+            println!("Aborted; the flag was not written.");
+            return Ok(());
+        }
+    }
+
+    if options.dry_run {
+        println!("Dry run: all {pixel_count} pixels mapped to the palette; the flag data would be {} bytes.", raw_data.len());
+        return Ok(());
+    }
+
+    write_raw_flag_data(storage, &raw_data, options)
+}
+
+/// Write the flag data from the CSV of palette coordinates in `input_file` (or standard input, if
+/// it is [STDIO_PATH]) into storage. See [coordinates_from_csv] for the format.
+pub fn write_flag_csv(storage: &dyn FlagStore, input_file: PathBuf, dimensions: &FlagDimensions, options: &WriteOptions) -> Result<(), Error> {
+    let csv = if input_file == Path::new(STDIO_PATH) {
+        io::read_to_string(io::stdin().lock())
+    } else {
+        fs::read_to_string(&input_file)
+    }.map_err(|err| AccessFailure(format!("failed to read CSV file ({}): {err}", input_file.display())))?;
+
+    let dimensions = &with_stored_separators(storage, dimensions);
+    let coordinates = coordinates_from_csv(&csv, dimensions)?;
+    let raw_data = encode_flag_records(&coordinates, dimensions)?;
+
+    if options.dry_run {
+        println!("Dry run: read all {} pixels from the CSV; the flag data would be {} bytes.", coordinates.len(), raw_data.len());
+        return Ok(());
+    }
+
+    write_raw_flag_data(storage, &raw_data, options)
+}
+
+/// How long the input image must go unmodified before [watch_flag] writes it, so that an image
+/// that is still being saved isn't written part way through.
+const WATCH_SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// Whether the debounced events include a change to the watched file that has settled (rather
+/// than one that is still ongoing, see [DebouncedEventKind::AnyContinuous]).
+fn watched_file_changed(events: &[DebouncedEvent], watched_file: &Path) -> bool {
+    events.iter().any(|event| event.kind == DebouncedEventKind::Any && event.path == watched_file)
+}
+
+/// Write the flag from `input_file` into storage (as [write_flag] does), and again each time the
+/// file is modified, until the process is interrupted (e.g., with Ctrl-C).
+///
+/// The directory containing the file is watched, so that a file that is replaced when it is saved
+/// is still followed, and the file is only written once it has gone unmodified for
+/// [WATCH_SETTLE_TIME]. The current flag is backed up to `backup_directory` (if any) once, before
+/// watching starts, rather than on every change. A failed update (e.g., of an image that was only
+/// partially saved) is reported, and watching continues.
+///
+/// This only returns if the file can't be watched, or the watcher fails.
+pub fn watch_flag(storage: &dyn FlagStore, palette_file: PathBuf, input_file: PathBuf, dimensions: &FlagDimensions, backup_directory: Option<&Path>, options: &WriteOptions) -> Result<(), Error> {
+    if input_file == Path::new(STDIO_PATH) {
+        return Err(UnexpectedValue("the image to watch can't be read from standard input".to_string()));
+    }
+
+    // The watcher reports the absolute paths of the files in the directory.
+    let (Some(directory), Some(file_name)) = (input_file.parent(), input_file.file_name()) else {
+        return Err(UnexpectedValue(format!("{} is not a file that can be watched", input_file.display())));
+    };
+    let directory = if directory.as_os_str().is_empty() { Path::new(".") } else { directory };
+    let directory = fs::canonicalize(directory)
+        .map_err(|err| AccessFailure(format!("could not access the directory of {}: {err}", input_file.display())))?;
+    let watched_file = directory.join(file_name);
+
+    let (sender, receiver) = mpsc::channel();
+    let mut debouncer = new_debouncer(WATCH_SETTLE_TIME, sender)
+        .map_err(|err| External(format!("failed to create a file watcher: {err}")))?;
+    debouncer.watcher().watch(&directory, RecursiveMode::NonRecursive)
+        .map_err(|err| External(format!("failed to watch {}: {err}", directory.display())))?;
+
+    if let Some(backup_directory) = backup_directory
+        && let Some(backup_file) = backup_flag(storage, backup_directory)? {
+        info!("Backed up the current flag to {}", backup_file.display());
+    }
+
+    let update = || match write_flag(storage, palette_file.clone(), input_file.clone(), dimensions, options) {
+        Ok(()) => println!("Updated the flag from {}", input_file.display()),
+        Err(err) => warn!("failed to update the flag from {}: {err}", input_file.display()),
+    };
+
+    update();
+    println!("Watching {} for changes; press Ctrl-C to stop.", input_file.display());
+
+    // The debouncer holds the sender, so this only ends if the watcher is dropped.
+    for events in receiver {
+        let events = events.map_err(|err| External(format!("failed while watching {}: {err}", input_file.display())))?;
+        if watched_file_changed(&events, &watched_file) {
+            update();
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolve [RecordSeparators::Auto] to the separators of the flag that's currently stored (if
+/// any), as they depend on the locale of the game build.
+fn with_stored_separators(storage: &dyn FlagStore, dimensions: &FlagDimensions) -> FlagDimensions {
+    let mut dimensions = *dimensions;
+    if dimensions.separators == RecordSeparators::Auto && let Ok(current_data) = storage.read_raw() {
+        dimensions.separators = dimensions.separators.resolve(&current_data);
+    }
+
+    dimensions
+}
+
+/// Write the raw flag data into storage, first checking that the game isn't running and backing
+/// up the current flag, as configured by the options.
+fn write_raw_flag_data(storage: &dyn FlagStore, raw_data: &str, options: &WriteOptions) -> Result<(), Error> {
+    if !options.force && process::is_mage_arena_running() {
+        return Err(AccessFailure(
+            "Mage Arena is running, so the flag was not written (the game may overwrite the flag with its own when it exits); close the game first, or pass --force to write the flag anyway".to_string()
+        ));
+    }
+
+    if let Some(backup_directory) = &options.backup_directory
+        && let Some(backup_file) = backup_flag(storage, backup_directory)? {
+        info!("Backed up the current flag to {}", backup_file.display());
+    }
+
+    storage.write_raw(raw_data.as_bytes())?;
+
+    info!("Wrote {} bytes of flag data", raw_data.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flag::MAGE_ARENA_FLAG_PIXEL_SIZE;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    /// An in-memory [FlagStore] for testing.
+    struct FakeFlagStore(RefCell<Vec<u8>>);
+
+    impl FlagStore for FakeFlagStore {
+        fn read_raw(&self) -> Result<Vec<u8>, Error> {
+            Ok(self.0.borrow().clone())
+        }
+
+        fn write_raw(&self, data: &[u8]) -> Result<(), Error> {
+            *self.0.borrow_mut() = data.to_vec();
+            Ok(())
+        }
+    }
+
+    /// The named values under a [FakeRegistryHive]'s settings key, shared with every key opened.
+    type FakeRegistryValues = Rc<RefCell<Vec<(String, Vec<u8>)>>>;
+
+    /// An in-memory [RegistryHive], with a single settings key, that counts how often it is opened.
+    struct FakeRegistryHive {
+        key_path: String,
+        values: FakeRegistryValues,
+        opened: Cell<usize>,
+    }
+
+    /// A key opened under a [FakeRegistryHive], with the access it was opened with.
+    struct FakeRegistryKey(FakeRegistryValues, KeyAccess);
+
+    impl RegistryHive for FakeRegistryHive {
+        type Key = FakeRegistryKey;
+
+        fn open_key(&self, path: &str, access: KeyAccess) -> Result<FakeRegistryKey, RegistryError> {
+            if path != self.key_path {
+                return Err(RegistryError { message: format!("{path} not found"), transient: false });
+            }
+
+            self.opened.set(self.opened.get() + 1);
+            Ok(FakeRegistryKey(Rc::clone(&self.values), access))
+        }
+    }
+
+    impl RegistryKey for FakeRegistryKey {
+        fn values(&self) -> Result<Vec<RegistryValue>, RegistryError> {
+            Ok(self.0.borrow().iter().map(|(name, data)| RegistryValue { name: name.clone(), ty: Type::Bytes, data: data.clone() }).collect())
+        }
+
+        fn read_value(&self, name: &str) -> Result<Vec<u8>, RegistryError> {
+            self.0.borrow().iter().find(|(value_name, _)| value_name == name).map(|(_, data)| data.clone())
+                .ok_or_else(|| RegistryError { message: format!("{name} not found"), transient: false })
+        }
+
+        fn write_value(&self, name: &str, data: &[u8]) -> Result<(), RegistryError> {
+            if self.1 != KeyAccess::ReadWrite {
+                return Err(RegistryError { message: "access is denied".to_string(), transient: false });
+            }
+
+            let mut values = self.0.borrow_mut();
+            match values.iter_mut().find(|(value_name, _)| value_name == name) {
+                Some((_, value)) => *value = data.to_vec(),
+                None => values.push((name.to_string(), data.to_vec())),
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn mage_arena_flag_opens_key_once() {
+        let hive = FakeRegistryHive {
+            key_path: r"Software\WOW6432Node\jrsjams\MageArena".to_string(),
+            values: Rc::new(RefCell::new(vec![
+                ("unity.player_sessionid_h1".to_string(), b"1".to_vec()),
+                ("flagGrid_h2".to_string(), b"0,00:0,00\0".to_vec()),
+            ])),
+            opened: Cell::new(0),
+        };
+
+        let flag = MageArenaFlag::open(&hive, MAGE_ARENA_KEY, KeyAccess::ReadWrite).unwrap();
+        assert_eq!(flag.key_path(), hive.key_path);
+        assert_eq!(flag.flag_grid_key(), "flagGrid_h2");
+
+        assert_eq!(flag.read().unwrap(), b"0,00:0,00\0");
+        flag.write(b"0,50:0,50\0").unwrap();
+        assert_eq!(flag.read().unwrap(), b"0,50:0,50\0");
+        assert_eq!(flag.read_raw().unwrap(), b"0,50:0,50\0");
+        assert_eq!(hive.opened.get(), 1);
+    }
+
+    #[test]
+    fn mage_arena_flag_opened_for_reading_is_not_written() {
+        let hive = FakeRegistryHive {
+            key_path: MAGE_ARENA_KEY.to_string(),
+            values: Rc::new(RefCell::new(vec![("flagGrid_h2".to_string(), b"0,00:0,00\0".to_vec())])),
+            opened: Cell::new(0),
+        };
+
+        let flag = MageArenaFlag::open(&hive, MAGE_ARENA_KEY, KeyAccess::Read).unwrap();
+        assert_eq!(flag.read().unwrap(), b"0,00:0,00\0");
+        assert!(matches!(flag.write(b"0,50:0,50\0"), Err(AccessFailure(_))));
+        assert_eq!(flag.read().unwrap(), b"0,00:0,00\0");
+    }
+
+    #[test]
+    fn settings_key_values_are_listed_without_a_flag_grid_value() {
+        let hive = FakeRegistryHive {
+            key_path: MAGE_ARENA_KEY.to_string(),
+            values: Rc::new(RefCell::new(vec![
+                ("unity.player_session_count_h2".to_string(), b"3\0".to_vec()),
+                ("Screenmanager Resolution Width_h182942802".to_string(), vec![0x80, 0x07, 0x00, 0x00]),
+            ])),
+            opened: Cell::new(0),
+        };
+
+        assert!(MageArenaFlag::open(&hive, MAGE_ARENA_KEY, KeyAccess::Read).is_err());
+
+        let (key_path, key) = open_settings_key(&hive, MAGE_ARENA_KEY, KeyAccess::Read).unwrap();
+        assert_eq!(key_path, MAGE_ARENA_KEY);
+
+        let values = key.values().unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0].type_name(), "REG_BINARY");
+        assert_eq!(values[0].text().as_deref(), Some("3"));
+        assert_eq!(values[1].data.len(), 4);
+        assert_eq!(values[1].text(), None);
+
+        let string_value = RegistryValue { name: "name".to_string(), ty: Type::String, data: vec![b'h', 0, b'i', 0, 0, 0] };
+        assert_eq!(string_value.text().as_deref(), Some("hi"));
+    }
+
+    /// A [FlagStore] whose reads fail with the given error a number of times before succeeding.
+    struct FlakyFlagStore {
+        failures: Cell<u32>,
+        error: fn() -> Error,
+        attempts: Cell<u32>,
+    }
+
+    impl FlagStore for FlakyFlagStore {
+        fn read_raw(&self) -> Result<Vec<u8>, Error> {
+            self.attempts.set(self.attempts.get() + 1);
+            if self.failures.get() > 0 {
+                self.failures.set(self.failures.get() - 1);
+                return Err((self.error)());
+            }
+
+            Ok(b"0,00:0,00\0".to_vec())
+        }
+
+        fn write_raw(&self, _data: &[u8]) -> Result<(), Error> {
+            unreachable!("the retry test only reads")
+        }
+    }
+
+    #[test]
+    fn retry_retries_only_transient_failures() {
+        let flaky = |failures, error| FlakyFlagStore { failures: Cell::new(failures), error, attempts: Cell::new(0) };
+
+        let storage = flaky(2, || Busy("the key is in use".to_string()));
+        assert_eq!(retry(3, || storage.read_raw()).unwrap(), b"0,00:0,00\0");
+        assert_eq!(storage.attempts.get(), 3);
+
+        let storage = flaky(2, || Busy("the key is in use".to_string()));
+        assert!(matches!(retry(1, || storage.read_raw()), Err(Busy(_))));
+        assert_eq!(storage.attempts.get(), 2);
+
+        let storage = flaky(2, || AccessFailure("the key doesn't exist".to_string()));
+        assert!(matches!(retry(3, || storage.read_raw()), Err(AccessFailure(_))));
+        assert_eq!(storage.attempts.get(), 1);
+    }
+
+    #[test]
+    fn watched_file_changed_only_for_settled_changes_to_the_file() {
+        let watched_file = Path::new("/flags/flag.bmp");
+        let event = |path: &str, kind| DebouncedEvent::new(PathBuf::from(path), kind);
+
+        assert!(watched_file_changed(&[event("/flags/flag.bmp", DebouncedEventKind::Any)], watched_file));
+        assert!(watched_file_changed(&[event("/flags/other.bmp", DebouncedEventKind::Any), event("/flags/flag.bmp", DebouncedEventKind::Any)], watched_file));
+
+        // Changes that are still ongoing, and changes to other files in the directory, are skipped.
+        assert!(!watched_file_changed(&[event("/flags/flag.bmp", DebouncedEventKind::AnyContinuous)], watched_file));
+        assert!(!watched_file_changed(&[event("/flags/flag.bmp~", DebouncedEventKind::Any)], watched_file));
+        assert!(!watched_file_changed(&[], watched_file));
+    }
+
+    #[test]
+    fn select_flag_grid_key_lists_present_values_when_missing() {
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+
+        let flag_grid_key = select_flag_grid_key(names(&["unity.player_sessionid_h1", "flagGrid_h2", "flagGrid_h3"]), MAGE_ARENA_KEY).unwrap();
+        assert_eq!(flag_grid_key, "flagGrid_h2");
+
+        let err = select_flag_grid_key(names(&["unity.player_sessionid_h1", "flagGridV2_h2"]), MAGE_ARENA_KEY).unwrap_err().to_string();
+        assert!(err.contains(MAGE_ARENA_KEY), "{err}");
+        assert!(err.contains("values present: unity.player_sessionid_h1, flagGridV2_h2"), "{err}");
+    }
+
+    #[test]
+    fn wow6432_node_key_path_redirects_software_keys() {
+        assert_eq!(wow6432_node_key_path(MAGE_ARENA_KEY).as_deref(), Some(r"Software\WOW6432Node\jrsjams\MageArena"));
+        assert_eq!(wow6432_node_key_path(r"SOFTWARE\Modded\MageArena").as_deref(), Some(r"SOFTWARE\WOW6432Node\Modded\MageArena"));
+        assert_eq!(wow6432_node_key_path(r"Software\WOW6432Node\jrsjams\MageArena"), None);
+        assert_eq!(wow6432_node_key_path(r"Environment"), None);
+    }
+
+    #[test]
+    fn read_flag_coordinates_reads_from_storage() {
+        let dimensions = FlagDimensions::default();
+        let coordinates: Vec<(f64, f64)> = (0..dimensions.pixel_count())
+            .map(|i| ((i % dimensions.width) as f64 / 100.0, (i / dimensions.width) as f64 / 100.0))
+            .collect();
+
+        let storage = FakeFlagStore(RefCell::new(encode_flag_records(&coordinates, &dimensions).unwrap().into_bytes()));
+        assert_eq!(read_flag_coordinates(&storage, &dimensions).unwrap(), coordinates);
+    }
+
+    #[test]
+    fn write_flag_then_read_flag_round_trips_through_storage() {
+        let dimensions = FlagDimensions::new(2, 2, MAGE_ARENA_FLAG_PIXEL_SIZE).unwrap();
+        let colors = |colors: &[(u8, u8, u8)]| colors.iter().map(|&(red, green, blue)| Pixel24Bit { red, green, blue }).collect::<Vec<_>>();
+        let palette = Bitmap::new_from_pixels(2, 2, colors(&[(255, 0, 0), (0, 255, 0), (0, 0, 255), (255, 255, 255)])).unwrap();
+        let flag = Bitmap::new_from_pixels(2, 2, colors(&[(0, 0, 250), (250, 0, 0), (250, 250, 250), (0, 250, 0)])).unwrap();
+
+        let directory = std::env::temp_dir().join(format!("write_flag_round_trip_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let (palette_file, input_file, output_file) = (directory.join("palette.bmp"), directory.join("input.bmp"), directory.join("output.bmp"));
+        write_bitmap_file(&palette, &palette_file).unwrap();
+        write_bitmap_file(&flag, &input_file).unwrap();
+
+        let storage = FakeFlagStore(RefCell::new(vec![]));
+        let options = WriteOptions { force: true, ..WriteOptions::default() };
+        let result = write_flag(&storage, palette_file.clone(), input_file, &dimensions, &options)
+            .and_then(|()| read_flag(&storage, palette_file, output_file.clone(), &dimensions, &ReadOptions::default()))
+            .and_then(|()| read_bitmap_file(&output_file));
+        fs::remove_dir_all(&directory).unwrap();
+
+        // Each flag pixel is read back as the closest palette color.
+        let channels: Vec<(u8, u8, u8)> = result.unwrap().pixels.iter().map(|pixel| (pixel.red, pixel.green, pixel.blue)).collect();
+        assert_eq!(channels, vec![(0, 0, 255), (255, 0, 0), (255, 255, 255), (0, 255, 0)]);
+    }
+
+    /// Decode a PNG written by [Bitmap::to_png] (which stores the image data uncompressed) into
+    /// its width, height and the channels of each pixel from the top row down.
+    fn decode_stored_png(png: &[u8]) -> (u32, u32, Vec<(u8, u8, u8)>) {
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+
+        let (mut header, mut zlib) = (vec![], vec![]);
+        let mut chunks = &png[8..];
+        while !chunks.is_empty() {
+            let length = u32::from_be_bytes(chunks[..4].try_into().unwrap()) as usize;
+            let (chunk_type, data) = (&chunks[4..8], &chunks[8..8 + length]);
+            match chunk_type {
+                b"IHDR" => header = data.to_vec(),
+                b"IDAT" => zlib.extend_from_slice(data),
+                _ => {},
+            }
+            chunks = &chunks[12 + length..];
+        }
+
+        // Skip the zlib header, and join the stored deflate blocks.
+        let mut scanlines = vec![];
+        let mut blocks = &zlib[2..];
+        loop {
+            let length = u16::from_le_bytes([blocks[1], blocks[2]]) as usize;
+            scanlines.extend_from_slice(&blocks[5..5 + length]);
+            if blocks[0] & 1 == 1 {
+                break;
+            }
+            blocks = &blocks[5 + length..];
+        }
+
+        let width = u32::from_be_bytes(header[..4].try_into().unwrap());
+        let height = u32::from_be_bytes(header[4..8].try_into().unwrap());
+        let pixels = scanlines.chunks(1 + width as usize * 3)
+            .flat_map(|row| {
+                assert_eq!(row[0], 0, "every row should be unfiltered");
+                row[1..].chunks(3).map(|pixel| (pixel[0], pixel[1], pixel[2])).collect::<Vec<_>>()
             })
-        }).map(|index| flag.pixels[index]).collect();
+            .collect();
+        (width, height, pixels)
+    }
 
-    let mut bad_pixels: Vec<Error> = vec![];
-    let pixels: Vec<String> = pixels.iter()
-        .map(|pixel| {
-            let Some(closest_pixel) = palette.find_pixel_by_closest_match(pixel) else {
-                return Err(UnexpectedValue("failed to find match for pixel".to_string()));
-            };
+    #[test]
+    fn read_flag_writes_png_from_top_row_down() {
+        let dimensions = FlagDimensions::new(2, 2, MAGE_ARENA_FLAG_PIXEL_SIZE).unwrap();
 
-            Ok(closest_pixel)
-        })
-        .filter_map(|pixel| pixel.map_err(|err| bad_pixels.push(err)).ok())
-        .enumerate()
-        .map(|(i, (x, y))| {
-            let trailing_character = if i == pixel_count - 1 {
-                '\0'
-            } else {
-                ','
-            };
+        // A 2x2 palette of red and green (bottom row), then blue and white (top row), with each
+        // pixel stored as blue, green, red, and each row padded to 4 bytes.
+        let mut palette_bytes = Bitmap::new_from_pixels(2, 2, vec![Pixel24Bit { red: 0, green: 0, blue: 0 }; 4]).unwrap().to_bytes();
+        palette_bytes[54..70].copy_from_slice(&[
+            0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0, 0,
+            0xFF, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0, 0,
+        ]);
 
-            format!("{:.2}:{:.2}{}", f64::from(x) / palette_width, f64::from(y) / palette_height, trailing_character)
-        })
-        .collect();
+        let coordinates = [(0.0, 0.0), (0.5, 0.5), (0.5, 0.0), (0.0, 0.5)];
+        let storage = FakeFlagStore(RefCell::new(encode_flag_records(&coordinates, &dimensions).unwrap().into_bytes()));
 
-    if !bad_pixels.is_empty() {
-        return Err(UnexpectedValue(format!(
-            "error mapping pixels\n\n{}",
-            bad_pixels.iter().map(|err| err.to_string()).collect::<Vec<String>>().join("\n")
-        )));
+        let directory = std::env::temp_dir().join(format!("read_flag_png_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let (palette_file, output_file) = (directory.join("palette.bmp"), directory.join("flag.PNG"));
+        fs::write(&palette_file, palette_bytes).unwrap();
+
+        let result = read_flag(&storage, palette_file, output_file.clone(), &dimensions, &ReadOptions::default())
+            .map(|()| fs::read(&output_file).unwrap());
+        fs::remove_dir_all(&directory).unwrap();
+
+        // The flag is red and white (bottom row), then green and blue (top row), and the PNG rows
+        // run from the top of the image down, with each pixel as red, green, blue.
+        let expected = vec![(0, 255, 0), (0, 0, 255), (255, 0, 0), (255, 255, 255)];
+        assert_eq!(decode_stored_png(&result.unwrap()), (2, 2, expected));
     }
 
-    write_raw_flag_data(pixels.join("").as_bytes())
+    #[test]
+    fn write_flag_csv_writes_records_with_stored_separators() {
+        let dimensions = FlagDimensions::new(2, 2, MAGE_ARENA_FLAG_PIXEL_SIZE).unwrap();
+        let storage = FakeFlagStore(RefCell::new(b"0,00:0,00;0,00:0,00;0,00:0,00;0,00:0,00\0".to_vec()));
+
+        let input_file = std::env::temp_dir().join(format!("write_flag_csv_{}.csv", std::process::id()));
+        fs::write(&input_file, "index,x,y\n0,0,0\n1,0.5,0\n2,0.5,0.5\n3,0,0.5\n").unwrap();
+
+        let options = WriteOptions { force: true, ..WriteOptions::default() };
+        let result = write_flag_csv(&storage, input_file.clone(), &dimensions, &options);
+        fs::remove_file(&input_file).unwrap();
+
+        result.unwrap();
+        assert_eq!(storage.0.borrow().as_slice(), b"0,00:0,00;0,50:0,50;0,50:0,00;0,00:0,50\0");
+    }
+
+    #[test]
+    fn write_flag_csv_to_opened_flag_opens_key_once() {
+        let dimensions = FlagDimensions::new(2, 2, MAGE_ARENA_FLAG_PIXEL_SIZE).unwrap();
+        let hive = FakeRegistryHive {
+            key_path: MAGE_ARENA_KEY.to_string(),
+            values: Rc::new(RefCell::new(vec![("flagGrid_h2".to_string(), b"0,00:0,00;0,00:0,00;0,00:0,00;0,00:0,00\0".to_vec())])),
+            opened: Cell::new(0),
+        };
+
+        let directory = std::env::temp_dir().join(format!("write_flag_csv_opened_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let input_file = directory.join("flag.csv");
+        fs::write(&input_file, "index,x,y\n0,0,0\n1,0.5,0\n2,0.5,0.5\n3,0,0.5\n").unwrap();
+
+        // The stored separators are resolved, the flag backed up and the new flag written, all
+        // through the one open key.
+        let flag = MageArenaFlag::open(&hive, MAGE_ARENA_KEY, KeyAccess::ReadWrite).unwrap();
+        let options = WriteOptions { force: true, backup_directory: Some(directory.join("backups")), ..WriteOptions::default() };
+        let result = write_flag_csv(&flag, input_file, &dimensions, &options)
+            .and_then(|()| find_latest_flag_backup(&directory.join("backups")))
+            .map(|backup_file| fs::read(backup_file).unwrap());
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(result.unwrap(), b"0,00:0,00;0,00:0,00;0,00:0,00;0,00:0,00\0");
+        assert_eq!(flag.read().unwrap(), b"0,00:0,00;0,50:0,50;0,50:0,00;0,00:0,50\0");
+        assert_eq!(hive.opened.get(), 1);
+    }
+
+    #[test]
+    fn differing_pixels_pairs_pixels_by_position() {
+        let first = Bitmap::new_from_pixels(2, 2, vec![
+            Pixel24Bit::from_rgb(10, 10, 10),
+            Pixel24Bit::from_rgb(20, 20, 20),
+            Pixel24Bit::from_rgb(30, 30, 30),
+            Pixel24Bit::from_rgb(40, 40, 40),
+        ]).unwrap();
+
+        // The same image stored bottom-up, but with the first pixel too blue.
+        let mut second = first.map_pixels(|x, y, pixel| if (x, y) == (0, 0) { Pixel24Bit::from_rgb(10, 10, 15) } else { *pixel });
+        second.set_top_down(!second.is_top_down());
+
+        assert_eq!(differing_pixels(&first, second).unwrap(), vec![(0, [0, 0, 5])]);
+
+        let smaller = Bitmap::new_from_pixels(1, 2, vec![Pixel24Bit::from_rgb(0, 0, 0); 2]).unwrap();
+        assert!(differing_pixels(&first, smaller).is_err());
+    }
+
+    #[test]
+    fn render_flag_preview_uses_true_rgb_colors() {
+        // A red pixel and a blue pixel, as their bytes are stored in a bitmap file.
+        let pixels = [Pixel24Bit::try_from(&[0u8, 0, 255][..]).unwrap(), Pixel24Bit::try_from(&[255u8, 0, 0][..]).unwrap()];
+        let dimensions = FlagDimensions::new(1, 2, MAGE_ARENA_FLAG_PIXEL_SIZE).unwrap();
+
+        assert_eq!(render_flag_preview(&pixels, &dimensions), "\x1b[38;2;255;0;0m\x1b[48;2;0;0;255m\u{2580}\x1b[0m\n");
+    }
 }
@@ -1,17 +1,167 @@
+#[macro_use]
+extern crate mage_arena_flag;
+
 use std::path::PathBuf;
-use clap::{Parser, Subcommand};
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use bitmap_rs::{GradientDirection, Pixel24Bit};
+use mage_arena_flag::{error, flag, logging, preprocess};
+use crate::config::Config;
 use crate::error::Error;
+use crate::flag::{ColorMetric, CoordinateRounding, EncodeOptions, FlagDimensions, PaletteSampling, RecordSeparators, MAGE_ARENA_FLAG_HEIGHT, MAGE_ARENA_FLAG_PIXEL_SIZE, MAGE_ARENA_FLAG_WIDTH};
+use crate::mage_arena::{KeyAccess, ReadOptions, RegistryFlagStore, WriteOptions};
+use crate::reg_file::RegFileFlagStore;
 
 mod mage_arena;
-mod error;
+mod batch;
+mod config;
+mod palette;
+mod process;
+mod reg_file;
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None, arg_required_else_help = true)]
+#[command(version, about, long_about = None, arg_required_else_help = true, after_help = AFTER_HELP)]
 struct Cli {
+    /// Print more progress information (may be repeated).
+    #[clap(short, long, global = true, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Don't print warnings or progress information.
+    #[clap(short, long, global = true)]
+    quiet: bool,
+
+    /// The Mage Arena settings key, relative to HKEY_CURRENT_USER (for modded or region-specific
+    /// installs).
+    #[clap(long, global = true, default_value = mage_arena::MAGE_ARENA_KEY)]
+    registry_key: String,
+
+    /// The number of times to retry accessing the registry while the settings key is in use (e.g.,
+    /// by the game), waiting twice as long before each retry.
+    #[clap(long, global = true, default_value_t = 3)]
+    retries: u32,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
+/// The help text shown after the list of commands.
+const AFTER_HELP: &str = "Options that aren't given on the command line are read from mageflag.toml, in the current \
+directory or the user config directory, if it exists. Switches can be turned off with =false (e.g., --dry-run=false) to \
+override the config file.";
+
+/// Options for the dimensions and format of the flag grid, in case they change in a future game
+/// version (or differ between game builds).
+#[derive(Args, Debug)]
+struct FlagDimensionArgs {
+    /// The width of the flag in pixels.
+    #[clap(long, default_value_t = MAGE_ARENA_FLAG_WIDTH as usize)]
+    flag_width: usize,
+
+    /// The height of the flag in pixels.
+    #[clap(long, default_value_t = MAGE_ARENA_FLAG_HEIGHT as usize)]
+    flag_height: usize,
+
+    /// The number of bytes used to represent each pixel in the flag data.
+    #[clap(long, default_value_t = MAGE_ARENA_FLAG_PIXEL_SIZE)]
+    pixel_size: usize,
+
+    /// The decimal separator and record delimiter of the flag data, which depend on the locale
+    /// of the game build.
+    #[clap(long, value_enum, default_value_t = RecordSeparators::Auto)]
+    separators: RecordSeparators,
+
+    /// Require the last pixel of the flag data to end with a null, as the game writes it. Set to
+    /// false to also accept flag data whose last pixel ends with the record delimiter.
+    #[clap(long, default_value_t = true, action = clap::ArgAction::Set)]
+    strict_trailing: bool,
+}
+
+impl FlagDimensionArgs {
+    fn to_dimensions(&self) -> Result<FlagDimensions, Error> {
+        Ok(FlagDimensions {
+            separators: self.separators,
+            strict_trailing: self.strict_trailing,
+            ..FlagDimensions::new(self.flag_width, self.flag_height, self.pixel_size)?
+        })
+    }
+}
+
+/// Options for how `write` and `watch` map the image to the palette.
+#[derive(Args, Debug)]
+struct EncodeArgs {
+    /// Preprocessing steps to apply to the image, in order, before it is matched to the
+    /// palette (e.g., `brightness=20,gamma=2.2,dither=floyd-steinberg`).
+    #[clap(long)]
+    preprocess: Option<String>,
+
+    /// The metric used to find the closest palette color to each pixel.
+    #[clap(long, value_enum, default_value_t = ColorMetric::Euclidean)]
+    metric: ColorMetric,
+
+    /// Mirror the palette vertically, for a palette authored the other way up. By default, a
+    /// y coordinate of 0.0 is the first row stored in the palette file, which is the bottom row
+    /// of a (standard) bottom-up bitmap.
+    #[clap(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, action = clap::ArgAction::Set)]
+    flip_y: bool,
+
+    /// When several palette cells match a pixel equally well, choose the one nearest to this
+    /// normalized palette coordinate (e.g., `0.0,0.0`), instead of the first in the palette.
+    #[clap(long, value_name = "X,Y", value_parser = parse_palette_coordinate)]
+    tie_break: Option<(f64, f64)>,
+
+    /// Crop away the uniform border around the artwork (the color of its first pixel), and
+    /// scale what's left to fit the flag, centered and padded with the border color.
+    #[clap(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, action = clap::ArgAction::Set)]
+    auto_trim: bool,
+}
+
+impl EncodeArgs {
+    /// Fill in how the image is mapped to the palette in the write options.
+    fn to_write_options(&self, options: WriteOptions) -> Result<WriteOptions, Error> {
+        Ok(WriteOptions {
+            flip_y: self.flip_y,
+            encode: EncodeOptions {
+                preprocess: self.preprocess.as_deref().map(preprocess::parse_preprocess_steps).transpose()?.unwrap_or_default(),
+                metric: self.metric,
+                tie_break: self.tie_break,
+                auto_trim: self.auto_trim,
+            },
+            ..options
+        })
+    }
+}
+
+/// Parse a normalized palette coordinate given as `x,y` (e.g., `0.0,1.0`).
+fn parse_palette_coordinate(value: &str) -> Result<(f64, f64), String> {
+    let parse = |coordinate: &str| coordinate.trim().parse::<f64>()
+        .ok()
+        .filter(|coordinate| (0.0..=1.0).contains(coordinate))
+        .ok_or_else(|| format!("{coordinate} is not a number from 0.0 to 1.0"));
+
+    match value.split_once(',') {
+        Some((x, y)) => Ok((parse(x)?, parse(y)?)),
+        None => Err("expected a coordinate of the form x,y (e.g., 0.0,1.0)".to_string()),
+    }
+}
+
+/// Parse a color given as a hex code of the form `#RRGGBB`.
+fn parse_color(value: &str) -> Result<Pixel24Bit, String> {
+    if value.len() != 7 || !value.starts_with('#') || !value[1..].chars().all(|digit| digit.is_ascii_hexdigit()) {
+        return Err(format!("{value} is not a color of the form #RRGGBB"));
+    }
+
+    bitmap_rs::hex_to_rgb(value).map_err(|err| err.to_string())
+}
+
+/// Parse the direction of a gradient: `horizontal`, `vertical` or `diagonal`.
+fn parse_gradient_direction(value: &str) -> Result<GradientDirection, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "horizontal" => Ok(GradientDirection::Horizontal),
+        "vertical" => Ok(GradientDirection::Vertical),
+        "diagonal" => Ok(GradientDirection::Diagonal),
+        _ => Err(format!("{value} is not a gradient direction (expected horizontal, vertical or diagonal)")),
+    }
+}
+
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Read the current Mage Arena flag from storage.
@@ -20,9 +170,47 @@ enum Commands {
         #[clap(short, long, default_value = "palette.bmp")]
         palette_file: PathBuf,
 
-        /// The file to read the flag data into.
+        /// The file to read the flag data into (as a PNG if it ends in .png, or a bitmap
+        /// otherwise), or - to write the bitmap to standard output.
         #[clap(short, long, default_value = "flag.bmp")]
         output_file: PathBuf,
+
+        /// Read the flag from a .reg file exported with regedit, instead of from the registry.
+        #[clap(long)]
+        reg_file: Option<PathBuf>,
+
+        /// How the palette color of each flag pixel is sampled.
+        #[clap(long, value_enum, default_value_t = PaletteSampling::Nearest)]
+        sample: PaletteSampling,
+
+        /// How each palette coordinate is rounded to a palette cell when sampling the nearest
+        /// cell: floor (the cell containing it), round (the cell whose edge is nearest), or
+        /// nearest-center (the cell whose center is nearest, with 0 and 1 at the outer centers).
+        #[clap(long, value_enum, default_value_t = CoordinateRounding::Floor)]
+        coord_rounding: CoordinateRounding,
+
+        /// Average each palette sample over the n x n palette cells around it (clamped to the edges
+        /// of the palette), to smooth the flag when the palette is small.
+        #[clap(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        supersample: u32,
+
+        /// Mirror the palette vertically, for a palette authored the other way up. By default, a
+        /// y coordinate of 0.0 is the first row stored in the palette file, which is the bottom row
+        /// of a (standard) bottom-up bitmap.
+        #[clap(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, action = clap::ArgAction::Set)]
+        flip_y: bool,
+
+        /// Print statistics about how the flag samples the palette.
+        #[clap(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, action = clap::ArgAction::Set)]
+        stats: bool,
+
+        /// Also write the normalized palette coordinate of each flag pixel to this file, as CSV
+        /// rows of `index,x,y` in the same (row) order as the pixels of the output bitmap.
+        #[clap(long)]
+        csv: Option<PathBuf>,
+
+        #[command(flatten)]
+        dimensions: FlagDimensionArgs,
     },
 
     /// Write the image into the Mage Arena flag storage.
@@ -31,23 +219,367 @@ enum Commands {
         #[clap(short, long, default_value = "palette.bmp")]
         palette_file: PathBuf,
         
-        /// The file to read the flag data from.
+        /// The file to read the flag data from, or - to read the bitmap from standard input.
         #[clap(short, long, default_value = "custom_flag.bmp")]
         input_file: PathBuf,
-    }
+
+        /// Preview the flag as it will appear in-game, and ask for confirmation before writing it.
+        #[clap(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, action = clap::ArgAction::Set)]
+        preview: bool,
+
+        /// Map the image to the palette and report any bad pixels, without writing the flag.
+        #[clap(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, action = clap::ArgAction::Set)]
+        dry_run: bool,
+
+        /// Don't back up the current flag before overwriting it.
+        #[clap(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, action = clap::ArgAction::Set)]
+        no_backup: bool,
+
+        /// The directory to back up the current flag to before overwriting it.
+        #[clap(long, default_value = "backups")]
+        backup_dir: PathBuf,
+
+        /// Write the flag even if Mage Arena is running (which may overwrite the flag on exit).
+        #[clap(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, action = clap::ArgAction::Set)]
+        force: bool,
+
+        #[command(flatten)]
+        encode: EncodeArgs,
+
+        #[command(flatten)]
+        dimensions: FlagDimensionArgs,
+    },
+
+    /// Write a flag from a CSV of palette coordinates into the Mage Arena flag storage.
+    ///
+    /// The CSV has a row of `index,x,y` for each flag pixel (in the format written by `read
+    /// --csv`), where the index counts the pixels row by row and x and y are normalized palette
+    /// coordinates from 0.0 to 1.0.
+    WriteCsv {
+        /// The CSV file to read the palette coordinates from, or - to read from standard input.
+        #[clap(short, long)]
+        input: PathBuf,
+
+        /// Read the CSV and report any bad rows, without writing the flag.
+        #[clap(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, action = clap::ArgAction::Set)]
+        dry_run: bool,
+
+        /// Don't back up the current flag before overwriting it.
+        #[clap(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, action = clap::ArgAction::Set)]
+        no_backup: bool,
+
+        /// The directory to back up the current flag to before overwriting it.
+        #[clap(long, default_value = "backups")]
+        backup_dir: PathBuf,
+
+        /// Write the flag even if Mage Arena is running (which may overwrite the flag on exit).
+        #[clap(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, action = clap::ArgAction::Set)]
+        force: bool,
+
+        #[command(flatten)]
+        dimensions: FlagDimensionArgs,
+    },
+
+    /// Write the flag from a bitmap image, and write it again each time the image is saved, until
+    /// interrupted with Ctrl-C.
+    Watch {
+        /// The bitmap image containing the palette.
+        #[clap(short, long, default_value = "palette.bmp")]
+        palette_file: PathBuf,
+
+        /// The bitmap image to watch for changes.
+        #[clap(short, long, default_value = "custom_flag.bmp")]
+        input_file: PathBuf,
+
+        /// Don't back up the current flag before watching starts.
+        #[clap(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, action = clap::ArgAction::Set)]
+        no_backup: bool,
+
+        /// The directory to back up the current flag to before watching starts.
+        #[clap(long, default_value = "backups")]
+        backup_dir: PathBuf,
+
+        /// Write the flag even if Mage Arena is running (which may overwrite the flag on exit).
+        #[clap(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, action = clap::ArgAction::Set)]
+        force: bool,
+
+        #[command(flatten)]
+        encode: EncodeArgs,
+
+        #[command(flatten)]
+        dimensions: FlagDimensionArgs,
+    },
+
+    /// Restore the most recent flag backup, and remove it from the backup directory.
+    Undo {
+        /// The directory containing the flag backups.
+        #[clap(long, default_value = "backups")]
+        backup_dir: PathBuf,
+
+        #[command(flatten)]
+        dimensions: FlagDimensionArgs,
+    },
+
+    /// Generate a palette image from the most representative colors of any bitmap image.
+    ///
+    /// The palette is a 100x100 pixel grid of equally-sized swatches, filled left-to-right then
+    /// top-to-bottom, and can be used as the palette file for `read` and `write`.
+    GeneratePalette {
+        /// The bitmap image to extract the colors from.
+        #[clap(short, long)]
+        input: PathBuf,
+
+        /// The file to write the palette image to.
+        #[clap(short, long, default_value = "palette.bmp")]
+        output: PathBuf,
+
+        /// The number of colors in the palette.
+        #[clap(short, long, default_value_t = 64)]
+        colors: usize,
+
+        /// Choose the initial colors at random (with k-means++) from this seed, instead of always
+        /// starting from the most common color. The same seed always gives the same palette.
+        #[clap(long)]
+        seed: Option<u64>,
+    },
+
+    /// Generate a palette that blends between two colors, for quick testing without exporting a
+    /// palette from an image editor.
+    GenerateGradient {
+        /// The file to write the palette image to.
+        #[clap(short, long, default_value = "palette.bmp")]
+        output: PathBuf,
+
+        /// The color at the start of the gradient, as #RRGGBB.
+        #[clap(long, value_parser = parse_color)]
+        start: Pixel24Bit,
+
+        /// The color at the end of the gradient, as #RRGGBB.
+        #[clap(long, value_parser = parse_color)]
+        end: Pixel24Bit,
+
+        /// The direction to blend in: horizontal (left to right), vertical (top to bottom), or
+        /// diagonal (top-left to bottom-right).
+        #[clap(long, default_value = "horizontal", value_parser = parse_gradient_direction)]
+        direction: GradientDirection,
+
+        /// The width of the palette in pixels.
+        #[clap(long, default_value_t = palette::GENERATED_PALETTE_SIZE)]
+        width: u32,
+
+        /// The height of the palette in pixels.
+        #[clap(long, default_value_t = palette::GENERATED_PALETTE_SIZE)]
+        height: u32,
+    },
+
+    /// Generate a palette of the full color spectrum, with the hue changing across it and the
+    /// colors fading from white at the top to black at the bottom, which can match almost any color.
+    GenerateHsvPalette {
+        /// The file to write the palette image to.
+        #[clap(short, long, default_value = "palette.bmp")]
+        output: PathBuf,
+
+        /// The width of the palette in pixels.
+        #[clap(long, default_value_t = palette::GENERATED_PALETTE_SIZE)]
+        width: u32,
+
+        /// The height of the palette in pixels.
+        #[clap(long, default_value_t = palette::GENERATED_PALETTE_SIZE)]
+        height: u32,
+    },
+
+    /// Map every bitmap image in a directory to the palette, saving the flag data of each to a file
+    /// without writing to the registry.
+    ///
+    /// Only .bmp images are converted. PNG images aren't supported, so they are skipped with a
+    /// warning.
+    Batch {
+        /// The bitmap image containing the palette.
+        #[clap(short, long, default_value = "palette.bmp")]
+        palette_file: PathBuf,
+
+        /// The directory containing the bitmap (.bmp) images to convert.
+        #[clap(short, long)]
+        input_dir: PathBuf,
+
+        /// The directory to write the flag data files to.
+        #[clap(short, long)]
+        output_dir: PathBuf,
+
+        #[command(flatten)]
+        dimensions: FlagDimensionArgs,
+    },
+
+    /// Export the current Mage Arena flag as a .reg file, which can be imported to set the flag.
+    ExportReg {
+        /// The .reg file to write the flag to.
+        #[clap(short, long, default_value = "flag.reg")]
+        output: PathBuf,
+    },
+
+    /// Export a map of the palette cells that the current flag samples, as an image in which each
+    /// sampled cell is white (e.g., to recover the palette layout that a flag expects).
+    PaletteMap {
+        /// The bitmap image to write the map to, or - to write it to standard output.
+        #[clap(short, long, default_value = "palette_map.bmp")]
+        output: PathBuf,
+
+        /// Read the flag from a .reg file exported with regedit, instead of from the registry.
+        #[clap(long)]
+        reg_file: Option<PathBuf>,
+
+        #[command(flatten)]
+        dimensions: FlagDimensionArgs,
+    },
+
+    /// Report how closely an image survives being mapped to the palette and read back, to check
+    /// whether the palette is rich enough for the image before writing it.
+    Quality {
+        /// The bitmap image containing the palette.
+        #[clap(short, long, default_value = "palette.bmp")]
+        palette_file: PathBuf,
+
+        /// The bitmap image to check, or - to read it from standard input.
+        #[clap(short, long, default_value = "custom_flag.bmp")]
+        input_file: PathBuf,
+
+        /// The largest difference between an original and a mapped color for the pixel to count as
+        /// preserved.
+        #[clap(short, long, default_value_t = 10.0)]
+        threshold: f64,
+
+        /// The metric used to find the closest palette color to each pixel.
+        #[clap(long, value_enum, default_value_t = ColorMetric::Euclidean)]
+        metric: ColorMetric,
+
+        #[command(flatten)]
+        dimensions: FlagDimensionArgs,
+    },
+
+    /// Print each distinct color of the palette with the normalized palette coordinate of its
+    /// first cell (as `x, y`), sorted by brightness, as a reference for writing coordinates by hand.
+    Swatches {
+        /// The bitmap image containing the palette.
+        #[clap(short, long, default_value = "palette.bmp")]
+        palette_file: PathBuf,
+    },
+
+    /// Compare two bitmap images pixel by pixel (e.g., an image and the flag read back from the
+    /// game), reporting how many pixels differ and the mean difference of each channel. With
+    /// --verbose, each differing pixel is listed with the difference of each channel.
+    Diff {
+        /// The first bitmap image, or - to read it from standard input.
+        first_file: PathBuf,
+
+        /// The second bitmap image.
+        second_file: PathBuf,
+    },
+
+    /// List every value under the Mage Arena settings key, with its type and size (and its text,
+    /// if it holds any), to diagnose why the flag can't be found.
+    Inspect,
 }
 
 fn main() -> Result<(), Error> {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    logging::set_verbosity(if cli.quiet { -1 } else { cli.verbose.min(i8::MAX as u8) as i8 });
+
+    // Fill in any options that weren't given on the command line from the config file.
+    if let Some(command) = &mut cli.command {
+        Config::load()?.apply(command, &matches);
+    }
+
+    let registry = RegistryFlagStore::new(cli.registry_key, cli.retries);
 
     match cli.command {
-        Some(Commands::Read { palette_file, output_file }) => {
-            mage_arena::read_flag(palette_file, output_file)?;
+        Some(Commands::Read { palette_file, output_file, reg_file, sample, coord_rounding, supersample, flip_y, stats, csv, dimensions }) => {
+            let dimensions = dimensions.to_dimensions()?;
+            let options = ReadOptions { sampling: sample, coord_rounding, flip_y, supersample, stats, csv_file: csv };
+            match reg_file {
+                Some(reg_file) => mage_arena::read_flag(&RegFileFlagStore::new(reg_file), palette_file, output_file, &dimensions, &options)?,
+                None => mage_arena::read_flag(&registry, palette_file, output_file, &dimensions, &options)?,
+            }
         },
         
-        Some(Commands::Write { palette_file, input_file }) => {
-            mage_arena::write_flag(palette_file, input_file)?;
-        }
+        Some(Commands::Write { palette_file, input_file, preview, dry_run, no_backup, backup_dir, force, encode, dimensions }) => {
+            let options = encode.to_write_options(WriteOptions {
+                preview,
+                dry_run,
+                backup_directory: if no_backup { None } else { Some(backup_dir) },
+                force,
+                ..WriteOptions::default()
+            })?;
+            mage_arena::write_flag(&registry.open(KeyAccess::ReadWrite)?, palette_file, input_file, &dimensions.to_dimensions()?, &options)?;
+        },
+
+        Some(Commands::WriteCsv { input, dry_run, no_backup, backup_dir, force, dimensions }) => {
+            let options = WriteOptions {
+                dry_run,
+                backup_directory: if no_backup { None } else { Some(backup_dir) },
+                force,
+                ..WriteOptions::default()
+            };
+            mage_arena::write_flag_csv(&registry.open(KeyAccess::ReadWrite)?, input, &dimensions.to_dimensions()?, &options)?;
+        },
+
+        Some(Commands::Watch { palette_file, input_file, no_backup, backup_dir, force, encode, dimensions }) => {
+            let options = encode.to_write_options(WriteOptions { force, ..WriteOptions::default() })?;
+            let backup_directory = if no_backup { None } else { Some(backup_dir.as_path()) };
+            mage_arena::watch_flag(&registry.open(KeyAccess::ReadWrite)?, palette_file, input_file, &dimensions.to_dimensions()?, backup_directory, &options)?;
+        },
+
+        Some(Commands::Undo { backup_dir, dimensions }) => {
+            let backup_file = mage_arena::undo_flag(&registry.open(KeyAccess::ReadWrite)?, &backup_dir, &dimensions.to_dimensions()?)?;
+            println!("Restored the flag from {}", backup_file.display());
+        },
+
+        Some(Commands::GeneratePalette { input, output, colors, seed }) => {
+            palette::generate_palette(input, output, colors, seed)?;
+        },
+
+        Some(Commands::GenerateGradient { output, start, end, direction, width, height }) => {
+            palette::generate_gradient(output, width, height, start, end, direction)?;
+        },
+
+        Some(Commands::GenerateHsvPalette { output, width, height }) => {
+            palette::generate_hsv_palette(output, width, height)?;
+        },
+
+        Some(Commands::Batch { palette_file, input_dir, output_dir, dimensions }) => {
+            batch::batch_convert(palette_file, &input_dir, &output_dir, &dimensions.to_dimensions()?)?;
+        },
+
+        Some(Commands::ExportReg { output }) => {
+            let flag = registry.open(KeyAccess::Read)?;
+            reg_file::export_reg_file(&output, flag.key_path(), flag.flag_grid_key(), &flag.read()?)?;
+            info!("Exported the flag to {}", output.display());
+        },
+
+        Some(Commands::PaletteMap { output, reg_file, dimensions }) => {
+            let dimensions = dimensions.to_dimensions()?;
+            match reg_file {
+                Some(reg_file) => mage_arena::export_palette_map(&RegFileFlagStore::new(reg_file), &output, &dimensions)?,
+                None => mage_arena::export_palette_map(&registry, &output, &dimensions)?,
+            }
+        },
+
+        Some(Commands::Quality { palette_file, input_file, threshold, metric, dimensions }) => {
+            let options = EncodeOptions { metric, ..EncodeOptions::default() };
+            mage_arena::report_flag_quality(palette_file, input_file, &dimensions.to_dimensions()?, &options, threshold)?;
+        },
+
+        Some(Commands::Swatches { palette_file }) => {
+            mage_arena::print_palette_swatches(palette_file)?;
+        },
+
+        Some(Commands::Diff { first_file, second_file }) => {
+            mage_arena::diff_bitmaps(first_file, second_file)?;
+        },
+
+        Some(Commands::Inspect) => {
+            mage_arena::print_registry_values(&registry)?;
+        },
 
         None => {}
     }
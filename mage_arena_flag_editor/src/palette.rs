@@ -0,0 +1,125 @@
+use crate::error::Error;
+use crate::error::Error::{External, UnexpectedValue};
+use crate::mage_arena::{read_bitmap_file, write_bitmap_file};
+use bitmap_rs::{Bitmap, GradientDirection, Pixel24Bit};
+use std::path::PathBuf;
+
+/// The width and height of a generated palette, in pixels.
+///
+/// The flag stores each coordinate with two decimal places, so with one pixel per hundredth of the
+/// palette, every swatch boundary falls on a coordinate that the flag can represent exactly.
+pub const GENERATED_PALETTE_SIZE: u32 = 100;
+
+/// Get the smallest divisor of [GENERATED_PALETTE_SIZE] that is at least `minimum`.
+fn swatch_divisor(minimum: u32) -> Option<u32> {
+    (minimum.max(1)..=GENERATED_PALETTE_SIZE).find(|&divisor| GENERATED_PALETTE_SIZE.is_multiple_of(divisor))
+}
+
+/// Arrange the colors as a swatch grid in a [GENERATED_PALETTE_SIZE] pixel square bitmap.
+///
+/// The grid has the fewest columns (that evenly divide the palette width) needed to make it
+/// roughly square, and the fewest rows (that evenly divide the palette height) needed to fit the
+/// colors. Color `i` fills the swatch in column `i % columns` and row `i / columns`, counting from
+/// the top-left, so its top-left pixel is at the normalized coordinate
+/// `(column / columns, row / rows)`. Any swatches left over after the last color are filled with
+/// the first color, so that they don't introduce any colors of their own.
+pub fn build_swatch_grid(colors: &[Pixel24Bit]) -> Result<Bitmap<Pixel24Bit>, Error> {
+    let color_count = u32::try_from(colors.len()).unwrap_or(u32::MAX);
+    if color_count == 0 {
+        return Err(UnexpectedValue("a palette must contain at least one color".to_string()));
+    }
+
+    // Use at least the (rounded up) square root of the number of colors as the number of columns.
+    let minimum_columns = color_count.isqrt() + u32::from(color_count.isqrt().pow(2) < color_count);
+
+    let Some((columns, rows)) = swatch_divisor(minimum_columns)
+        .and_then(|columns| Some((columns, swatch_divisor(color_count.div_ceil(columns))?))) else {
+        return Err(UnexpectedValue(format!(
+            "a {size}x{size} palette can't fit {color_count} colors",
+            size = GENERATED_PALETTE_SIZE
+        )));
+    };
+
+    let swatch_width = GENERATED_PALETTE_SIZE / columns;
+    let swatch_height = GENERATED_PALETTE_SIZE / rows;
+
+    let pixels = (0..GENERATED_PALETTE_SIZE)
+        .flat_map(|y| (0..GENERATED_PALETTE_SIZE).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let swatch = (y / swatch_height) * columns + (x / swatch_width);
+            *colors.get(swatch as usize).unwrap_or(&colors[0])
+        })
+        .collect();
+
+    let size = GENERATED_PALETTE_SIZE as i32;
+    Bitmap::new_from_pixels(size, size, pixels)
+        .map_err(|err| External(format!("failed to create palette image: {err}")))
+}
+
+/// Extract a palette of `colors` colors from the input image with k-means clustering, and write
+/// it to the output file as a swatch grid (see [build_swatch_grid]) that can be used as the
+/// palette file for reading and writing flags.
+///
+/// With a `seed`, the clustering starts from centroids chosen at random by k-means++ (see
+/// [Bitmap::extract_palette_seeded]), so trying different seeds can give a better palette, while
+/// the same seed always gives the same palette.
+pub fn generate_palette(input_file: PathBuf, output_file: PathBuf, colors: usize, seed: Option<u64>) -> Result<(), Error> {
+    let image = read_bitmap_file(&input_file)?;
+
+    let palette = match seed {
+        Some(seed) => image.extract_palette_seeded(colors, seed),
+        None => image.extract_palette(colors),
+    }
+        .map_err(|err| External(format!("failed to extract palette: {err}")))?;
+    info!("Extracted {} colors from {}", palette.len(), input_file.display());
+
+    write_bitmap_file(&build_swatch_grid(&palette)?, &output_file)?;
+
+    info!("Wrote palette to {}", output_file.display());
+    Ok(())
+}
+
+/// Write a `width` x `height` palette that blends linearly from the `start` color to the `end`
+/// color in the given direction (see [Bitmap::gradient]) to the output file.
+pub fn generate_gradient(output_file: PathBuf, width: u32, height: u32, start: Pixel24Bit, end: Pixel24Bit, direction: GradientDirection) -> Result<(), Error> {
+    let palette = Bitmap::gradient(width, height, start, end, direction)
+        .map_err(|err| External(format!("failed to create gradient image: {err}")))?;
+
+    write_bitmap_file(&palette, &output_file)?;
+
+    info!("Wrote gradient palette to {}", output_file.display());
+    Ok(())
+}
+
+/// Write a `width` x `height` palette of the full color spectrum (see [Bitmap::hsv_palette]) to
+/// the output file.
+pub fn generate_hsv_palette(output_file: PathBuf, width: u32, height: u32) -> Result<(), Error> {
+    let palette = Bitmap::hsv_palette(width, height)
+        .map_err(|err| External(format!("failed to create HSV palette image: {err}")))?;
+
+    write_bitmap_file(&palette, &output_file)?;
+
+    info!("Wrote HSV palette to {}", output_file.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_color;
+    use std::fs;
+
+    #[test]
+    fn generate_gradient_writes_hex_colors_in_file_order() {
+        let (start, end) = (parse_color("#FF0000").unwrap(), parse_color("#0000FF").unwrap());
+        assert_eq!((start.to_string(), end.to_string()), ("#FF0000".to_string(), "#0000FF".to_string()));
+
+        let output_file = std::env::temp_dir().join(format!("generate_gradient_{}.bmp", std::process::id()));
+        let result = generate_gradient(output_file.clone(), 2, 1, start, end, GradientDirection::Horizontal)
+            .and_then(|()| fs::read(&output_file).map_err(|err| External(err.to_string())));
+        fs::remove_file(&output_file).unwrap();
+
+        // The file stores each pixel as blue, green, red, so red is 00 00 FF.
+        assert_eq!(result.unwrap()[54..60], [0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00]);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::error::Error;
+use crate::error::Error::{External, UnexpectedValue};
+use bitmap_rs::{Bitmap, Pixel24Bit};
+
+/// A step in the preprocessing pipeline that is applied to the flag image before it is matched to
+/// the palette.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PreprocessStep {
+    /// Add the amount to every channel (`brightness=<amount>`).
+    Brightness(i16),
+
+    /// Apply gamma correction (`gamma=<gamma>`).
+    Gamma(f64),
+
+    /// Dither the image with the colors of the palette (`dither=floyd-steinberg`).
+    FloydSteinbergDither,
+}
+
+/// Parse a comma-separated preprocessing pipeline, such as
+/// `brightness=20,gamma=2.2,dither=floyd-steinberg`.
+pub fn parse_preprocess_steps(pipeline: &str) -> Result<Vec<PreprocessStep>, Error> {
+    pipeline.split(',')
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(|token| {
+            let Some((name, value)) = token.split_once('=') else {
+                return Err(UnexpectedValue(format!("preprocessing step '{token}' is missing a value (expected <step>=<value>)")));
+            };
+
+            match name.trim() {
+                "brightness" => value.trim().parse::<i16>().ok()
+                    .filter(|amount| (-255..=255).contains(amount))
+                    .map(PreprocessStep::Brightness)
+                    .ok_or_else(|| UnexpectedValue(format!("invalid preprocessing step '{token}': brightness must be an integer from -255 to 255"))),
+
+                "gamma" => value.trim().parse::<f64>().ok()
+                    .filter(|gamma| gamma.is_finite() && *gamma > 0.0)
+                    .map(PreprocessStep::Gamma)
+                    .ok_or_else(|| UnexpectedValue(format!("invalid preprocessing step '{token}': gamma must be a positive number"))),
+
+                "dither" => match value.trim() {
+                    "floyd-steinberg" => Ok(PreprocessStep::FloydSteinbergDither),
+                    _ => Err(UnexpectedValue(format!("invalid preprocessing step '{token}': the supported dithering methods are: floyd-steinberg"))),
+                },
+
+                _ => Err(UnexpectedValue(format!("unknown preprocessing step '{token}' (expected brightness, gamma or dither)"))),
+            }
+        })
+        .collect()
+}
+
+/// Apply the preprocessing steps to the flag image, in order.
+pub fn apply_preprocess_steps(steps: &[PreprocessStep], flag: &mut Bitmap<Pixel24Bit>, palette: &Bitmap<Pixel24Bit>) -> Result<(), Error> {
+    for step in steps {
+        match step {
+            PreprocessStep::Brightness(amount) => flag.adjust_brightness(*amount),
+
+            PreprocessStep::Gamma(gamma) => flag.adjust_gamma(*gamma)
+                .map_err(|err| External(format!("failed to apply gamma correction: {err}")))?,
+
+            PreprocessStep::FloydSteinbergDither => flag.dither_floyd_steinberg(&palette.pixels)
+                .map_err(|err| External(format!("failed to dither the flag image: {err}")))?,
+        }
+
+        debug!("Applied preprocessing step {step:?}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse a pipeline that is expected to fail, and get the error message.
+    fn parse_error(pipeline: &str) -> String {
+        parse_preprocess_steps(pipeline).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn parse_preprocess_steps_reads_steps_in_order() {
+        let steps = parse_preprocess_steps("brightness=20, gamma=2.2,dither=floyd-steinberg").unwrap();
+        assert_eq!(steps, vec![
+            PreprocessStep::Brightness(20),
+            PreprocessStep::Gamma(2.2),
+            PreprocessStep::FloydSteinbergDither,
+        ]);
+
+        assert_eq!(parse_preprocess_steps("brightness=-255,,").unwrap(), vec![PreprocessStep::Brightness(-255)]);
+        assert_eq!(parse_preprocess_steps("").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn parse_preprocess_steps_rejects_unknown_steps() {
+        assert_eq!(
+            parse_error("brightness=20,sharpen=2"),
+            "unexpected value: unknown preprocessing step 'sharpen=2' (expected brightness, gamma or dither)"
+        );
+        assert_eq!(
+            parse_error("gamma"),
+            "unexpected value: preprocessing step 'gamma' is missing a value (expected <step>=<value>)"
+        );
+    }
+
+    #[test]
+    fn parse_preprocess_steps_rejects_invalid_parameters() {
+        assert_eq!(
+            parse_error("brightness=256"),
+            "unexpected value: invalid preprocessing step 'brightness=256': brightness must be an integer from -255 to 255"
+        );
+        assert_eq!(
+            parse_error("brightness=1.5"),
+            "unexpected value: invalid preprocessing step 'brightness=1.5': brightness must be an integer from -255 to 255"
+        );
+        assert_eq!(
+            parse_error("gamma=0"),
+            "unexpected value: invalid preprocessing step 'gamma=0': gamma must be a positive number"
+        );
+        assert_eq!(
+            parse_error("gamma=inf"),
+            "unexpected value: invalid preprocessing step 'gamma=inf': gamma must be a positive number"
+        );
+        assert_eq!(
+            parse_error("dither=ordered"),
+            "unexpected value: invalid preprocessing step 'dither=ordered': the supported dithering methods are: floyd-steinberg"
+        );
+    }
+}
@@ -0,0 +1,50 @@
+/// The name of the Mage Arena process, compared case-insensitively and ignoring spaces and any
+/// `.exe` extension.
+const MAGE_ARENA_PROCESS_NAME: &str = "magearena";
+
+/// Check whether a process name refers to Mage Arena.
+fn is_mage_arena_process_name(name: &str) -> bool {
+    let name: String = name.trim().to_ascii_lowercase().chars().filter(|c| !c.is_whitespace()).collect();
+    name.strip_suffix(".exe").unwrap_or(&name) == MAGE_ARENA_PROCESS_NAME
+}
+
+/// Get the names of the running processes, using `tasklist`.
+#[cfg(windows)]
+fn running_process_names() -> Vec<String> {
+    let Ok(output) = std::process::Command::new("tasklist").args(["/FO", "CSV", "/NH"]).output() else {
+        warn!("could not list the running processes to check whether Mage Arena is running");
+        return vec![];
+    };
+
+    // Each line is a CSV record, starting with the quoted image name.
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.strip_prefix('"')?.split_once('"').map(|(name, _)| name.to_string()))
+        .collect()
+}
+
+/// Get the names of the running processes from `/proc` (e.g., for Mage Arena running under Proton).
+#[cfg(target_os = "linux")]
+fn running_process_names() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        warn!("could not list the running processes to check whether Mage Arena is running");
+        return vec![];
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_str().is_some_and(|name| name.bytes().all(|b| b.is_ascii_digit())))
+        .filter_map(|entry| std::fs::read_to_string(entry.path().join("comm")).ok())
+        .collect()
+}
+
+/// Listing the running processes isn't supported on this platform, so none are reported.
+#[cfg(not(any(windows, target_os = "linux")))]
+fn running_process_names() -> Vec<String> {
+    vec![]
+}
+
+/// Check whether Mage Arena is currently running.
+pub fn is_mage_arena_running() -> bool {
+    running_process_names().iter().any(|name| is_mage_arena_process_name(name))
+}
@@ -0,0 +1,209 @@
+use crate::error::Error;
+use crate::error::Error::{AccessFailure, UnexpectedValue};
+use crate::mage_arena::{FlagStore, MAGE_ARENA_FLAG_KEY_PREFIX, MAGE_ARENA_KEY};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The root key of [MAGE_ARENA_KEY], as it is named in `.reg` files.
+const REG_FILE_ROOT_KEY: &str = "HKEY_CURRENT_USER";
+
+/// The header line of `.reg` files.
+const REG_FILE_HEADER: &str = "Windows Registry Editor Version 5.00";
+
+/// The length beyond which `regedit` wraps the lines of hex values.
+const REG_FILE_LINE_LENGTH: usize = 76;
+
+/// Decode the contents of a `.reg` file, which `regedit` exports as UTF-16LE (with a byte order
+/// mark), but may also be UTF-8.
+fn decode_reg_file(bytes: &[u8]) -> Result<String, Error> {
+    if let Some(utf16_bytes) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        let (code_units, []) = utf16_bytes.as_chunks::<2>() else {
+            return Err(UnexpectedValue("UTF-16 .reg file has an odd number of bytes".to_string()));
+        };
+
+        return String::from_utf16(&code_units.iter().map(|&unit| u16::from_le_bytes(unit)).collect::<Vec<u16>>())
+            .map_err(|err| UnexpectedValue(format!(".reg file is not valid UTF-16: {err}")));
+    }
+
+    let utf8_bytes = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(bytes);
+    String::from_utf8(utf8_bytes.to_vec())
+        .map_err(|err| UnexpectedValue(format!(".reg file is not valid UTF-8: {err}")))
+}
+
+/// Split the contents of a `.reg` file into logical lines, joining any lines that are wrapped with
+/// a trailing backslash onto the following line.
+fn logical_lines(contents: &str) -> Vec<String> {
+    let mut lines = vec![];
+    let mut current_line = String::new();
+
+    for line in contents.lines() {
+        let line = if current_line.is_empty() { line.trim_end() } else { line.trim() };
+
+        match line.strip_suffix('\\') {
+            Some(wrapped_line) => current_line.push_str(wrapped_line),
+            None => {
+                current_line.push_str(line);
+                lines.push(std::mem::take(&mut current_line));
+            }
+        }
+    }
+
+    if !current_line.is_empty() {
+        lines.push(current_line);
+    }
+
+    lines
+}
+
+/// Decode the bytes of a `hex:` or `hex(<type>):` value in a `.reg` file.
+fn parse_hex_value(value: &str) -> Result<Vec<u8>, Error> {
+    let hex_bytes = value.strip_prefix("hex:")
+        .or_else(|| value.strip_prefix("hex(").and_then(|value| value.split_once("):")).map(|(_, bytes)| bytes))
+        .ok_or_else(|| UnexpectedValue(format!("expected a hex value in the .reg file, got: {}", value.chars().take(16).collect::<String>())))?;
+
+    hex_bytes.split(',')
+        .map(str::trim)
+        .filter(|byte| !byte.is_empty())
+        .map(|byte| u8::from_str_radix(byte, 16)
+            .map_err(|err| UnexpectedValue(format!("invalid byte ({byte}) in .reg file hex value: {err}"))))
+        .collect()
+}
+
+/// Parse the contents of a `.reg` file, returning the raw flag data from the value under the Mage
+/// Arena key that starts with [MAGE_ARENA_FLAG_KEY_PREFIX].
+pub fn parse_reg_file_flag(contents: &str) -> Result<Vec<u8>, Error> {
+    let mage_arena_section = format!(r"[{REG_FILE_ROOT_KEY}\{MAGE_ARENA_KEY}]");
+    let mut is_in_mage_arena_section = false;
+
+    for line in logical_lines(contents) {
+        if line.starts_with('[') {
+            is_in_mage_arena_section = line.eq_ignore_ascii_case(&mage_arena_section);
+            continue;
+        }
+
+        if !is_in_mage_arena_section {
+            continue;
+        }
+
+        let Some((name, value)) = line.strip_prefix('"').and_then(|line| line.split_once("\"=")) else {
+            continue;
+        };
+
+        if name.starts_with(MAGE_ARENA_FLAG_KEY_PREFIX) {
+            return parse_hex_value(value.trim());
+        }
+    }
+
+    Err(AccessFailure(format!(
+        r"failed to find flag grid value in .reg file (expected value with prefix {MAGE_ARENA_FLAG_KEY_PREFIX} under {REG_FILE_ROOT_KEY}\{MAGE_ARENA_KEY})"
+    )))
+}
+
+/// Format the raw flag data as the contents of a `.reg` file that sets the named flag grid value
+/// under the Mage Arena key at `key_path` (relative to `HKEY_CURRENT_USER`), with the lines of the
+/// hex value wrapped in the same way as `regedit`.
+pub fn format_reg_file_flag(key_path: &str, value_name: &str, raw_data: &[u8]) -> String {
+    let mut value_line = format!("\"{value_name}\"=hex:");
+    let mut lines = vec![];
+
+    for (i, byte) in raw_data.iter().enumerate() {
+        value_line.push_str(&format!("{byte:02x}"));
+
+        if i + 1 < raw_data.len() {
+            value_line.push(',');
+
+            if value_line.len() > REG_FILE_LINE_LENGTH {
+                value_line.push('\\');
+                lines.push(std::mem::replace(&mut value_line, "  ".to_string()));
+            }
+        }
+    }
+    lines.push(value_line);
+
+    format!(
+        "{REG_FILE_HEADER}\r\n\r\n[{REG_FILE_ROOT_KEY}\\{key_path}]\r\n{}\r\n\r\n",
+        lines.join("\r\n")
+    )
+}
+
+/// Write the raw flag data to a `.reg` file that can be imported (e.g., by double-clicking it) to
+/// set the named flag grid value under the Mage Arena key at `key_path`.
+///
+/// The file is encoded as UTF-16LE with a byte order mark, like those exported by `regedit`.
+pub fn export_reg_file(output_file: &Path, key_path: &str, value_name: &str, raw_data: &[u8]) -> Result<(), Error> {
+    let bytes: Vec<u8> = [0xFF, 0xFE].into_iter()
+        .chain(format_reg_file_flag(key_path, value_name, raw_data).encode_utf16().flat_map(u16::to_le_bytes))
+        .collect();
+
+    fs::write(output_file, bytes)
+        .map_err(|err| AccessFailure(format!("failed to write .reg file ({}): {err}", output_file.display())))
+}
+
+/// Reads the flag from a `.reg` file exported from another registry with `regedit`.
+pub struct RegFileFlagStore {
+    path: PathBuf,
+}
+
+impl RegFileFlagStore {
+    pub fn new(path: PathBuf) -> Self {
+        RegFileFlagStore { path }
+    }
+}
+
+impl FlagStore for RegFileFlagStore {
+    fn read_raw(&self) -> Result<Vec<u8>, Error> {
+        let bytes = fs::read(&self.path)
+            .map_err(|err| AccessFailure(format!("failed to read .reg file ({}): {err}", self.path.display())))?;
+
+        parse_reg_file_flag(&decode_reg_file(&bytes)?)
+    }
+
+    fn write_raw(&self, _data: &[u8]) -> Result<(), Error> {
+        Err(AccessFailure("writing the flag to a .reg file is not supported".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_EXPORT: &str = r#"Windows Registry Editor Version 5.00
+
+[HKEY_CURRENT_USER\Software\jrsjams]
+
+[HKEY_CURRENT_USER\Software\jrsjams\MageArena]
+"unity.player_sessionid_h1234"=hex:32,00
+"flagGrid_h2868227452"=hex:30,2e,35,30,3a,30,2e,32,35,2c,30,2e,30,30,3a,31,2e,\
+  30,30,00
+"Screenmanager Fullscreen mode_h3630240806"=dword:00000001
+
+[HKEY_CURRENT_USER\Software\jrsjams\MageArena\Unity]
+"flagGrid_h1"=hex:00
+"#;
+
+    #[test]
+    fn parse_reg_file_flag_reads_sample_export() {
+        let raw_data = parse_reg_file_flag(SAMPLE_EXPORT).unwrap();
+        assert_eq!(raw_data, b"0.50:0.25,0.00:1.00\0");
+    }
+
+    #[test]
+    fn decode_reg_file_reads_utf16_export() {
+        let bytes: Vec<u8> = [0xFF, 0xFE].into_iter()
+            .chain(SAMPLE_EXPORT.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+
+        let raw_data = parse_reg_file_flag(&decode_reg_file(&bytes).unwrap()).unwrap();
+        assert_eq!(raw_data, b"0.50:0.25,0.00:1.00\0");
+    }
+
+    #[test]
+    fn format_reg_file_flag_round_trips() {
+        let raw_data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let contents = format_reg_file_flag(MAGE_ARENA_KEY, "flagGrid_h2868227452", &raw_data);
+
+        assert!(contents.starts_with("Windows Registry Editor Version 5.00\r\n"));
+        assert!(contents.lines().all(|line| line.len() <= 80), "line too long in:\n{contents}");
+        assert_eq!(parse_reg_file_flag(&contents).unwrap(), raw_data);
+    }
+}
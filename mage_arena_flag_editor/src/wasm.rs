@@ -0,0 +1,53 @@
+use crate::error::Error;
+use crate::error::Error::External;
+use crate::flag::{encode_flag, EncodeOptions, FlagDimensions, MAGE_ARENA_FLAG_PIXEL_SIZE};
+use bitmap_rs::{Bitmap, Pixel24Bit};
+use wasm_bindgen::prelude::*;
+
+/// Parse the bytes of a bitmap file.
+fn parse_bitmap(bytes: &[u8], name: &str) -> Result<Bitmap<Pixel24Bit>, Error> {
+    Bitmap::new_from_bytes(bytes.to_vec())
+        .map_err(|err| External(format!("failed to parse bitmap data in {name}: {err}")))
+}
+
+/// Convert a flag image to the flag data that `write` would store in the registry, given the
+/// bytes of the palette and flag bitmap files and the dimensions of the flag.
+///
+/// The flag data is the comma-delimited list of palette coordinates, including the trailing null
+/// character.
+#[wasm_bindgen(js_name = flagToRegistryString)]
+pub fn flag_to_registry_string(palette: &[u8], flag: &[u8], width: usize, height: usize) -> Result<String, JsError> {
+    let palette = parse_bitmap(palette, "palette file")?;
+    let flag = parse_bitmap(flag, "flag file")?;
+    let dimensions = FlagDimensions::new(width, height, MAGE_ARENA_FLAG_PIXEL_SIZE)?;
+
+    Ok(encode_flag(flag, &palette, &dimensions, &EncodeOptions::default())?)
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    // Run with `wasm-pack test --headless --firefox --features wasm`.
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn flag_to_registry_string_maps_flag_to_palette() {
+        let palette = Bitmap::new_from_pixels(2, 1, vec![
+            Pixel24Bit { red: 0, green: 0, blue: 0 },
+            Pixel24Bit { red: 255, green: 255, blue: 255 },
+        ]).unwrap();
+
+        // A 2x2 flag with a black top row and a near-white bottom row.
+        let flag = Bitmap::new_from_pixels(2, 2, vec![
+            Pixel24Bit { red: 250, green: 250, blue: 250 },
+            Pixel24Bit { red: 240, green: 255, blue: 250 },
+            Pixel24Bit { red: 10, green: 0, blue: 0 },
+            Pixel24Bit { red: 0, green: 5, blue: 0 },
+        ]).unwrap();
+
+        let raw_data = flag_to_registry_string(&palette.to_bytes(), &flag.to_bytes(), 2, 2).unwrap();
+        assert_eq!(raw_data, "0.50:0.00,0.00:0.00,0.50:0.00,0.00:0.00\0");
+    }
+}